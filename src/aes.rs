@@ -1,101 +1,141 @@
-use super::pkcs7;
-
-pub struct AesCtrIter {
-    key_schedule: Vec<u32>,
-    block: Block,
-    nonce: u64,
-    counter: u64,
-    index: usize,
+use super::modes::{self, BlockCipher, Ctr, ModeError};
+
+#[derive(Clone)]
+pub struct Aes {
+    encrypt_schedule: Vec<u32>,
+    decrypt_schedule: Vec<u32>,
 }
 
-impl AesCtrIter {
-    pub fn new(key: &AesKey, nonce: u64) -> Self {
+impl Aes {
+    pub fn new(key: &AesKey) -> Self {
+        let encrypt_schedule = key.schedule();
+        let decrypt_schedule = inv_key_schedule(&encrypt_schedule);
+
         Self {
-            key_schedule: key.schedule(),
-            block: Block([0; 16]),
-            nonce,
-            counter: 0,
-            index: 16,
+            encrypt_schedule,
+            decrypt_schedule,
         }
     }
 
-    fn make_block(&mut self) {
-        let mut block = Block([0; 16]);
+    pub fn ctr_iter(&self, nonce: u64) -> AesCtrIter {
+        AesCtrIter(Ctr::with_nonce(self.clone(), nonce))
+    }
 
-        (&mut block.0[..8]).copy_from_slice(&self.nonce.to_le_bytes());
-        (&mut block.0[8..]).copy_from_slice(&self.counter.to_le_bytes());
+    pub fn ctr<I: AsRef<[u8]>>(&self, input: I, nonce: u64) -> Vec<u8> {
+        modes::ctr(self, input, nonce)
+    }
 
-        self.block = cipher(block, &self.key_schedule);
-        self.counter += 1;
-        self.index = 0;
+    pub fn gcm_encrypt<I: AsRef<[u8]>, V: AsRef<[u8]>, A: AsRef<[u8]>>(
+        &self,
+        input: I,
+        iv: V,
+        associated_data: A,
+        tag_length: usize,
+    ) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+        Ok(modes::gcm_encrypt(
+            self,
+            input,
+            iv,
+            associated_data,
+            tag_length,
+        )?)
     }
-}
 
-impl Iterator for AesCtrIter {
-    type Item = u8;
+    pub fn gcm_decrypt<I: AsRef<[u8]>, V: AsRef<[u8]>, A: AsRef<[u8]>, T: AsRef<[u8]>>(
+        &self,
+        input: I,
+        iv: V,
+        associated_data: A,
+        tag: T,
+    ) -> Result<Vec<u8>, AesError> {
+        Ok(modes::gcm_decrypt(self, input, iv, associated_data, tag)?)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index > 15 {
-            self.make_block();
-        }
+    pub fn cbc_encrypt<I: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        input: I,
+        iv: V,
+    ) -> Result<Vec<u8>, AesError> {
+        Ok(modes::cbc_encrypt(self, input, iv)?)
+    }
+
+    pub fn cbc_decrypt<I: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        input: I,
+        iv: V,
+    ) -> Result<Vec<u8>, AesError> {
+        Ok(modes::cbc_decrypt(self, input, iv)?)
+    }
 
-        let byte = self.block.0[self.index];
-        self.index += 1;
+    pub fn ecb_encrypt<I: AsRef<[u8]>>(&self, input: I) -> Vec<u8> {
+        modes::ecb_encrypt(self, input)
+    }
 
-        Some(byte)
+    pub fn ecb_decrypt<I: AsRef<[u8]>>(&self, input: I) -> Result<Vec<u8>, AesError> {
+        Ok(modes::ecb_decrypt(self, input)?)
     }
 }
 
-pub fn aes_ctr<I: AsRef<[u8]>>(input: I, key: &AesKey, nonce: u64) -> Vec<u8> {
-    let mut output = Vec::new();
+impl BlockCipher for Aes {
+    const BLOCK_SIZE: usize = 16;
 
-    let key_schedule = key.schedule();
-
-    let mut gen_block = Block([0; 16]);
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let state = Block(block.try_into().unwrap());
+        block.copy_from_slice(&cipher(state, &self.encrypt_schedule).0);
+    }
 
-    for (i, block) in input.as_ref().chunks(16).enumerate() {
-        (&mut gen_block.0[..8]).copy_from_slice(&nonce.to_le_bytes());
-        (&mut gen_block.0[8..]).copy_from_slice(&(i as u64).to_le_bytes());
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let state = Block(block.try_into().unwrap());
+        block.copy_from_slice(&inv_cipher(state, &self.decrypt_schedule).0);
+    }
+}
 
-        gen_block = cipher(gen_block, &key_schedule);
+pub struct AesCtrIter(Ctr<Aes>);
 
-        let mut xor_block = Block([0; 16]);
-        let input_block_len = block.len();
-        (&mut xor_block.0[..input_block_len]).copy_from_slice(block);
+impl AesCtrIter {
+    pub fn new(key: &AesKey, nonce: u64) -> Self {
+        Aes::new(key).ctr_iter(nonce)
+    }
+}
 
-        xor_block.xor_inplace(&gen_block);
+impl Iterator for AesCtrIter {
+    type Item = u8;
 
-        output.extend_from_slice(&xor_block.0[..input_block_len]);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
     }
+}
 
-    output
+pub fn aes_ctr<I: AsRef<[u8]>>(input: I, key: &AesKey, nonce: u64) -> Vec<u8> {
+    Aes::new(key).ctr(input, nonce)
 }
 
-pub fn aes_cbc_encrypt<I: AsRef<[u8]>, V: AsRef<[u8]>>(
+pub fn aes_gcm_encrypt<I: AsRef<[u8]>, V: AsRef<[u8]>, A: AsRef<[u8]>>(
     input: I,
     key: &AesKey,
     iv: V,
-) -> Result<Vec<u8>, AesError> {
-    if iv.as_ref().len() != 16 {
-        return Err(AesError::WrongSizeIv);
-    }
-
-    let mut iv_block: [u8; 16] = [0; 16];
-    iv_block.copy_from_slice(iv.as_ref());
-
-    let key_schedule = key.schedule();
-
-    let mut last_cipher_block = Block(iv_block);
-    let mut ciphertext = Vec::new();
-
-    for block in BlockIter::padded(input.as_ref()) {
-        let block = block.xor(&last_cipher_block);
-        last_cipher_block = cipher(block, &key_schedule);
+    associated_data: A,
+    tag_length: usize,
+) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+    Aes::new(key).gcm_encrypt(input, iv, associated_data, tag_length)
+}
 
-        ciphertext.extend_from_slice(last_cipher_block.as_ref());
-    }
+pub fn aes_gcm_decrypt<I: AsRef<[u8]>, V: AsRef<[u8]>, A: AsRef<[u8]>, T: AsRef<[u8]>>(
+    input: I,
+    key: &AesKey,
+    iv: V,
+    associated_data: A,
+    tag: T,
+) -> Result<Vec<u8>, AesError> {
+    Aes::new(key).gcm_decrypt(input, iv, associated_data, tag)
+}
 
-    Ok(ciphertext)
+pub fn aes_cbc_encrypt<I: AsRef<[u8]>, V: AsRef<[u8]>>(
+    input: I,
+    key: &AesKey,
+    iv: V,
+) -> Result<Vec<u8>, AesError> {
+    Aes::new(key).cbc_encrypt(input, iv)
 }
 
 pub fn aes_cbc_decrypt<I: AsRef<[u8]>, V: AsRef<[u8]>>(
@@ -103,110 +143,15 @@ pub fn aes_cbc_decrypt<I: AsRef<[u8]>, V: AsRef<[u8]>>(
     key: &AesKey,
     iv: V,
 ) -> Result<Vec<u8>, AesError> {
-    if input.as_ref().len() % 16 != 0 {
-        return Err(AesError::IrregularDecryptLength);
-    }
-
-    let mut iv_block: [u8; 16] = [0; 16];
-    iv_block.copy_from_slice(iv.as_ref());
-
-    let key_schedule = key.schedule();
-
-    let mut last_cipher_block = Block(iv_block);
-    let mut cleartext = Vec::new();
-
-    for block in BlockIter::exact(input.as_ref()) {
-        let inv_cipher_block = inv_cipher(block, &key_schedule);
-        let clear_block = inv_cipher_block.xor(&last_cipher_block);
-
-        last_cipher_block = block;
-
-        cleartext.extend_from_slice(clear_block.as_ref());
-    }
-
-    let cleartext_end = pkcs7::unpad_length(&cleartext);
-
-    if cleartext_end == cleartext.len() {
-        return Err(AesError::PaddingError);
-    }
-
-    cleartext.truncate(cleartext_end);
-
-    Ok(cleartext)
+    Aes::new(key).cbc_decrypt(input, iv)
 }
 
 pub fn aes_ecb_encrypt<I: AsRef<[u8]>>(input: I, key: &AesKey) -> Vec<u8> {
-    let key_schedule = key.schedule();
-
-    BlockIter::padded(input.as_ref())
-        .into_iter()
-        .map(|block| cipher(block, &key_schedule))
-        .collect()
+    Aes::new(key).ecb_encrypt(input)
 }
 
 pub fn aes_ecb_decrypt<I: AsRef<[u8]>>(input: I, key: &AesKey) -> Result<Vec<u8>, AesError> {
-    if input.as_ref().len() % 16 != 0 {
-        return Err(AesError::IrregularDecryptLength);
-    }
-
-    let key_schedule = key.schedule();
-
-    Ok(BlockIter::exact(input.as_ref())
-        .into_iter()
-        .map(|block| inv_cipher(block, &key_schedule))
-        .collect())
-}
-
-struct BlockIter<'a> {
-    create_padded_block: bool,
-    chunks: std::slice::ChunksExact<'a, u8>,
-}
-
-impl<'a> BlockIter<'a> {
-    fn padded(input: &'a [u8]) -> Self {
-        Self {
-            create_padded_block: true,
-            chunks: input.chunks_exact(16),
-        }
-    }
-
-    fn exact(input: &'a [u8]) -> Self {
-        Self {
-            create_padded_block: false,
-            chunks: input.chunks_exact(16),
-        }
-    }
-}
-
-impl<'a> Iterator for BlockIter<'a> {
-    type Item = Block;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match (self.chunks.next(), self.create_padded_block) {
-            (Some(chunk), _) => {
-                let mut block: [u8; 16] = [0; 16];
-                block.copy_from_slice(chunk);
-
-                Some(Block(block))
-            }
-
-            (None, true) => {
-                self.create_padded_block = false;
-
-                let rest = self.chunks.remainder();
-                let bytes_to_copy = rest.len();
-
-                let mut block: [u8; 16] = [0; 16];
-                (&mut block[0..bytes_to_copy]).copy_from_slice(&rest);
-
-                pkcs7::pad_inplace(&mut block, bytes_to_copy);
-
-                Some(Block(block))
-            }
-
-            (None, false) => None,
-        }
-    }
+    Aes::new(key).ecb_decrypt(input)
 }
 
 #[derive(Debug)]
@@ -214,7 +159,23 @@ pub enum AesError {
     NonstandardKeyLength,
     IrregularDecryptLength,
     WrongSizeIv,
+    WrongSizeTag,
     PaddingError,
+    AuthenticationFailed,
+    InvalidCounterLayout,
+}
+
+impl From<ModeError> for AesError {
+    fn from(value: ModeError) -> Self {
+        match value {
+            ModeError::IrregularDecryptLength => AesError::IrregularDecryptLength,
+            ModeError::WrongSizeIv => AesError::WrongSizeIv,
+            ModeError::WrongSizeTag => AesError::WrongSizeTag,
+            ModeError::PaddingError => AesError::PaddingError,
+            ModeError::AuthenticationFailed => AesError::AuthenticationFailed,
+            ModeError::InvalidCounterLayout => AesError::InvalidCounterLayout,
+        }
+    }
 }
 
 pub enum AesKey {
@@ -376,40 +337,6 @@ impl Block {
             *self.at(row, 3) = xmul(a, 0x0b) ^ xmul(b, 0x0d) ^ xmul(c, 0x09) ^ xmul(d, 0x0e);
         }
     }
-
-    fn xor(&self, rhs: &Self) -> Self {
-        let mut block: [u8; 16] = [0; 16];
-
-        for (slot, (a, b)) in block.iter_mut().zip(self.0.iter().zip(rhs.0.iter())) {
-            *slot = a ^ b;
-        }
-
-        Self(block)
-    }
-
-    fn xor_inplace(&mut self, rhs: &Self) {
-        for (a, b) in self.0.iter_mut().zip(rhs.0.iter()) {
-            *a ^= b;
-        }
-    }
-}
-
-impl AsRef<[u8]> for Block {
-    fn as_ref(&self) -> &[u8] {
-        &self.0
-    }
-}
-
-impl FromIterator<Block> for Vec<u8> {
-    fn from_iter<T: IntoIterator<Item = Block>>(iter: T) -> Self {
-        let mut vec = Vec::new();
-
-        for block in iter.into_iter() {
-            vec.extend_from_slice(&block.0);
-        }
-
-        vec
-    }
 }
 
 fn xtimes(byte: u8) -> u8 {
@@ -442,21 +369,21 @@ fn cipher(mut state: Block, key_schedule: &[u32]) -> Block {
     state
 }
 
-fn inv_cipher(mut state: Block, key_schedule: &[u32]) -> Block {
-    let rounds = key_schedule.len() / 4 - 1;
+fn inv_cipher(mut state: Block, inv_key_schedule: &[u32]) -> Block {
+    let rounds = inv_key_schedule.len() / 4 - 1;
 
-    state.add_round_key(&key_schedule[4 * rounds..4 * rounds + 4]);
+    state.add_round_key(&inv_key_schedule[0..4]);
 
-    for round in (1..rounds).rev() {
-        state.inv_shift_rows();
+    for round in 1..=rounds - 1 {
         state.inv_sub_bytes();
-        state.add_round_key(&key_schedule[4 * round..4 * round + 4]);
+        state.inv_shift_rows();
         state.inv_mix_columns();
+        state.add_round_key(&inv_key_schedule[4 * round..4 * round + 4]);
     }
 
-    state.inv_shift_rows();
     state.inv_sub_bytes();
-    state.add_round_key(&key_schedule[0..4]);
+    state.inv_shift_rows();
+    state.add_round_key(&inv_key_schedule[4 * rounds..4 * rounds + 4]);
 
     state
 }
@@ -564,3 +491,91 @@ fn key_expansion(key: &[u8]) -> Vec<u32> {
 
     key_words
 }
+
+fn inv_key_schedule(key_schedule: &[u32]) -> Vec<u32> {
+    let rounds = key_schedule.len() / 4 - 1;
+
+    let mut inv_key_words = Vec::with_capacity(key_schedule.len());
+
+    for round in (0..=rounds).rev() {
+        let round_key = &key_schedule[4 * round..4 * round + 4];
+
+        if round == 0 || round == rounds {
+            inv_key_words.extend_from_slice(round_key);
+            continue;
+        }
+
+        let mut block = Block([0; 16]);
+
+        for (bytes, word) in block.0.chunks_exact_mut(4).zip(round_key.iter()) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        block.inv_mix_columns();
+
+        inv_key_words.extend(
+            block
+                .0
+                .chunks_exact(4)
+                .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap())),
+        );
+    }
+
+    inv_key_words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &str = "00112233445566778899aabbccddeeff";
+
+    /* FIPS-197 Appendix C */
+    const VECTORS: [(&str, &str); 3] = [
+        (
+            "000102030405060708090a0b0c0d0e0f",
+            "69c4e0d86a7b0430d8cdb78070b4c55a",
+        ),
+        (
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "dda97ca4864cdfe06eaf70a0ec0d7191",
+        ),
+        (
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "8ea2b7ca516745bfeafc49904b496089",
+        ),
+    ];
+
+    #[test]
+    fn fips197_known_answers() {
+        for (key, ciphertext) in VECTORS {
+            let aes = Aes::new(&AesKey::from(&hex::decode(key).unwrap()).unwrap());
+
+            let mut block = hex::decode(PLAINTEXT).unwrap();
+            aes.encrypt_block(&mut block);
+            assert_eq!(hex::encode(&block), ciphertext);
+
+            aes.decrypt_block(&mut block);
+            assert_eq!(hex::encode(&block), PLAINTEXT);
+
+            /* Decrypt the published ciphertext directly, not just our own output */
+            let mut block = hex::decode(ciphertext).unwrap();
+            aes.decrypt_block(&mut block);
+            assert_eq!(hex::encode(&block), PLAINTEXT);
+        }
+    }
+
+    #[test]
+    fn key_lengths() {
+        for length in [16, 24, 32] {
+            assert!(AesKey::from(&vec![0; length]).is_ok());
+        }
+
+        for length in [0, 15, 17, 31, 33] {
+            assert!(matches!(
+                AesKey::from(&vec![0; length]),
+                Err(AesError::NonstandardKeyLength)
+            ));
+        }
+    }
+}
@@ -0,0 +1,686 @@
+use super::base64::{base64_to_bytes, bytes_to_base64};
+use super::blake2::{blake2b_digest, Blake2b};
+use super::constant_time;
+use super::digest::Digest;
+use super::urandom;
+use std::fmt;
+use std::str::FromStr;
+
+const ARGON2_VERSION: u32 = 0x13;
+const BLOCK_WORDS: usize = 128;
+const SYNC_POINTS: usize = 4;
+
+/* In KiB; 2 GiB is the largest cost RFC 9106 recommends */
+pub const DEFAULT_MAX_MEMORY: u32 = 1 << 21;
+
+type Block = [u64; BLOCK_WORDS];
+
+#[derive(Debug)]
+pub enum Argon2Error {
+    MemoryTooSmall,
+    MemoryTooLarge,
+    NoIterations,
+    NoParallelism,
+    OutputTooShort,
+    SaltTooShort,
+    UnsupportedVersion,
+    InvalidPhcString,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Argon2Variant {
+    Argon2i,
+    Argon2id,
+}
+
+impl Argon2Variant {
+    fn type_code(self) -> u32 {
+        match self {
+            Argon2Variant::Argon2i => 1,
+            Argon2Variant::Argon2id => 2,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Argon2Variant::Argon2i => "argon2i",
+            Argon2Variant::Argon2id => "argon2id",
+        }
+    }
+}
+
+/* Memory in KiB, at least 8 per lane */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Argon2Params {
+    memory: u32,
+    iterations: u32,
+    parallelism: u32,
+    output_length: usize,
+}
+
+impl Argon2Params {
+    pub fn new(
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+        output_length: usize,
+    ) -> Result<Self, Argon2Error> {
+        if parallelism == 0 || parallelism >= 1 << 24 {
+            return Err(Argon2Error::NoParallelism);
+        }
+
+        if iterations == 0 {
+            return Err(Argon2Error::NoIterations);
+        }
+
+        if (memory as u64) < 8 * parallelism as u64 {
+            return Err(Argon2Error::MemoryTooSmall);
+        }
+
+        if output_length < 4 {
+            return Err(Argon2Error::OutputTooShort);
+        }
+
+        Ok(Self {
+            memory,
+            iterations,
+            parallelism,
+            output_length,
+        })
+    }
+
+    pub fn memory(&self) -> u32 {
+        self.memory
+    }
+
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    pub fn parallelism(&self) -> u32 {
+        self.parallelism
+    }
+
+    pub fn output_length(&self) -> usize {
+        self.output_length
+    }
+}
+
+pub fn argon2(
+    variant: Argon2Variant,
+    params: &Argon2Params,
+    password: &[u8],
+    salt: &[u8],
+) -> Result<Vec<u8>, Argon2Error> {
+    argon2_with_secret(variant, params, password, salt, &[], &[])
+}
+
+/* RFC 9106, version 0x13, with the optional secret K and associated data X */
+pub fn argon2_with_secret(
+    variant: Argon2Variant,
+    params: &Argon2Params,
+    password: &[u8],
+    salt: &[u8],
+    secret: &[u8],
+    associated_data: &[u8],
+) -> Result<Vec<u8>, Argon2Error> {
+    if salt.len() < 8 {
+        return Err(Argon2Error::SaltTooShort);
+    }
+
+    let lanes = params.parallelism as usize;
+
+    /* Round the block count down to a multiple of 4p */
+    let block_count = params.memory as usize / (SYNC_POINTS * lanes) * SYNC_POINTS * lanes;
+    let lane_length = block_count / lanes;
+    let segment_length = lane_length / SYNC_POINTS;
+
+    let mut hasher = Blake2b::new();
+
+    for value in [
+        params.parallelism,
+        params.output_length as u32,
+        params.memory,
+        params.iterations,
+        ARGON2_VERSION,
+        variant.type_code(),
+    ] {
+        hasher.update(&value.to_le_bytes());
+    }
+
+    for field in [password, salt, secret, associated_data] {
+        hasher.update(&(field.len() as u32).to_le_bytes());
+        hasher.update(field);
+    }
+
+    let h0 = hasher.finalize();
+
+    let mut memory = vec![[0u64; BLOCK_WORDS]; block_count];
+
+    for lane in 0..lanes {
+        for column in 0..2 {
+            let mut seed = h0.to_vec();
+            seed.extend_from_slice(&(column as u32).to_le_bytes());
+            seed.extend_from_slice(&(lane as u32).to_le_bytes());
+
+            memory[lane * lane_length + column] = bytes_to_block(&variable_hash(&seed, 1024));
+        }
+    }
+
+    for pass in 0..params.iterations as usize {
+        for slice in 0..SYNC_POINTS {
+            for lane in 0..lanes {
+                let position = Position { pass, lane, slice };
+
+                fill_segment(
+                    &mut memory,
+                    &position,
+                    params,
+                    variant,
+                    block_count,
+                    lane_length,
+                    segment_length,
+                );
+            }
+        }
+    }
+
+    let mut last = memory[lane_length - 1];
+
+    for lane in 1..lanes {
+        for (word, other) in last
+            .iter_mut()
+            .zip(&memory[lane * lane_length + lane_length - 1])
+        {
+            *word ^= other;
+        }
+    }
+
+    Ok(variable_hash(&block_to_bytes(&last), params.output_length))
+}
+
+/* A random 16-byte salt and a PHC string such as $argon2id$v=19$m=65536,t=3,p=4$salt$hash */
+pub fn hash_password(
+    variant: Argon2Variant,
+    params: &Argon2Params,
+    password: &[u8],
+) -> Result<String, Argon2Error> {
+    let salt = urandom::bytes(16);
+    let hash = argon2(variant, params, password, &salt)?;
+
+    Ok(PhcString {
+        variant,
+        params: *params,
+        salt,
+        hash,
+    }
+    .to_string())
+}
+
+pub fn verify_password(phc: &str, password: &[u8]) -> Result<bool, Argon2Error> {
+    verify_password_with_limit(phc, password, DEFAULT_MAX_MEMORY)
+}
+
+/* The PHC string is untrusted input, so refuse to allocate more than max_memory KiB for it */
+pub fn verify_password_with_limit(
+    phc: &str,
+    password: &[u8],
+    max_memory: u32,
+) -> Result<bool, Argon2Error> {
+    let phc: PhcString = phc.parse()?;
+
+    if phc.params.memory > max_memory {
+        return Err(Argon2Error::MemoryTooLarge);
+    }
+
+    let hash = argon2(phc.variant, &phc.params, password, &phc.salt)?;
+
+    Ok(constant_time::eq(&hash, &phc.hash))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PhcString {
+    pub variant: Argon2Variant,
+    pub params: Argon2Params,
+    pub salt: Vec<u8>,
+    pub hash: Vec<u8>,
+}
+
+impl fmt::Display for PhcString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "${}$v={}$m={},t={},p={}${}${}",
+            self.variant.name(),
+            ARGON2_VERSION,
+            self.params.memory,
+            self.params.iterations,
+            self.params.parallelism,
+            phc_base64(&self.salt),
+            phc_base64(&self.hash),
+        )
+    }
+}
+
+impl FromStr for PhcString {
+    type Err = Argon2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split('$').collect();
+
+        let [empty, name, version, parameters, salt, hash] = fields[..] else {
+            return Err(Argon2Error::InvalidPhcString);
+        };
+
+        if !empty.is_empty() {
+            return Err(Argon2Error::InvalidPhcString);
+        }
+
+        let variant = match name {
+            "argon2i" => Argon2Variant::Argon2i,
+            "argon2id" => Argon2Variant::Argon2id,
+            _ => return Err(Argon2Error::InvalidPhcString),
+        };
+
+        if version != format!("v={}", ARGON2_VERSION) {
+            return Err(Argon2Error::UnsupportedVersion);
+        }
+
+        let mut values = [None; 3];
+
+        for parameter in parameters.split(',') {
+            let (key, value) = parameter
+                .split_once('=')
+                .ok_or(Argon2Error::InvalidPhcString)?;
+
+            let slot = match key {
+                "m" => 0,
+                "t" => 1,
+                "p" => 2,
+                _ => return Err(Argon2Error::InvalidPhcString),
+            };
+
+            if values[slot].is_some() {
+                return Err(Argon2Error::InvalidPhcString);
+            }
+
+            values[slot] = Some(
+                value
+                    .parse::<u32>()
+                    .map_err(|_| Argon2Error::InvalidPhcString)?,
+            );
+        }
+
+        let [Some(memory), Some(iterations), Some(parallelism)] = values else {
+            return Err(Argon2Error::InvalidPhcString);
+        };
+
+        let salt = phc_base64_decode(salt)?;
+        let hash = phc_base64_decode(hash)?;
+
+        let params = Argon2Params::new(memory, iterations, parallelism, hash.len())?;
+
+        Ok(Self {
+            variant,
+            params,
+            salt,
+            hash,
+        })
+    }
+}
+
+struct Position {
+    pass: usize,
+    lane: usize,
+    slice: usize,
+}
+
+fn fill_segment(
+    memory: &mut [Block],
+    position: &Position,
+    params: &Argon2Params,
+    variant: Argon2Variant,
+    block_count: usize,
+    lane_length: usize,
+    segment_length: usize,
+) {
+    let Position { pass, lane, slice } = *position;
+
+    /* Argon2id only uses data-independent addressing for the first half of the first pass */
+    let data_independent = match variant {
+        Argon2Variant::Argon2i => true,
+        Argon2Variant::Argon2id => pass == 0 && slice < SYNC_POINTS / 2,
+    };
+
+    let mut address_input = [0u64; BLOCK_WORDS];
+    let mut addresses = [0u64; BLOCK_WORDS];
+
+    if data_independent {
+        address_input[0] = pass as u64;
+        address_input[1] = lane as u64;
+        address_input[2] = slice as u64;
+        address_input[3] = block_count as u64;
+        address_input[4] = params.iterations as u64;
+        address_input[5] = variant.type_code() as u64;
+    }
+
+    /* The first two blocks of each lane are already filled */
+    let start = if pass == 0 && slice == 0 { 2 } else { 0 };
+
+    if data_independent && start == 2 {
+        next_addresses(&mut address_input, &mut addresses);
+    }
+
+    for index in start..segment_length {
+        let column = slice * segment_length + index;
+        let current = lane * lane_length + column;
+        let previous = if column == 0 {
+            current + lane_length - 1
+        } else {
+            current - 1
+        };
+
+        let pseudo_random = if data_independent {
+            if index % BLOCK_WORDS == 0 {
+                next_addresses(&mut address_input, &mut addresses);
+            }
+
+            addresses[index % BLOCK_WORDS]
+        } else {
+            memory[previous][0]
+        };
+
+        let reference_lane = if pass == 0 && slice == 0 {
+            lane
+        } else {
+            (pseudo_random >> 32) as usize % params.parallelism as usize
+        };
+
+        let reference_column = reference_column(
+            position,
+            index,
+            pseudo_random as u32,
+            reference_lane == lane,
+            lane_length,
+            segment_length,
+        );
+
+        let block = compress(
+            &memory[previous],
+            &memory[reference_lane * lane_length + reference_column],
+        );
+
+        /* Version 0x13 XORs into the old block from the second pass on */
+        if pass == 0 {
+            memory[current] = block;
+        } else {
+            for (word, new) in memory[current].iter_mut().zip(block) {
+                *word ^= new;
+            }
+        }
+    }
+}
+
+fn reference_column(
+    position: &Position,
+    index: usize,
+    j1: u32,
+    same_lane: bool,
+    lane_length: usize,
+    segment_length: usize,
+) -> usize {
+    let Position { pass, slice, .. } = *position;
+
+    /* Blocks finished so far that may be referenced, excluding the previous one */
+    let area = if pass == 0 {
+        if same_lane {
+            slice * segment_length + index - 1
+        } else {
+            slice * segment_length - usize::from(index == 0)
+        }
+    } else if same_lane {
+        lane_length - segment_length + index - 1
+    } else {
+        lane_length - segment_length - usize::from(index == 0)
+    };
+
+    /* Map J1 non-uniformly, favouring recent blocks */
+    let x = (j1 as u64 * j1 as u64) >> 32;
+    let y = (area as u64 * x) >> 32;
+    let relative = area - 1 - y as usize;
+
+    let start = if pass == 0 || slice == SYNC_POINTS - 1 {
+        0
+    } else {
+        (slice + 1) * segment_length
+    };
+
+    (start + relative) % lane_length
+}
+
+fn next_addresses(input: &mut Block, addresses: &mut Block) {
+    input[6] += 1;
+
+    let zero = [0u64; BLOCK_WORDS];
+    *addresses = compress(&zero, &compress(&zero, input));
+}
+
+/* G(X, Y): the BlaMka permutation over rows then columns of R = X ^ Y, XORed back into R */
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut r = [0u64; BLOCK_WORDS];
+
+    for (word, (a, b)) in r.iter_mut().zip(x.iter().zip(y)) {
+        *word = a ^ b;
+    }
+
+    let mut z = r;
+
+    for row in 0..8 {
+        let indices: [usize; 16] = std::array::from_fn(|i| 16 * row + i);
+        permute(&mut z, &indices);
+    }
+
+    for column in 0..8 {
+        let indices: [usize; 16] = std::array::from_fn(|i| 16 * (i / 2) + 2 * column + i % 2);
+        permute(&mut z, &indices);
+    }
+
+    for (word, original) in z.iter_mut().zip(r) {
+        *word ^= original;
+    }
+
+    z
+}
+
+fn permute(block: &mut Block, indices: &[usize; 16]) {
+    let mut v: [u64; 16] = std::array::from_fn(|i| block[indices[i]]);
+
+    mix(&mut v, 0, 4, 8, 12);
+    mix(&mut v, 1, 5, 9, 13);
+    mix(&mut v, 2, 6, 10, 14);
+    mix(&mut v, 3, 7, 11, 15);
+    mix(&mut v, 0, 5, 10, 15);
+    mix(&mut v, 1, 6, 11, 12);
+    mix(&mut v, 2, 7, 8, 13);
+    mix(&mut v, 3, 4, 9, 14);
+
+    for (i, index) in indices.iter().enumerate() {
+        block[*index] = v[i];
+    }
+}
+
+/* BLAKE2b's G with each addition replaced by a + b + 2 * lo(a) * lo(b) */
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+    v[a] = blamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = blamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = blamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = blamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+fn blamka(a: u64, b: u64) -> u64 {
+    let product = (a & 0xffffffff) * (b & 0xffffffff);
+
+    a.wrapping_add(b).wrapping_add(product.wrapping_mul(2))
+}
+
+/* H': BLAKE2b stretched to any length by chaining 64-byte digests and keeping 32 bytes of each */
+fn variable_hash(input: &[u8], length: usize) -> Vec<u8> {
+    let mut prefixed = (length as u32).to_le_bytes().to_vec();
+    prefixed.extend_from_slice(input);
+
+    if length <= 64 {
+        return blake2b_digest(&prefixed, length);
+    }
+
+    let rounds = length.div_ceil(32) - 2;
+    let mut output = Vec::with_capacity(length);
+    let mut v = blake2b_digest(&prefixed, 64);
+
+    for _ in 1..rounds {
+        output.extend_from_slice(&v[..32]);
+        v = blake2b_digest(&v, 64);
+    }
+
+    output.extend_from_slice(&v[..32]);
+    output.extend_from_slice(&blake2b_digest(&v, length - 32 * rounds));
+
+    output
+}
+
+fn bytes_to_block(bytes: &[u8]) -> Block {
+    let mut block = [0u64; BLOCK_WORDS];
+
+    for (word, chunk) in block.iter_mut().zip(bytes.chunks_exact(8)) {
+        *word = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+
+    block
+}
+
+fn block_to_bytes(block: &Block) -> Vec<u8> {
+    block.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/* PHC strings use standard base64 without padding */
+fn phc_base64(bytes: &[u8]) -> String {
+    bytes_to_base64(bytes).trim_end_matches('=').to_string()
+}
+
+fn phc_base64_decode(encoding: &str) -> Result<Vec<u8>, Argon2Error> {
+    let valid = encoding
+        .chars()
+        .all(|ch| matches!(ch, 'A'..='Z' | 'a'..='z' | '0'..='9' | '+' | '/'));
+
+    if !valid || encoding.len() % 4 == 1 {
+        return Err(Argon2Error::InvalidPhcString);
+    }
+
+    Ok(base64_to_bytes(encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHC: &str = "$argon2id$v=19$m=256,t=2,p=2$c29tZXNhbHRzb21lc2FsdA$+/khWr5CrQBdPhUl7LfxjE3yA8uhGRxtRWlks9DqJUQ";
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    /* RFC 9106 section 5: p = 4, m = 32, t = 3, with a secret and associated data */
+    fn rfc9106(variant: Argon2Variant) -> Vec<u8> {
+        let params = Argon2Params::new(32, 3, 4, 32).unwrap();
+
+        argon2_with_secret(variant, &params, &[1; 32], &[2; 16], &[3; 8], &[4; 12]).unwrap()
+    }
+
+    #[test]
+    fn rfc9106_argon2i() {
+        assert_eq!(
+            rfc9106(Argon2Variant::Argon2i),
+            h("c814d9d1dc7f37aa13f0d77f2494bda1c8de6b016dd388d29952a4c4672b6ce8")
+        );
+    }
+
+    #[test]
+    fn rfc9106_argon2id() {
+        assert_eq!(
+            rfc9106(Argon2Variant::Argon2id),
+            h("0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659")
+        );
+    }
+
+    #[test]
+    fn phc_round_trip() {
+        assert!(verify_password(PHC, b"hunter2").unwrap());
+        assert!(!verify_password(PHC, b"hunter3").unwrap());
+        assert_eq!(PHC.parse::<PhcString>().unwrap().to_string(), PHC);
+
+        let params = Argon2Params::new(64, 1, 1, 32).unwrap();
+        let phc = hash_password(Argon2Variant::Argon2i, &params, b"pw").unwrap();
+
+        assert!(phc.starts_with("$argon2i$v=19$m=64,t=1,p=1$"));
+        assert!(verify_password(&phc, b"pw").unwrap());
+        assert!(!verify_password(&phc, b"pw ").unwrap());
+    }
+
+    #[test]
+    fn malformed_phc_strings_are_rejected() {
+        let cases = [
+            "$argon2d$v=19$m=64,t=1,p=1$c2FsdHNhbHQ$AAAAAAAA",
+            "$argon2id$v=19$m=64,t=1$c2FsdHNhbHQ$AAAAAAAA",
+            "$argon2id$v=19$m=64,t=1,p=1$c2F*dHNhbHQ$AAAAAAAA",
+            "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHQ$AAAAA",
+            "argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHQ$AAAAAAAA",
+            "$argon2id$v=19$m=64,t=1,p=1,p=1$c2FsdHNhbHQ$AAAAAAAA",
+            "$argon2id$v=19$m=64,t=1,p=1,x=1$c2FsdHNhbHQ$AAAAAAAA",
+            "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHQ",
+        ];
+
+        for phc in cases {
+            assert!(
+                matches!(
+                    verify_password(phc, b"pw"),
+                    Err(Argon2Error::InvalidPhcString)
+                ),
+                "{}",
+                phc
+            );
+        }
+
+        assert!(matches!(
+            verify_password("$argon2id$v=16$m=64,t=1,p=1$c2FsdHNhbHQ$AAAAAAAA", b"pw"),
+            Err(Argon2Error::UnsupportedVersion)
+        ));
+        assert!(matches!(
+            verify_password("$argon2id$v=19$m=4,t=1,p=1$c2FsdHNhbHQ$AAAAAAAA", b"pw"),
+            Err(Argon2Error::MemoryTooSmall)
+        ));
+        assert!(matches!(
+            verify_password("$argon2id$v=19$m=64,t=1,p=1$c2FsdA$AAAAAAAA", b"pw"),
+            Err(Argon2Error::SaltTooShort)
+        ));
+    }
+
+    #[test]
+    fn verify_limits_memory() {
+        assert!(matches!(
+            verify_password(
+                "$argon2id$v=19$m=4294967295,t=1,p=1$c2FsdHNhbHQ$AAAAAAAA",
+                b"pw"
+            ),
+            Err(Argon2Error::MemoryTooLarge)
+        ));
+
+        assert!(matches!(
+            verify_password_with_limit(PHC, b"hunter2", 255),
+            Err(Argon2Error::MemoryTooLarge)
+        ));
+        assert!(verify_password_with_limit(PHC, b"hunter2", 256).unwrap());
+    }
+}
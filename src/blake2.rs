@@ -0,0 +1,220 @@
+use super::digest::Digest;
+
+const BLAKE2B_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 12] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+];
+
+pub fn blake2b_digest(input: &[u8], output_length: usize) -> Vec<u8> {
+    let mut hasher = Blake2b::with_output_length(output_length);
+    hasher.update(input);
+
+    hasher.finalize_variable()
+}
+
+/* Unkeyed BLAKE2b; as a Digest it is BLAKE2b-512 */
+#[derive(Clone)]
+pub struct Blake2b {
+    h: [u64; 8],
+    buffer: Vec<u8>,
+    length: u128,
+    output_length: usize,
+}
+
+impl Blake2b {
+    pub fn with_output_length(output_length: usize) -> Self {
+        assert!(
+            (1..=64).contains(&output_length),
+            "BLAKE2b outputs between 1 and 64 bytes"
+        );
+
+        let mut h = BLAKE2B_IV;
+        h[0] ^= 0x01010000 ^ output_length as u64;
+
+        Self {
+            h,
+            buffer: Vec::with_capacity(128),
+            length: 0,
+            output_length,
+        }
+    }
+
+    pub fn finalize_variable(mut self) -> Vec<u8> {
+        self.length += self.buffer.len() as u128;
+
+        let mut block = [0u8; 128];
+        block[..self.buffer.len()].copy_from_slice(&self.buffer);
+
+        blake2b_compress(&mut self.h, &block, self.length, true);
+
+        let mut digest: Vec<u8> = self.h.iter().flat_map(|word| word.to_le_bytes()).collect();
+        digest.truncate(self.output_length);
+
+        digest
+    }
+}
+
+impl Digest for Blake2b {
+    const BLOCK_SIZE: usize = 128;
+    const OUTPUT_SIZE: usize = 64;
+
+    type Output = [u8; 64];
+
+    fn new() -> Self {
+        Self::with_output_length(64)
+    }
+
+    /* The last block is held back, it has to be compressed with the final flag */
+    fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            if self.buffer.len() == 128 {
+                let mut block = [0u8; 128];
+                block.copy_from_slice(&self.buffer);
+
+                self.length += 128;
+                blake2b_compress(&mut self.h, &block, self.length, false);
+                self.buffer.clear();
+            }
+
+            let take = std::cmp::min(128 - self.buffer.len(), input.len());
+
+            self.buffer.extend_from_slice(&input[..take]);
+            input = &input[take..];
+        }
+    }
+
+    fn finalize(self) -> Self::Output {
+        let mut digest = [0; 64];
+        let variable = self.finalize_variable();
+
+        digest[..variable.len()].copy_from_slice(&variable);
+
+        digest
+    }
+}
+
+fn blake2b_compress(h: &mut [u64; 8], block: &[u8; 128], length: u128, last: bool) {
+    let mut m = [0u64; 16];
+
+    for (word, bytes) in m.iter_mut().zip(block.chunks_exact(8)) {
+        *word = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&BLAKE2B_IV);
+
+    v[12] ^= length as u64;
+    v[13] ^= (length >> 64) as u64;
+
+    if last {
+        v[14] = !v[14];
+    }
+
+    for s in SIGMA.iter() {
+        mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for (i, word) in h.iter_mut().enumerate() {
+        *word ^= v[i] ^ v[i + 8];
+    }
+}
+
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    #[test]
+    fn rfc7693_blake2b_512() {
+        assert_eq!(
+            blake2b_digest(b"abc", 64),
+            h(
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+               7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+            )
+        );
+    }
+
+    #[test]
+    fn block_boundaries_and_short_outputs() {
+        let cases: [(&[u8], usize, &str); 4] = [
+            (
+                b"",
+                64,
+                "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419\
+                 d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce",
+            ),
+            (
+                &[b'a'; 128],
+                64,
+                "fc6c71f688f43ea7d60817478808f3cac753e61571865c95adbc2d9122c943a7\
+                 6b92c2cb1047ef3fe7bf6e436ec1d0a99a9e5b216780bf7fed9d7ca91d3a8f3b",
+            ),
+            (
+                &[b'a'; 129],
+                32,
+                "2f64744a6de0d2c0b56e64cf6e29a5aaa255010d415d51c75ccc82f73dccd865",
+            ),
+            (&[b'x'; 300], 17, "0d3420e29ad101d5414f1dc726cdd0cf30"),
+        ];
+
+        for (input, length, expected) in cases {
+            assert_eq!(blake2b_digest(input, length), h(expected));
+        }
+    }
+
+    #[test]
+    fn incremental_updates_match() {
+        let input: Vec<u8> = (0..=255).collect();
+        let mut hasher = Blake2b::new();
+
+        for chunk in input.chunks(37) {
+            hasher.update(chunk);
+        }
+
+        assert_eq!(hasher.finalize().as_ref(), blake2b_digest(&input, 64));
+    }
+}
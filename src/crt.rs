@@ -0,0 +1,152 @@
+use super::ubig::Ubig;
+
+#[derive(Debug)]
+pub enum CrtError {
+    ZeroModulus,
+    NotCoprime,
+    Inconsistent,
+}
+
+/*
+ * Solves x = r_i (mod m_i) for pairwise coprime moduli, returning
+ * (x, M) with M the product of the moduli and 0 <= x < M.
+ */
+pub fn crt(congruences: &[(Ubig, Ubig)]) -> Result<(Ubig, Ubig), CrtError> {
+    solve(congruences, false)
+}
+
+/*
+ * As crt, but the moduli may share factors as long as the residues agree
+ * on them. M is then the lcm of the moduli.
+ */
+pub fn crt_general(congruences: &[(Ubig, Ubig)]) -> Result<(Ubig, Ubig), CrtError> {
+    solve(congruences, true)
+}
+
+fn solve(
+    congruences: &[(Ubig, Ubig)],
+    allow_common_factors: bool,
+) -> Result<(Ubig, Ubig), CrtError> {
+    let mut residue = Ubig::from(0u64);
+    let mut modulus = Ubig::from(1u64);
+
+    for (next_residue, next_modulus) in congruences {
+        if !next_modulus.not_zero() {
+            return Err(CrtError::ZeroModulus);
+        }
+
+        let gcd = Ubig::gcd(modulus.clone(), next_modulus.clone());
+
+        if !gcd.is_one() && !allow_common_factors {
+            return Err(CrtError::NotCoprime);
+        }
+
+        /* (next_residue - residue) mod next_modulus, kept non-negative */
+        let difference =
+            (next_residue % next_modulus + next_modulus - &residue % next_modulus) % next_modulus;
+
+        if (&difference % &gcd).not_zero() {
+            return Err(CrtError::Inconsistent);
+        }
+
+        /* x = residue + modulus * t, where (modulus / g) * t = difference / g (mod next_modulus / g) */
+        let reduced_modulus = next_modulus / &gcd;
+
+        /* A reduced modulus of 1 means the congruence adds nothing, and t = 0 */
+        let t = match Ubig::modinv(&modulus / &gcd, reduced_modulus.clone()) {
+            Some(inverse) => &difference / &gcd * inverse % &reduced_modulus,
+            None => Ubig::from(0u64),
+        };
+
+        residue += &modulus * t;
+        modulus *= reduced_modulus;
+    }
+
+    Ok((residue, modulus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prime;
+
+    fn congruences(pairs: &[(u64, u64)]) -> Vec<(Ubig, Ubig)> {
+        pairs
+            .iter()
+            .map(|(residue, modulus)| (Ubig::from(*residue), Ubig::from(*modulus)))
+            .collect()
+    }
+
+    fn solution(x: u64, modulus: u64) -> (Ubig, Ubig) {
+        (Ubig::from(x), Ubig::from(modulus))
+    }
+
+    #[test]
+    fn textbook_system() {
+        let system = congruences(&[(2, 3), (3, 5), (2, 7)]);
+
+        assert_eq!(crt(&system).unwrap(), solution(23, 105));
+        assert_eq!(crt_general(&system).unwrap(), solution(23, 105));
+    }
+
+    #[test]
+    fn empty_and_single_congruences() {
+        assert_eq!(crt(&[]).unwrap(), solution(0, 1));
+        assert_eq!(crt_general(&[]).unwrap(), solution(0, 1));
+
+        assert_eq!(crt(&congruences(&[(5, 7)])).unwrap(), solution(5, 7));
+        assert_eq!(crt(&congruences(&[(12, 7)])).unwrap(), solution(5, 7));
+        assert_eq!(crt(&congruences(&[(3, 1)])).unwrap(), solution(0, 1));
+    }
+
+    #[test]
+    fn common_factors() {
+        let consistent = congruences(&[(2, 4), (4, 6)]);
+
+        assert!(matches!(crt(&consistent), Err(CrtError::NotCoprime)));
+        assert_eq!(crt_general(&consistent).unwrap(), solution(10, 12));
+
+        /* The second congruence is implied by the first */
+        assert_eq!(
+            crt_general(&congruences(&[(3, 4), (1, 2)])).unwrap(),
+            solution(3, 4)
+        );
+        assert_eq!(
+            crt_general(&congruences(&[(3, 4), (3, 4)])).unwrap(),
+            solution(3, 4)
+        );
+
+        for inconsistent in [[(1, 4), (2, 6)], [(3, 4), (0, 2)]] {
+            assert!(matches!(
+                crt_general(&congruences(&inconsistent)),
+                Err(CrtError::Inconsistent)
+            ));
+        }
+    }
+
+    #[test]
+    fn zero_modulus() {
+        for system in [[(1, 3), (0, 0)], [(0, 0), (1, 3)]] {
+            assert!(matches!(
+                crt(&congruences(&system)),
+                Err(CrtError::ZeroModulus)
+            ));
+        }
+    }
+
+    #[test]
+    fn large_moduli() {
+        let moduli: Vec<Ubig> = (0..3).map(|_| prime::random_prime(128)).collect();
+        let x = Ubig::from(3u64).pow(200) % &(&(&moduli[0] * &moduli[1]) * &moduli[2]);
+
+        let system: Vec<(Ubig, Ubig)> = moduli
+            .iter()
+            .map(|modulus| (&x % modulus, modulus.clone()))
+            .collect();
+
+        let (residue, modulus) = crt(&system).unwrap();
+
+        assert_eq!(residue, x);
+        assert_eq!(modulus, &(&moduli[0] * &moduli[1]) * &moduli[2]);
+    }
+}
@@ -0,0 +1,132 @@
+use super::ubig::Ubig;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/* An element of the field of integers modulo p = 2^255 - 19, kept reduced */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FieldElement(Ubig);
+
+impl FieldElement {
+    pub fn new(value: Ubig) -> Self {
+        Self(value % prime())
+    }
+
+    pub fn zero() -> Self {
+        Self(Ubig::from(0u64))
+    }
+
+    pub fn one() -> Self {
+        Self(Ubig::from(1u64))
+    }
+
+    pub fn prime() -> Ubig {
+        prime()
+    }
+
+    /* Little-endian, the top bit is ignored as in RFC 7748 */
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut bytes = *bytes;
+        bytes[31] &= 0x7f;
+
+        Self::new(Ubig::from(bytes.as_ref()))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        let value = self.0.as_ref();
+
+        bytes[..value.len()].copy_from_slice(value);
+
+        bytes
+    }
+
+    pub fn value(&self) -> &Ubig {
+        &self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        !self.0.not_zero()
+    }
+
+    /* The sign used by point compression is the least significant bit */
+    pub fn is_negative(&self) -> bool {
+        self.0.bit(0)
+    }
+
+    pub fn square(&self) -> Self {
+        self * self
+    }
+
+    pub fn pow(&self, exponent: &Ubig) -> Self {
+        Self(Ubig::modexp(self.0.clone(), exponent.clone(), prime()))
+    }
+
+    /* a^(p - 2), so zero maps to zero */
+    pub fn invert(&self) -> Self {
+        self.pow(&(prime() - Ubig::from(2u64)))
+    }
+
+    /* Square root of u / v when it exists, using p = 5 (mod 8) as in RFC 8032 */
+    pub fn sqrt_ratio(u: &Self, v: &Self) -> Option<Self> {
+        let exponent = (prime() - Ubig::from(5u64)) >> 3;
+
+        let v3 = &v.square() * v;
+        let v7 = &v3.square() * v;
+        let mut x = &(u * &v3) * &(u * &v7).pow(&exponent);
+
+        let check = v * &x.square();
+
+        if check == -u {
+            x = &x * &sqrt_minus_one();
+        } else if check != *u {
+            return None;
+        }
+
+        Some(x)
+    }
+
+    /* Branch-free on swap, though the Ubig arithmetic around it is still variable-time */
+    pub fn conditional_swap(a: &mut Self, b: &mut Self, swap: bool) {
+        Ubig::conditional_swap(&mut a.0, &mut b.0, swap);
+    }
+}
+
+impl Add for &FieldElement {
+    type Output = FieldElement;
+
+    fn add(self, rhs: &FieldElement) -> FieldElement {
+        FieldElement::new(&self.0 + &rhs.0)
+    }
+}
+
+impl Sub for &FieldElement {
+    type Output = FieldElement;
+
+    fn sub(self, rhs: &FieldElement) -> FieldElement {
+        FieldElement::new(&self.0 + &prime() - &rhs.0)
+    }
+}
+
+impl Mul for &FieldElement {
+    type Output = FieldElement;
+
+    fn mul(self, rhs: &FieldElement) -> FieldElement {
+        FieldElement::new(&self.0 * &rhs.0)
+    }
+}
+
+impl Neg for &FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> FieldElement {
+        &FieldElement::zero() - self
+    }
+}
+
+fn prime() -> Ubig {
+    (Ubig::from(1u64) << 255) - Ubig::from(19u64)
+}
+
+/* 2^((p - 1) / 4) */
+fn sqrt_minus_one() -> FieldElement {
+    FieldElement(Ubig::from(2u64)).pow(&((prime() - Ubig::from(1u64)) >> 2))
+}
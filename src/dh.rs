@@ -1,58 +1,402 @@
+use super::prime;
 use super::ubig::Ubig;
+use super::urandom;
 
-const NIST_DH_P: [u8; 192] = [
-    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x27, 0x73, 0x23, 0xca, 0x08, 0x6c, 0x74, 0xf1,
-    0x04, 0x98, 0xbc, 0x4a, 0x4e, 0x35, 0x0c, 0x67, 0x6d, 0x96, 0x96, 0x70, 0x07, 0x29, 0xd5, 0x9e,
-    0xbb, 0x52, 0x85, 0x20, 0x56, 0xf3, 0x62, 0x1c, 0x96, 0xad, 0xa3, 0xdc, 0x23, 0x5d, 0x65, 0x83,
-    0x5f, 0xcf, 0x24, 0xfd, 0xa8, 0x3f, 0x16, 0x69, 0x9a, 0xd3, 0x55, 0x1c, 0x36, 0x48, 0xda, 0x98,
-    0x05, 0xbf, 0x63, 0xa1, 0xb8, 0x7c, 0x00, 0xc2, 0x3d, 0x5b, 0xe4, 0xec, 0x51, 0x66, 0x28, 0x49,
-    0xe6, 0x1f, 0x4b, 0x7c, 0x11, 0x24, 0x9f, 0xae, 0xa5, 0x9f, 0x89, 0x5a, 0xfb, 0x6b, 0x38, 0xee,
-    0xed, 0xb7, 0x06, 0xf4, 0xb6, 0x5c, 0xff, 0x0b, 0x6b, 0xed, 0x37, 0xa6, 0xe9, 0x42, 0x4c, 0xf4,
-    0xc6, 0x7e, 0x5e, 0x62, 0x76, 0xb5, 0x85, 0xe4, 0x45, 0xc2, 0x51, 0x6d, 0x6d, 0x35, 0xe1, 0x4f,
-    0x37, 0x14, 0x5f, 0xf2, 0x6d, 0x0a, 0x2b, 0x30, 0x1b, 0x43, 0x3a, 0xcd, 0xb3, 0x19, 0x95, 0xef,
-    0xdd, 0x04, 0x34, 0x8e, 0x79, 0x08, 0x4a, 0x51, 0x22, 0x9b, 0x13, 0x3b, 0xa6, 0xbe, 0x0b, 0x02,
-    0x74, 0xcc, 0x67, 0x8a, 0x08, 0x4e, 0x02, 0x29, 0xd1, 0x1c, 0xdc, 0x80, 0x8b, 0x62, 0xc6, 0xc4,
-    0x34, 0xc2, 0x68, 0x21, 0xa2, 0xda, 0x0f, 0xc9, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
-];
-
-const NIST_DH_G: [u8; 1] = [2];
-
-pub fn nist_dh_p() -> Ubig {
-    Ubig::from(NIST_DH_P.as_ref())
-}
+/* RFC 3526 MODP groups, the 1536-bit one is the "NIST" prime used throughout */
+const MODP_1536_P: &str = "0xffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74\
+    020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437\
+    4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed\
+    ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05\
+    98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb\
+    9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff";
+
+const MODP_2048_P: &str = "0xffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74\
+    020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437\
+    4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed\
+    ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05\
+    98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb\
+    9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b\
+    e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf695581718\
+    3995497cea956ae515d2261898fa051015728e5a8aacaa68ffffffffffffffff";
+
+const MODP_3072_P: &str = "0xffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74\
+    020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437\
+    4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed\
+    ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05\
+    98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb\
+    9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b\
+    e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf695581718\
+    3995497cea956ae515d2261898fa051015728e5a8aaac42dad33170d04507a33\
+    a85521abdf1cba64ecfb850458dbef0a8aea71575d060c7db3970f85a6e1e4c7\
+    abf5ae8cdb0933d71e8c94e04a25619dcee3d2261ad2ee6bf12ffa06d98a0864\
+    d87602733ec86a64521f2b18177b200cbbe117577a615d6c770988c0bad946e2\
+    08e24fa074e5ab3143db5bfce0fd108e4b82d120a93ad2caffffffffffffffff";
+
+const MODP_4096_P: &str = "0xffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74\
+    020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437\
+    4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed\
+    ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05\
+    98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb\
+    9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b\
+    e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf695581718\
+    3995497cea956ae515d2261898fa051015728e5a8aaac42dad33170d04507a33\
+    a85521abdf1cba64ecfb850458dbef0a8aea71575d060c7db3970f85a6e1e4c7\
+    abf5ae8cdb0933d71e8c94e04a25619dcee3d2261ad2ee6bf12ffa06d98a0864\
+    d87602733ec86a64521f2b18177b200cbbe117577a615d6c770988c0bad946e2\
+    08e24fa074e5ab3143db5bfce0fd108e4b82d120a92108011a723c12a787e6d7\
+    88719a10bdba5b2699c327186af4e23c1a946834b6150bda2583e9ca2ad44ce8\
+    dbbbc2db04de8ef92e8efc141fbecaa6287c59474e6bc05d99b2964fa090c3a2\
+    233ba186515be7ed1f612970cee2d7afb81bdd762170481cd0069127d5b05aa9\
+    93b4ea988d8fddc186ffb7dc90a6c08f4df435c934063199ffffffffffffffff";
 
-pub fn nist_dh_public(private: &[u8]) -> Vec<u8> {
-    let p = Ubig::from(NIST_DH_P.as_ref());
-    let g = Ubig::from(NIST_DH_G.as_ref());
+/* RFC 7919 FFDHE groups */
+const FFDHE_2048_P: &str = "0xffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695\
+    a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a\
+    d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935\
+    984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a\
+    bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4\
+    ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61\
+    9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005\
+    c58ef1837d1683b2c6f34a26c1b2effa886b423861285c97ffffffffffffffff";
 
-    let q = Ubig::from(private);
+const FFDHE_3072_P: &str = "0xffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695\
+    a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a\
+    d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935\
+    984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a\
+    bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4\
+    ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61\
+    9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005\
+    c58ef1837d1683b2c6f34a26c1b2effa886b4238611fcfdcde355b3b6519035b\
+    bc34f4def99c023861b46fc9d6e6c9077ad91d2691f7f7ee598cb0fac186d91c\
+    aefe130985139270b4130c93bc437944f4fd4452e2d74dd364f2e21e71f54bff\
+    5cae82ab9c9df69ee86d2bc522363a0dabc521979b0deada1dbf9a42d5c4484e\
+    0abcd06bfa53ddef3c1b20ee3fd59d7c25e41d2b66c62e37ffffffffffffffff";
 
-    Ubig::modexp(g, q, p).into()
+const FFDHE_4096_P: &str = "0xffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695\
+    a9e13641146433fbcc939dce249b3ef97d2fe363630c75d8f681b202aec4617a\
+    d3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935\
+    984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797a\
+    bc0ab182b324fb61d108a94bb2c8e3fbb96adab760d7f4681d4f42a3de394df4\
+    ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61\
+    9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005\
+    c58ef1837d1683b2c6f34a26c1b2effa886b4238611fcfdcde355b3b6519035b\
+    bc34f4def99c023861b46fc9d6e6c9077ad91d2691f7f7ee598cb0fac186d91c\
+    aefe130985139270b4130c93bc437944f4fd4452e2d74dd364f2e21e71f54bff\
+    5cae82ab9c9df69ee86d2bc522363a0dabc521979b0deada1dbf9a42d5c4484e\
+    0abcd06bfa53ddef3c1b20ee3fd59d7c25e41d2b669e1ef16e6f52c3164df4fb\
+    7930e9e4e58857b6ac7d5f42d69f6d187763cf1d5503400487f55ba57e31cc7a\
+    7135c886efb4318aed6a1e012d9e6832a907600a918130c46dc778f971ad0038\
+    092999a333cb8b7a1a1db93d7140003c2a4ecea9f98d0acc0a8291cdcec97dcf\
+    8ec9b55a7f88a46b4db5a851f44182e1c68a007e5e655f6affffffffffffffff";
+
+#[derive(Debug)]
+pub enum DhError {
+    InvalidModulus,
+    ModulusNotPrime,
+    GeneratorOutOfRange,
+    WrongSubgroupOrder,
+    PublicOutOfRange,
+    PublicNotInSubgroup,
+    PrivateOutOfRange,
+}
+
+#[derive(Clone)]
+pub struct DhGroup {
+    p: Ubig,
+    g: Ubig,
+    q: Option<Ubig>,
 }
 
-pub fn nist_dh_secret(other_public: &[u8], our_private: &[u8]) -> Vec<u8> {
-    let p = Ubig::from(NIST_DH_P.as_ref());
-    let g = Ubig::from(other_public);
+impl DhGroup {
+    pub fn new(p: Ubig, g: Ubig, q: Option<Ubig>) -> Result<Self, DhError> {
+        let group = Self::new_unchecked(p, g, q)?;
+        group.validate()?;
+
+        Ok(group)
+    }
+
+    /*
+     * Skips validation so that degenerate generators such as 1, p or p - 1 can
+     * be tried. The modulus must still be odd and above 1, as Montgomery
+     * arithmetic needs.
+     */
+    pub fn new_unchecked(p: Ubig, g: Ubig, q: Option<Ubig>) -> Result<Self, DhError> {
+        if !p.bit(0) || p.is_one() {
+            return Err(DhError::InvalidModulus);
+        }
+
+        Ok(Self { p, g, q })
+    }
+
+    pub fn modp_1536() -> Self {
+        Self::safe_prime_group(MODP_1536_P)
+    }
+
+    pub fn modp_2048() -> Self {
+        Self::safe_prime_group(MODP_2048_P)
+    }
+
+    pub fn modp_3072() -> Self {
+        Self::safe_prime_group(MODP_3072_P)
+    }
+
+    pub fn modp_4096() -> Self {
+        Self::safe_prime_group(MODP_4096_P)
+    }
+
+    pub fn ffdhe_2048() -> Self {
+        Self::safe_prime_group(FFDHE_2048_P)
+    }
+
+    pub fn ffdhe_3072() -> Self {
+        Self::safe_prime_group(FFDHE_3072_P)
+    }
+
+    pub fn ffdhe_4096() -> Self {
+        Self::safe_prime_group(FFDHE_4096_P)
+    }
+
+    pub fn p(&self) -> &Ubig {
+        &self.p
+    }
+
+    pub fn g(&self) -> &Ubig {
+        &self.g
+    }
+
+    pub fn q(&self) -> Option<&Ubig> {
+        self.q.as_ref()
+    }
+
+    /*
+     * Callers should run this on untrusted groups, DhGroup::new already does.
+     * The generator is checked first as testing p is by far the slowest step.
+     */
+    pub fn validate(&self) -> Result<(), DhError> {
+        /* g = 0, 1 and p - 1 generate trivial subgroups */
+        let one = Ubig::from(1u64);
+        let p_minus_one = &self.p - &one;
+
+        if self.g <= one || self.g >= p_minus_one {
+            return Err(DhError::GeneratorOutOfRange);
+        }
+
+        if !prime::is_probable_prime(&self.p) {
+            return Err(DhError::ModulusNotPrime);
+        }
+
+        if let Some(q) = &self.q {
+            if !prime::is_probable_prime(q) || (&p_minus_one % q).not_zero() {
+                return Err(DhError::WrongSubgroupOrder);
+            }
+
+            if !Ubig::modexp(self.g.clone(), q.clone(), self.p.clone()).is_one() {
+                return Err(DhError::WrongSubgroupOrder);
+            }
+        }
+
+        Ok(())
+    }
+
+    /* 1 < y < p - 1, and y^q = 1 when the subgroup order is known */
+    pub fn validate_public(&self, y: &Ubig) -> Result<(), DhError> {
+        let one = Ubig::from(1u64);
+
+        if y <= &one || y >= &(&self.p - &one) {
+            return Err(DhError::PublicOutOfRange);
+        }
+
+        if let Some(q) = &self.q {
+            if !Ubig::modexp(y.clone(), q.clone(), self.p.clone()).is_one() {
+                return Err(DhError::PublicNotInSubgroup);
+            }
+        }
 
-    let q = Ubig::from(our_private);
+        Ok(())
+    }
 
-    Ubig::modexp(g, q, p).into()
+    /* Exponents are drawn below q when it is known, below p - 1 otherwise */
+    fn exponent_bound(&self) -> Ubig {
+        match &self.q {
+            Some(q) => q.clone(),
+            None => &self.p - &Ubig::from(1u64),
+        }
+    }
+
+    fn power(&self, base: &Ubig, exponent: &Ubig) -> Ubig {
+        Ubig::modexp_constant_time(
+            base.clone(),
+            exponent.clone(),
+            self.p.clone(),
+            self.exponent_bound().bit_length(),
+        )
+    }
+
+    fn safe_prime_group(p: &str) -> Self {
+        let p = p.parse::<Ubig>().expect("valid group constant");
+        let q = (&p - &Ubig::from(1u64)) >> 1;
+
+        Self {
+            p,
+            g: Ubig::from(2u64),
+            q: Some(q),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DhKeyPair {
+    group: DhGroup,
+    private: Ubig,
+    public: Ubig,
 }
 
-pub fn dh_public(private: &[u8], p: &[u8], g: &[u8]) -> Vec<u8> {
-    let p = Ubig::from(p);
-    let g = Ubig::from(g);
+impl DhKeyPair {
+    pub fn generate(group: &DhGroup) -> Self {
+        let bound = group.exponent_bound();
+        let bytes = urandom::bytes(bound.bit_length().div_ceil(8) + 8);
+
+        /* Uniform in [1, bound - 1] */
+        let private = Ubig::from(bytes.as_ref()) % (&bound - &Ubig::from(1u64)) + Ubig::from(1u64);
+
+        Self::from_private(group.clone(), private).expect("random exponent is in range")
+    }
+
+    pub fn from_private(group: DhGroup, private: Ubig) -> Result<Self, DhError> {
+        if !private.not_zero() || private >= group.exponent_bound() {
+            return Err(DhError::PrivateOutOfRange);
+        }
 
-    let q = Ubig::from(private);
+        let public = group.power(&group.g, &private);
 
-    Ubig::modexp(g, q, p).into()
+        Ok(Self {
+            group,
+            private,
+            public,
+        })
+    }
+
+    pub fn group(&self) -> &DhGroup {
+        &self.group
+    }
+
+    pub fn private_key(&self) -> &Ubig {
+        &self.private
+    }
+
+    pub fn public_key(&self) -> &Ubig {
+        &self.public
+    }
+
+    pub fn shared_secret(&self, peer_public: &Ubig) -> Result<Ubig, DhError> {
+        self.group.validate_public(peer_public)?;
+
+        Ok(self.shared_secret_unchecked(peer_public))
+    }
+
+    /* Skips peer validation, for the degenerate-g and small-subgroup attacks */
+    pub fn shared_secret_unchecked(&self, peer_public: &Ubig) -> Ubig {
+        self.group.power(peer_public, &self.private)
+    }
 }
 
-pub fn dh_secret(other_public: &[u8], our_private: &[u8], p: &[u8]) -> Vec<u8> {
-    let p = Ubig::from(p);
-    let g = Ubig::from(other_public);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_unchecked_rejects_even_or_tiny_moduli() {
+        for p in [0u64, 1, 2, 1024] {
+            assert!(matches!(
+                DhGroup::new_unchecked(Ubig::from(p), Ubig::from(2u64), None),
+                Err(DhError::InvalidModulus)
+            ));
+        }
+
+        assert!(DhGroup::new_unchecked(Ubig::from(23u64), Ubig::from(1u64), None).is_ok());
+    }
+
+    #[test]
+    fn new_validates_the_group() {
+        let group = |p: u64, g: u64, q: u64| {
+            DhGroup::new(Ubig::from(p), Ubig::from(g), Some(Ubig::from(q)))
+        };
+
+        assert!(group(23, 2, 11).is_ok());
+
+        for g in [0, 1, 22, 23] {
+            assert!(matches!(
+                group(23, g, 11),
+                Err(DhError::GeneratorOutOfRange)
+            ));
+        }
+
+        assert!(matches!(group(21, 2, 5), Err(DhError::ModulusNotPrime)));
+        assert!(matches!(group(23, 2, 7), Err(DhError::WrongSubgroupOrder)));
+
+        /* 5 is a non-residue mod 23, so it generates the whole group of order 22 */
+        assert!(matches!(group(23, 5, 11), Err(DhError::WrongSubgroupOrder)));
+    }
+
+    #[test]
+    fn degenerate_public_keys_are_rejected() {
+        for group in [DhGroup::modp_2048(), DhGroup::ffdhe_2048()] {
+            let key = DhKeyPair::generate(&group);
+            let p = group.p();
+            let one = Ubig::from(1u64);
+
+            for y in [Ubig::from(0u64), one.clone(), p - &one, p.clone(), p + &one] {
+                assert!(matches!(
+                    group.validate_public(&y),
+                    Err(DhError::PublicOutOfRange)
+                ));
+                assert!(matches!(
+                    key.shared_secret(&y),
+                    Err(DhError::PublicOutOfRange)
+                ));
+            }
+
+            /* 11 is a quadratic non-residue mod both primes, so outside the subgroup of order q */
+            let outside = Ubig::from(11u64);
+
+            assert!(matches!(
+                group.validate_public(&outside),
+                Err(DhError::PublicNotInSubgroup)
+            ));
+            assert!(matches!(
+                key.shared_secret(&outside),
+                Err(DhError::PublicNotInSubgroup)
+            ));
+
+            /* The unchecked path goes ahead and lands in a trivial subgroup */
+            assert!(key.shared_secret_unchecked(&one).is_one());
+            assert!(!key.shared_secret_unchecked(&Ubig::from(0u64)).not_zero());
+        }
+    }
+
+    #[test]
+    fn key_pairs_agree() {
+        for group in [DhGroup::modp_2048(), DhGroup::ffdhe_2048()] {
+            let alice = DhKeyPair::generate(&group);
+            let bob = DhKeyPair::generate(&group);
+
+            assert!(group.validate_public(alice.public_key()).is_ok());
+
+            let shared = alice.shared_secret(bob.public_key()).unwrap();
+            assert_eq!(shared, bob.shared_secret(alice.public_key()).unwrap());
+            assert!(shared.not_zero());
+        }
+    }
 
-    let q = Ubig::from(our_private);
+    #[test]
+    fn from_private_checks_range() {
+        let group = DhGroup::ffdhe_2048();
 
-    Ubig::modexp(g, q, p).into()
+        for private in [Ubig::from(0u64), group.q().unwrap().clone()] {
+            assert!(matches!(
+                DhKeyPair::from_private(group.clone(), private),
+                Err(DhError::PrivateOutOfRange)
+            ));
+        }
+    }
 }
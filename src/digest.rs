@@ -0,0 +1,70 @@
+pub trait Digest: Clone {
+    const BLOCK_SIZE: usize;
+    const OUTPUT_SIZE: usize;
+
+    type Output: AsRef<[u8]> + Copy;
+
+    fn new() -> Self;
+    fn update(&mut self, input: &[u8]);
+    fn finalize(self) -> Self::Output;
+
+    fn digest(input: &[u8]) -> Self::Output {
+        let mut hasher = Self::new();
+        hasher.update(input);
+
+        hasher.finalize()
+    }
+}
+
+#[derive(Clone)]
+pub struct BlockBuffer<const N: usize> {
+    buffer: [u8; N],
+    filled: usize,
+}
+
+impl<const N: usize> BlockBuffer<N> {
+    pub fn new() -> Self {
+        Self {
+            buffer: [0; N],
+            filled: 0,
+        }
+    }
+
+    pub fn update<F: FnMut(&[u8])>(&mut self, mut input: &[u8], mut compress: F) {
+        if self.filled > 0 {
+            let take = std::cmp::min(N - self.filled, input.len());
+
+            self.buffer[self.filled..self.filled + take].copy_from_slice(&input[..take]);
+            self.filled += take;
+            input = &input[take..];
+
+            if self.filled < N {
+                return;
+            }
+
+            compress(&self.buffer);
+            self.filled = 0;
+        }
+
+        let mut chunks = input.chunks_exact(N);
+
+        for chunk in &mut chunks {
+            compress(chunk);
+        }
+
+        let rest = chunks.remainder();
+
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    pub fn remainder(&self) -> &[u8] {
+        &self.buffer[..self.filled]
+    }
+}
+
+impl<const N: usize> Default for BlockBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,455 @@
+use super::digest::Digest;
+use super::prime;
+use super::ubig::Ubig;
+use super::urandom;
+
+#[derive(Debug)]
+pub enum DsaError {
+    InvalidParameters,
+    KeyOutOfRange,
+    NonceOutOfRange,
+}
+
+#[derive(Clone)]
+pub struct DsaParameters {
+    p: Ubig,
+    q: Ubig,
+    g: Ubig,
+}
+
+impl DsaParameters {
+    pub fn new(p: Ubig, q: Ubig, g: Ubig) -> Result<Self, DsaError> {
+        let parameters = Self { p, q, g };
+        parameters.validate()?;
+
+        Ok(parameters)
+    }
+
+    /* p of l bits and q of n bits, e.g. (2048, 256) */
+    pub fn generate(l: usize, n: usize) -> Self {
+        assert!(n < l, "q must be shorter than p");
+
+        let one = Ubig::from(1u64);
+        let q = prime::random_prime(n);
+        let two_q = &q << 1;
+
+        /* Search p = 1 (mod 2q) among l-bit numbers */
+        let p = loop {
+            let mut candidate = Ubig::from(urandom::bytes(l.div_ceil(8)).as_ref());
+
+            for bit in l..8 * l.div_ceil(8) {
+                candidate.set_bit(bit, false);
+            }
+
+            candidate.set_bit(l - 1, true);
+
+            let p = &candidate - &(&candidate % &two_q) + &one;
+
+            if p.bit_length() == l && prime::is_probable_prime(&p) {
+                break p;
+            }
+        };
+
+        let exponent = (&p - &one) / &q;
+        let mut h = Ubig::from(2u64);
+
+        let g = loop {
+            let g = Ubig::modexp(h.clone(), exponent.clone(), p.clone());
+
+            if !g.is_one() {
+                break g;
+            }
+
+            h += &one;
+        };
+
+        Self { p, q, g }
+    }
+
+    /* The primality tests are by far the slowest, so they come last */
+    pub fn validate(&self) -> Result<(), DsaError> {
+        let one = Ubig::from(1u64);
+
+        /* g must generate the subgroup of order q */
+        if self.g <= one || self.g >= self.p {
+            return Err(DsaError::InvalidParameters);
+        }
+
+        if !self.q.not_zero() || (&(&self.p - &one) % &self.q).not_zero() {
+            return Err(DsaError::InvalidParameters);
+        }
+
+        if !Ubig::modexp(self.g.clone(), self.q.clone(), self.p.clone()).is_one() {
+            return Err(DsaError::InvalidParameters);
+        }
+
+        if !prime::is_probable_prime(&self.q) || !prime::is_probable_prime(&self.p) {
+            return Err(DsaError::InvalidParameters);
+        }
+
+        Ok(())
+    }
+
+    pub fn p(&self) -> &Ubig {
+        &self.p
+    }
+
+    pub fn q(&self) -> &Ubig {
+        &self.q
+    }
+
+    pub fn g(&self) -> &Ubig {
+        &self.g
+    }
+
+    /* Uniform in [1, q - 1] */
+    fn random_scalar(&self) -> Ubig {
+        let q_minus_one = &self.q - &Ubig::from(1u64);
+        let bytes = urandom::bytes(self.q.bit_length().div_ceil(8) + 8);
+
+        Ubig::from(bytes.as_ref()) % &q_minus_one + Ubig::from(1u64)
+    }
+
+    /* The leftmost min(N, outlen) bits of the hash, as in FIPS 186-4 */
+    fn hash_to_scalar<D: Digest>(&self, message: &[u8]) -> Ubig {
+        let mut hash = D::digest(message).as_ref().to_vec();
+        let n = self.q.bit_length();
+
+        hash.truncate(n.div_ceil(8));
+        hash.reverse();
+
+        let excess = 8 * hash.len() - std::cmp::min(n, 8 * hash.len());
+
+        Ubig::from(hash.as_ref()) >> excess as u32
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DsaSignature {
+    pub r: Ubig,
+    pub s: Ubig,
+}
+
+#[derive(Clone)]
+pub struct DsaPublicKey {
+    parameters: DsaParameters,
+    y: Ubig,
+}
+
+impl DsaPublicKey {
+    pub fn new(parameters: DsaParameters, y: Ubig) -> Result<Self, DsaError> {
+        let one = Ubig::from(1u64);
+
+        if y <= one || y >= parameters.p {
+            return Err(DsaError::KeyOutOfRange);
+        }
+
+        if !Ubig::modexp(y.clone(), parameters.q.clone(), parameters.p.clone()).is_one() {
+            return Err(DsaError::KeyOutOfRange);
+        }
+
+        Ok(Self { parameters, y })
+    }
+
+    pub fn parameters(&self) -> &DsaParameters {
+        &self.parameters
+    }
+
+    pub fn y(&self) -> &Ubig {
+        &self.y
+    }
+
+    pub fn verify<D: Digest>(&self, message: &[u8], signature: &DsaSignature) -> bool {
+        let DsaParameters { p, q, g } = &self.parameters;
+        let DsaSignature { r, s } = signature;
+
+        if !r.not_zero() || r >= q || !s.not_zero() || s >= q {
+            return false;
+        }
+
+        let w = match Ubig::modinv(s.clone(), q.clone()) {
+            Some(w) => w,
+            None => return false,
+        };
+
+        let z = self.parameters.hash_to_scalar::<D>(message);
+
+        let u1 = &z * &w % q;
+        let u2 = r * &w % q;
+
+        let v = Ubig::modexp(g.clone(), u1, p.clone())
+            * Ubig::modexp(self.y.clone(), u2, p.clone())
+            % p
+            % q;
+
+        v == *r
+    }
+}
+
+#[derive(Clone)]
+pub struct DsaPrivateKey {
+    public: DsaPublicKey,
+    x: Ubig,
+}
+
+impl DsaPrivateKey {
+    pub fn generate(parameters: &DsaParameters) -> Self {
+        let x = parameters.random_scalar();
+
+        Self::from_x(parameters.clone(), x).expect("random scalar is in range")
+    }
+
+    pub fn from_x(parameters: DsaParameters, x: Ubig) -> Result<Self, DsaError> {
+        if !x.not_zero() || x >= parameters.q {
+            return Err(DsaError::KeyOutOfRange);
+        }
+
+        let y = Ubig::modexp_constant_time(
+            parameters.g.clone(),
+            x.clone(),
+            parameters.p.clone(),
+            parameters.q.bit_length(),
+        );
+
+        Ok(Self {
+            public: DsaPublicKey { parameters, y },
+            x,
+        })
+    }
+
+    pub fn public_key(&self) -> &DsaPublicKey {
+        &self.public
+    }
+
+    pub fn x(&self) -> &Ubig {
+        &self.x
+    }
+
+    pub fn sign<D: Digest>(&self, message: &[u8]) -> DsaSignature {
+        loop {
+            let k = self.public.parameters.random_scalar();
+
+            /* A nonce giving r = 0 or s = 0 is simply replaced */
+            if let Ok(signature) = self.sign_with_nonce::<D>(message, &k) {
+                return signature;
+            }
+        }
+    }
+
+    /* Signs with a caller-chosen k; reusing or biasing k leaks x */
+    pub fn sign_with_nonce<D: Digest>(
+        &self,
+        message: &[u8],
+        k: &Ubig,
+    ) -> Result<DsaSignature, DsaError> {
+        let DsaParameters { p, q, g } = &self.public.parameters;
+
+        if !k.not_zero() || k >= q {
+            return Err(DsaError::NonceOutOfRange);
+        }
+
+        let r = Ubig::modexp_constant_time(g.clone(), k.clone(), p.clone(), q.bit_length()) % q;
+
+        let k_inverse = Ubig::modinv(k.clone(), q.clone()).ok_or(DsaError::NonceOutOfRange)?;
+        let z = self.public.parameters.hash_to_scalar::<D>(message);
+
+        let s = k_inverse * (z + &self.x * &r) % q;
+
+        if !r.not_zero() || !s.not_zero() {
+            return Err(DsaError::NonceOutOfRange);
+        }
+
+        Ok(DsaSignature { r, s })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha::{Sha1, Sha256};
+
+    /* RFC 6979 A.2.2, DSA with 2048-bit p and 256-bit q */
+    const P: &str = "9db6fb5951b66bb6fe1e140f1d2ce5502374161fd6538df1648218642f0b5c48\
+                     c8f7a41aadfa187324b87674fa1822b00f1ecf8136943d7c55757264e5a1a44f\
+                     fe012e9936e00c1d3e9310b01c7d179805d3058b2a9f4bb6f9716bfe6117c6b5\
+                     b3cc4d9be341104ad4a80ad6c94e005f4b993e14f091eb51743bf33050c38de2\
+                     35567e1b34c3d6a5c0ceaa1a0f368213c3d19843d0b4b09dcb9fc72d39c8de41\
+                     f1bf14d4bb4563ca28371621cad3324b6a2d392145bebfac748805236f5ca2fe\
+                     92b871cd8f9c36d3292b5509ca8caa77a2adfc7bfd77dda6f71125a7456fea15\
+                     3e433256a2261c6a06ed3693797e7995fad5aabbcfbe3eda2741e375404ae25b";
+    const Q: &str = "f2c3119374ce76c9356990b465374a17f23f9ed35089bd969f61c6dde9998c1f";
+    const G: &str = "5c7ff6b06f8f143fe8288433493e4769c4d988ace5be25a0e24809670716c613\
+                     d7b0cee6932f8faa7c44d2cb24523da53fbe4f6ec3595892d1aa58c4328a06c4\
+                     6a15662e7eaa703a1decf8bbb2d05dbe2eb956c142a338661d10461c0d135472\
+                     085057f3494309ffa73c611f78b32adbb5740c361c9f35be90997db2014e2ef5\
+                     aa61782f52abeb8bd6432c4dd097bc5423b285dafb60dc364e8161f4a2a35aca\
+                     3a10b1c4d203cc76a470a33afdcbdd92959859abd8b56e1725252d78eac66e71\
+                     ba9ae3f1dd2487199874393cd4d832186800654760e1e34c09e4d155179f9ec0\
+                     dc4473f996bdce6eed1cabed8b6f116f7ad9cf505df0f998e34ab27514b0ffe7";
+    const X: &str = "69c7548c21d0dfea6b9a51c9ead4e27c33d3b3f180316e5bcab92c933f0e4dbc";
+    const Y: &str = "667098c654426c78d7f8201eac6c203ef030d43605032c2f1fa937e5237dbd94\
+                     9f34a0a2564fe126dc8b715c5141802ce0979c8246463c40e6b6bdaa2513fa61\
+                     1728716c2e4fd53bc95b89e69949d96512e873b9c8f8dfd499cc312882561ade\
+                     cb31f658e934c0c197f2c4d96b05cbad67381e7b768891e4da3843d24d94cdfb\
+                     5126e9b8bf21e8358ee0e0a30ef13fd6a664c0dce3731f7fb49a4845a4fd8254\
+                     687972a2d382599c9bac4e0ed7998193078913032558134976410b89d2c171d1\
+                     23ac35fd977219597aa7d15c1a9a428e59194f75c721ebcbcfae44696a499afa\
+                     74e04299f132026601638cb87ab79190d4a0986315da8eec6561c938996beadf";
+
+    /* Skips validation, which parameters_are_validated covers: testing p is slow without optimizations */
+    fn parameters() -> DsaParameters {
+        DsaParameters {
+            p: Ubig::new(P),
+            q: Ubig::new(Q),
+            g: Ubig::new(G),
+        }
+    }
+
+    fn key() -> DsaPrivateKey {
+        DsaPrivateKey::from_x(parameters(), Ubig::new(X)).unwrap()
+    }
+
+    fn signature(r: &str, s: &str) -> DsaSignature {
+        DsaSignature {
+            r: Ubig::new(r),
+            s: Ubig::new(s),
+        }
+    }
+
+    #[test]
+    fn rfc6979_dsa_2048() {
+        let key = key();
+        let public = key.public_key();
+
+        assert_eq!(public.y(), &Ubig::new(Y));
+
+        let cases = [
+            (
+                b"sample".as_ref(),
+                "8926a27c40484216f052f4427cfd5647338b7b3939bc6573af4333569d597c52",
+                "eace8bdbbe353c432a795d9ec556c6d021f7a03f42c36e9bc87e4ac7932cc809",
+                "7081e175455f9247b812b74583e9e94f9ea79bd640dc962533b0680793a38d53",
+            ),
+            (
+                b"test".as_ref(),
+                "1d6ce6dda1c5d37307839cd03ab0a5cbb18e60d800937d67dfb4479aac8dead7",
+                "8190012a1969f9957d56fccaad223186f423398d58ef5b3cefd5a4146a4476f0",
+                "7452a53f7075d417b4b013b278d1bb8bbd21863f5e7b1cee679cf2188e1ab19e",
+            ),
+        ];
+
+        for (message, k, r, s) in cases {
+            let expected = signature(r, s);
+
+            assert_eq!(
+                key.sign_with_nonce::<Sha256>(message, &Ubig::new(k))
+                    .unwrap(),
+                expected
+            );
+            assert!(public.verify::<Sha256>(message, &expected));
+        }
+
+        /* SHA-1 output is shorter than q, so no truncation happens */
+        let expected = signature(
+            "3a1b2dbd7489d6ed7e608fd036c83af396e290dbd602408e8677daabd6e7445a",
+            "d26fcba19fa3e3058ffc02ca1596cdbb6e0d20cb37b06054f7e36ded0cdbbccf",
+        );
+        let k = Ubig::new("888fa6f7738a41bdc9846466abdb8174c0338250ae50ce955ca16230f9cbd53e");
+
+        assert_eq!(
+            key.sign_with_nonce::<Sha1>(b"sample", &k).unwrap(),
+            expected
+        );
+        assert!(public.verify::<Sha1>(b"sample", &expected));
+    }
+
+    #[test]
+    fn verify_rejects_bad_signatures() {
+        let key = key();
+        let public = key.public_key();
+        let good = key.sign::<Sha256>(b"sample");
+
+        assert!(public.verify::<Sha256>(b"sample", &good));
+        assert!(!public.verify::<Sha256>(b"samples", &good));
+        assert!(!public.verify::<Sha1>(b"sample", &good));
+
+        let zero_r = DsaSignature {
+            r: Ubig::from(0u64),
+            s: good.s.clone(),
+        };
+        assert!(!public.verify::<Sha256>(b"sample", &zero_r));
+
+        let large_s = DsaSignature {
+            r: good.r.clone(),
+            s: &good.s + public.parameters().q(),
+        };
+        assert!(!public.verify::<Sha256>(b"sample", &large_s));
+
+        let q_as_s = DsaSignature {
+            r: good.r.clone(),
+            s: public.parameters().q().clone(),
+        };
+        assert!(!public.verify::<Sha256>(b"sample", &q_as_s));
+    }
+
+    #[test]
+    fn sign_with_nonce_checks_range() {
+        let key = key();
+
+        for k in [Ubig::from(0u64), Ubig::new(Q)] {
+            assert!(matches!(
+                key.sign_with_nonce::<Sha256>(b"sample", &k),
+                Err(DsaError::NonceOutOfRange)
+            ));
+        }
+    }
+
+    #[test]
+    fn parameters_are_validated() {
+        assert!(DsaParameters::new(Ubig::new(P), Ubig::new(Q), Ubig::new(G)).is_ok());
+
+        /* A prime q that does not divide p - 1 */
+        let other_q = (Ubig::from(1u64) << 255) - Ubig::from(19u64);
+
+        assert!(matches!(
+            DsaParameters::new(Ubig::new(P), other_q, Ubig::new(G)),
+            Err(DsaError::InvalidParameters)
+        ));
+
+        /* Out of range, or like 2 of an order other than q */
+        for g in [
+            Ubig::from(0u64),
+            Ubig::from(1u64),
+            Ubig::from(2u64),
+            Ubig::new(P),
+        ] {
+            assert!(matches!(
+                DsaParameters::new(Ubig::new(P), Ubig::new(Q), g),
+                Err(DsaError::InvalidParameters)
+            ));
+        }
+
+        assert!(matches!(
+            DsaPrivateKey::from_x(parameters(), Ubig::new(Q)),
+            Err(DsaError::KeyOutOfRange)
+        ));
+        assert!(matches!(
+            DsaPublicKey::new(parameters(), Ubig::from(2u64)),
+            Err(DsaError::KeyOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn generated_parameters_work() {
+        let parameters = DsaParameters::generate(1024, 160);
+
+        assert_eq!(parameters.p().bit_length(), 1024);
+        assert_eq!(parameters.q().bit_length(), 160);
+        assert!(parameters.validate().is_ok());
+
+        let key = DsaPrivateKey::generate(&parameters);
+        let signature = key.sign::<Sha256>(b"message");
+
+        assert!(key.public_key().verify::<Sha256>(b"message", &signature));
+    }
+}
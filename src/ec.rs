@@ -0,0 +1,697 @@
+use super::prime;
+use super::ubig::Ubig;
+use super::urandom;
+
+#[derive(Debug)]
+pub enum EcError {
+    FieldNotPrime,
+    SingularCurve,
+    GeneratorNotOnCurve,
+    WrongGeneratorOrder,
+    NotOnCurve,
+    NotInSubgroup,
+    PointAtInfinity,
+    InvalidEncoding,
+    ScalarOutOfRange,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Point {
+    Infinity,
+    Affine { x: Ubig, y: Ubig },
+}
+
+impl Point {
+    pub fn new(x: Ubig, y: Ubig) -> Self {
+        Point::Affine { x, y }
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        matches!(self, Point::Infinity)
+    }
+
+    pub fn x(&self) -> Option<&Ubig> {
+        match self {
+            Point::Infinity => None,
+            Point::Affine { x, .. } => Some(x),
+        }
+    }
+
+    pub fn y(&self) -> Option<&Ubig> {
+        match self {
+            Point::Infinity => None,
+            Point::Affine { y, .. } => Some(y),
+        }
+    }
+}
+
+/* (X, Y, Z) stands for (X / Z^2, Y / Z^3), the point at infinity has Z = 0 */
+#[derive(Clone, Debug)]
+pub struct JacobianPoint {
+    x: Ubig,
+    y: Ubig,
+    z: Ubig,
+}
+
+impl JacobianPoint {
+    pub fn infinity() -> Self {
+        Self {
+            x: Ubig::from(1u64),
+            y: Ubig::from(1u64),
+            z: Ubig::from(0u64),
+        }
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        !self.z.not_zero()
+    }
+
+    pub fn conditional_swap(a: &mut Self, b: &mut Self, swap: bool) {
+        Ubig::conditional_swap(&mut a.x, &mut b.x, swap);
+        Ubig::conditional_swap(&mut a.y, &mut b.y, swap);
+        Ubig::conditional_swap(&mut a.z, &mut b.z, swap);
+    }
+}
+
+/* y^2 = x^3 + a * x + b over the prime field of order p */
+#[derive(Clone, Debug)]
+pub struct Curve {
+    p: Ubig,
+    a: Ubig,
+    b: Ubig,
+    generator: Point,
+    order: Ubig,
+    cofactor: Ubig,
+}
+
+impl Curve {
+    pub fn new(
+        p: Ubig,
+        a: Ubig,
+        b: Ubig,
+        generator: Point,
+        order: Ubig,
+        cofactor: Ubig,
+    ) -> Result<Self, EcError> {
+        /* An odd prime, or the reductions below divide by zero and sqrt never finds a non-residue */
+        if !p.bit(0) || !prime::is_probable_prime(&p) {
+            return Err(EcError::FieldNotPrime);
+        }
+
+        let a = &a % &p;
+        let b = &b % &p;
+
+        let curve = Self {
+            p,
+            a,
+            b,
+            generator,
+            order,
+            cofactor,
+        };
+
+        /* 4a^3 + 27b^2 != 0 (mod p) */
+        let a_cubed = curve.mul(&curve.mul(&curve.a, &curve.a), &curve.a);
+        let b_squared = curve.mul(&curve.b, &curve.b);
+        let discriminant = (Ubig::from(4u64) * a_cubed + Ubig::from(27u64) * b_squared) % &curve.p;
+
+        if !discriminant.not_zero() {
+            return Err(EcError::SingularCurve);
+        }
+
+        if curve.generator.is_infinity() || !curve.is_on_curve(&curve.generator) {
+            return Err(EcError::GeneratorNotOnCurve);
+        }
+
+        if !curve.multiply(&curve.order, &curve.generator).is_infinity() {
+            return Err(EcError::WrongGeneratorOrder);
+        }
+
+        Ok(curve)
+    }
+
+    pub fn p256() -> Self {
+        let hex = |s: &str| s.parse::<Ubig>().expect("valid curve constant");
+
+        Self {
+            p: hex("0xffffffff00000001000000000000000000000000ffffffffffffffffffffffff"),
+            a: hex("0xffffffff00000001000000000000000000000000fffffffffffffffffffffffc"),
+            b: hex("0x5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b"),
+            generator: Point::new(
+                hex("0x6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296"),
+                hex("0x4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"),
+            ),
+            order: hex("0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551"),
+            cofactor: Ubig::from(1u64),
+        }
+    }
+
+    pub fn p(&self) -> &Ubig {
+        &self.p
+    }
+
+    pub fn a(&self) -> &Ubig {
+        &self.a
+    }
+
+    pub fn b(&self) -> &Ubig {
+        &self.b
+    }
+
+    pub fn generator(&self) -> &Point {
+        &self.generator
+    }
+
+    pub fn order(&self) -> &Ubig {
+        &self.order
+    }
+
+    pub fn cofactor(&self) -> &Ubig {
+        &self.cofactor
+    }
+
+    pub fn field_size(&self) -> usize {
+        self.p.bit_length().div_ceil(8)
+    }
+
+    /* Uniform in [1, n - 1] */
+    pub fn random_scalar(&self) -> Ubig {
+        let order_minus_one = &self.order - &Ubig::from(1u64);
+        let bytes = urandom::bytes(self.order.bit_length().div_ceil(8) + 8);
+
+        Ubig::from(bytes.as_ref()) % &order_minus_one + Ubig::from(1u64)
+    }
+
+    pub fn is_on_curve(&self, point: &Point) -> bool {
+        match point {
+            Point::Infinity => true,
+            Point::Affine { x, y } => {
+                if x >= &self.p || y >= &self.p {
+                    return false;
+                }
+
+                let left = self.mul(y, y);
+                let right = self.add(&self.mul(&self.mul(x, x), x), &self.mul(&self.a, x));
+
+                left == self.add(&right, &self.b)
+            }
+        }
+    }
+
+    /* Full public key check: on the curve, not infinity and of order n */
+    pub fn validate_point(&self, point: &Point) -> Result<(), EcError> {
+        if point.is_infinity() {
+            return Err(EcError::PointAtInfinity);
+        }
+
+        if !self.is_on_curve(point) {
+            return Err(EcError::NotOnCurve);
+        }
+
+        if !self.cofactor.is_one() && !self.multiply(&self.order, point).is_infinity() {
+            return Err(EcError::NotInSubgroup);
+        }
+
+        Ok(())
+    }
+
+    pub fn negate(&self, point: &Point) -> Point {
+        match point {
+            Point::Infinity => Point::Infinity,
+            Point::Affine { x, y } => Point::new(x.clone(), self.sub(&Ubig::from(0u64), y)),
+        }
+    }
+
+    pub fn add_points(&self, p: &Point, q: &Point) -> Point {
+        self.to_affine(&self.add_jacobian(&self.to_jacobian(p), &self.to_jacobian(q)))
+    }
+
+    pub fn double_point(&self, point: &Point) -> Point {
+        self.to_affine(&self.double_jacobian(&self.to_jacobian(point)))
+    }
+
+    /*
+     * Montgomery ladder over max(bits(n), bits(k)) bits: one addition and one
+     * doubling per bit, with masked swaps instead of branches on the scalar
+     * bits as in EdwardsPoint::multiply. This is still NOT constant-time as a
+     * whole: the Ubig field arithmetic and the special cases in add_jacobian
+     * are variable-time, and ECDSA and ECDH pass secret scalars here. Note
+     * also that b is never used: points from another curve are multiplied
+     * without complaint, which is exactly what an invalid-curve attack relies
+     * on.
+     */
+    pub fn multiply(&self, scalar: &Ubig, point: &Point) -> Point {
+        let bits = std::cmp::max(self.order.bit_length(), scalar.bit_length());
+
+        let mut r0 = JacobianPoint::infinity();
+        let mut r1 = self.to_jacobian(point);
+        let mut swap = false;
+
+        for i in (0..bits).rev() {
+            let bit = scalar.bit(i);
+
+            JacobianPoint::conditional_swap(&mut r0, &mut r1, swap ^ bit);
+            swap = bit;
+
+            r1 = self.add_jacobian(&r0, &r1);
+            r0 = self.double_jacobian(&r0);
+        }
+
+        JacobianPoint::conditional_swap(&mut r0, &mut r1, swap);
+
+        self.to_affine(&r0)
+    }
+
+    pub fn multiply_generator(&self, scalar: &Ubig) -> Point {
+        self.multiply(scalar, &self.generator)
+    }
+
+    pub fn to_jacobian(&self, point: &Point) -> JacobianPoint {
+        match point {
+            Point::Infinity => JacobianPoint::infinity(),
+            Point::Affine { x, y } => JacobianPoint {
+                x: x.clone(),
+                y: y.clone(),
+                z: Ubig::from(1u64),
+            },
+        }
+    }
+
+    pub fn to_affine(&self, point: &JacobianPoint) -> Point {
+        if point.is_infinity() {
+            return Point::Infinity;
+        }
+
+        let z_inverse = self.inverse(&point.z);
+        let z_inverse_squared = self.mul(&z_inverse, &z_inverse);
+
+        Point::new(
+            self.mul(&point.x, &z_inverse_squared),
+            self.mul(&point.y, &self.mul(&z_inverse_squared, &z_inverse)),
+        )
+    }
+
+    pub fn double_jacobian(&self, point: &JacobianPoint) -> JacobianPoint {
+        if point.is_infinity() || !point.y.not_zero() {
+            return JacobianPoint::infinity();
+        }
+
+        let JacobianPoint { x, y, z } = point;
+
+        /* S = 4XY^2, M = 3X^2 + aZ^4 */
+        let y_squared = self.mul(y, y);
+        let s = self.mul(&Ubig::from(4u64), &self.mul(x, &y_squared));
+
+        let z_squared = self.mul(z, z);
+        let m = self.add(
+            &self.mul(&Ubig::from(3u64), &self.mul(x, x)),
+            &self.mul(&self.a, &self.mul(&z_squared, &z_squared)),
+        );
+
+        let x3 = self.sub(&self.mul(&m, &m), &self.add(&s, &s));
+        let y3 = self.sub(
+            &self.mul(&m, &self.sub(&s, &x3)),
+            &self.mul(&Ubig::from(8u64), &self.mul(&y_squared, &y_squared)),
+        );
+        let z3 = self.mul(&Ubig::from(2u64), &self.mul(y, z));
+
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    pub fn add_jacobian(&self, p: &JacobianPoint, q: &JacobianPoint) -> JacobianPoint {
+        if p.is_infinity() {
+            return q.clone();
+        }
+
+        if q.is_infinity() {
+            return p.clone();
+        }
+
+        let p_z_squared = self.mul(&p.z, &p.z);
+        let q_z_squared = self.mul(&q.z, &q.z);
+
+        let u1 = self.mul(&p.x, &q_z_squared);
+        let u2 = self.mul(&q.x, &p_z_squared);
+        let s1 = self.mul(&p.y, &self.mul(&q_z_squared, &q.z));
+        let s2 = self.mul(&q.y, &self.mul(&p_z_squared, &p.z));
+
+        if u1 == u2 {
+            return if s1 == s2 {
+                self.double_jacobian(p)
+            } else {
+                JacobianPoint::infinity()
+            };
+        }
+
+        let h = self.sub(&u2, &u1);
+        let r = self.sub(&s2, &s1);
+
+        let h_squared = self.mul(&h, &h);
+        let h_cubed = self.mul(&h_squared, &h);
+        let u1_h_squared = self.mul(&u1, &h_squared);
+
+        let x3 = self.sub(
+            &self.sub(&self.mul(&r, &r), &h_cubed),
+            &self.add(&u1_h_squared, &u1_h_squared),
+        );
+        let y3 = self.sub(
+            &self.mul(&r, &self.sub(&u1_h_squared, &x3)),
+            &self.mul(&s1, &h_cubed),
+        );
+        let z3 = self.mul(&h, &self.mul(&p.z, &q.z));
+
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    /* SEC1: 0x00 for infinity, 0x04 || x || y, or 0x02/0x03 || x by the parity of y */
+    pub fn encode_point(&self, point: &Point, compressed: bool) -> Vec<u8> {
+        let (x, y) = match point {
+            Point::Infinity => return vec![0x00],
+            Point::Affine { x, y } => (x, y),
+        };
+
+        let mut encoded = Vec::with_capacity(1 + 2 * self.field_size());
+
+        if compressed {
+            encoded.push(if y.bit(0) { 0x03 } else { 0x02 });
+            encoded.extend_from_slice(&self.field_to_bytes(x));
+        } else {
+            encoded.push(0x04);
+            encoded.extend_from_slice(&self.field_to_bytes(x));
+            encoded.extend_from_slice(&self.field_to_bytes(y));
+        }
+
+        encoded
+    }
+
+    pub fn decode_point(&self, encoded: &[u8]) -> Result<Point, EcError> {
+        let size = self.field_size();
+
+        let point = match encoded {
+            [0x00] => return Ok(Point::Infinity),
+            [0x04, rest @ ..] if rest.len() == 2 * size => {
+                let (x, y) = rest.split_at(size);
+
+                Point::new(bytes_to_field(x), bytes_to_field(y))
+            }
+            [prefix @ (0x02 | 0x03), rest @ ..] if rest.len() == size => {
+                let x = bytes_to_field(rest);
+
+                if x >= self.p {
+                    return Err(EcError::InvalidEncoding);
+                }
+
+                let right = self.add(&self.mul(&self.mul(&x, &x), &x), &self.mul(&self.a, &x));
+                let y = self
+                    .sqrt(&self.add(&right, &self.b))
+                    .ok_or(EcError::NotOnCurve)?;
+
+                let y = if y.bit(0) == (*prefix == 0x03) {
+                    y
+                } else {
+                    self.sub(&Ubig::from(0u64), &y)
+                };
+
+                Point::new(x, y)
+            }
+            _ => return Err(EcError::InvalidEncoding),
+        };
+
+        if !self.is_on_curve(&point) {
+            return Err(EcError::NotOnCurve);
+        }
+
+        Ok(point)
+    }
+
+    pub fn field_to_bytes(&self, x: &Ubig) -> Vec<u8> {
+        let mut bytes = x.as_ref().to_vec();
+
+        bytes.resize(self.field_size(), 0);
+        bytes.reverse();
+
+        bytes
+    }
+
+    fn add(&self, a: &Ubig, b: &Ubig) -> Ubig {
+        (a + b) % &self.p
+    }
+
+    fn sub(&self, a: &Ubig, b: &Ubig) -> Ubig {
+        (a + &self.p - b % &self.p) % &self.p
+    }
+
+    fn mul(&self, a: &Ubig, b: &Ubig) -> Ubig {
+        a * b % &self.p
+    }
+
+    fn inverse(&self, a: &Ubig) -> Ubig {
+        Ubig::modinv(a.clone(), self.p.clone()).expect("field element is invertible")
+    }
+
+    /* Tonelli-Shanks */
+    fn sqrt(&self, a: &Ubig) -> Option<Ubig> {
+        let zero = Ubig::from(0u64);
+        let one = Ubig::from(1u64);
+
+        if !a.not_zero() {
+            return Some(zero);
+        }
+
+        let p_minus_one = &self.p - &one;
+        let legendre = |x: &Ubig| Ubig::modexp(x.clone(), &p_minus_one >> 1, self.p.clone());
+
+        if !legendre(a).is_one() {
+            return None;
+        }
+
+        /* p - 1 = q * 2^s with q odd */
+        let mut s = 0;
+        let mut q = p_minus_one.clone();
+
+        while !q.bit(0) {
+            q >>= 1;
+            s += 1;
+        }
+
+        let mut z = Ubig::from(2u64);
+
+        while legendre(&z) != p_minus_one {
+            z += &one;
+        }
+
+        let mut m = s;
+        let mut c = Ubig::modexp(z, q.clone(), self.p.clone());
+        let mut t = Ubig::modexp(a.clone(), q.clone(), self.p.clone());
+        let mut r = Ubig::modexp(a.clone(), (q + &one) >> 1, self.p.clone());
+
+        while !t.is_one() {
+            let mut i = 0;
+            let mut t_power = t.clone();
+
+            while !t_power.is_one() {
+                t_power = self.mul(&t_power, &t_power);
+                i += 1;
+            }
+
+            let mut b = c;
+
+            for _ in 0..m - i - 1 {
+                b = self.mul(&b, &b);
+            }
+
+            m = i;
+            c = self.mul(&b, &b);
+            t = self.mul(&t, &c);
+            r = self.mul(&r, &b);
+        }
+
+        Some(r)
+    }
+}
+
+fn bytes_to_field(bytes: &[u8]) -> Ubig {
+    let mut bytes = bytes.to_vec();
+    bytes.reverse();
+
+    Ubig::from(bytes.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* y^2 = x^3 + x + 6 over F_97 has 116 points: a subgroup of prime order 29 and cofactor 4 */
+    fn small_curve() -> Curve {
+        Curve::new(
+            Ubig::from(97u64),
+            Ubig::from(1u64),
+            Ubig::from(6u64),
+            Point::new(Ubig::from(30u64), Ubig::from(19u64)),
+            Ubig::from(29u64),
+            Ubig::from(4u64),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn sec1_round_trips() {
+        let curve = Curve::p256();
+
+        for point in [
+            curve.generator().clone(),
+            curve.multiply_generator(&curve.random_scalar()),
+            curve.negate(curve.generator()),
+        ] {
+            let uncompressed = curve.encode_point(&point, false);
+            let compressed = curve.encode_point(&point, true);
+
+            assert_eq!(uncompressed.len(), 65);
+            assert_eq!(compressed.len(), 33);
+            assert_eq!(curve.decode_point(&uncompressed).unwrap(), point);
+            assert_eq!(curve.decode_point(&compressed).unwrap(), point);
+        }
+
+        let infinity = curve.encode_point(&Point::Infinity, true);
+        assert_eq!(infinity, [0x00]);
+        assert_eq!(curve.decode_point(&infinity).unwrap(), Point::Infinity);
+    }
+
+    #[test]
+    fn sec1_rejects_bad_encodings() {
+        let curve = Curve::p256();
+        let encoded = curve.encode_point(curve.generator(), false);
+
+        let mut off_curve = encoded.clone();
+        off_curve[64] ^= 1;
+        assert!(matches!(
+            curve.decode_point(&off_curve),
+            Err(EcError::NotOnCurve)
+        ));
+
+        for prefix in [0x01, 0x05, 0x06] {
+            let mut bad_prefix = encoded.clone();
+            bad_prefix[0] = prefix;
+            assert!(matches!(
+                curve.decode_point(&bad_prefix),
+                Err(EcError::InvalidEncoding)
+            ));
+        }
+
+        assert!(matches!(
+            curve.decode_point(&encoded[..64]),
+            Err(EcError::InvalidEncoding)
+        ));
+
+        let mut too_large = vec![0x02];
+        too_large.extend_from_slice(&curve.field_to_bytes(curve.p()));
+        assert!(matches!(
+            curve.decode_point(&too_large),
+            Err(EcError::InvalidEncoding)
+        ));
+    }
+
+    #[test]
+    fn new_rejects_bad_curves() {
+        let small = small_curve();
+        let generator = small.generator().clone();
+
+        for p in [0u64, 2, 91, 96] {
+            assert!(matches!(
+                Curve::new(
+                    Ubig::from(p),
+                    Ubig::from(1u64),
+                    Ubig::from(6u64),
+                    generator.clone(),
+                    Ubig::from(29u64),
+                    Ubig::from(4u64),
+                ),
+                Err(EcError::FieldNotPrime)
+            ));
+        }
+
+        /* y^2 = x^3 is singular */
+        assert!(matches!(
+            Curve::new(
+                Ubig::from(97u64),
+                Ubig::from(0u64),
+                Ubig::from(0u64),
+                Point::new(Ubig::from(1u64), Ubig::from(1u64)),
+                Ubig::from(29u64),
+                Ubig::from(4u64),
+            ),
+            Err(EcError::SingularCurve)
+        ));
+
+        assert!(matches!(
+            Curve::new(
+                Ubig::from(97u64),
+                Ubig::from(1u64),
+                Ubig::from(6u64),
+                Point::new(Ubig::from(30u64), Ubig::from(20u64)),
+                Ubig::from(29u64),
+                Ubig::from(4u64),
+            ),
+            Err(EcError::GeneratorNotOnCurve)
+        ));
+
+        assert!(matches!(
+            Curve::new(
+                Ubig::from(97u64),
+                Ubig::from(1u64),
+                Ubig::from(6u64),
+                generator,
+                Ubig::from(31u64),
+                Ubig::from(4u64),
+            ),
+            Err(EcError::WrongGeneratorOrder)
+        ));
+    }
+
+    #[test]
+    fn validate_point_checks_the_subgroup() {
+        let curve = small_curve();
+
+        assert!(curve.validate_point(curve.generator()).is_ok());
+        assert!(curve
+            .validate_point(&curve.double_point(curve.generator()))
+            .is_ok());
+
+        /* On the curve but of order 116 */
+        let outside = Point::new(Ubig::from(0u64), Ubig::from(43u64));
+        assert!(curve.is_on_curve(&outside));
+        assert!(matches!(
+            curve.validate_point(&outside),
+            Err(EcError::NotInSubgroup)
+        ));
+
+        assert!(matches!(
+            curve.validate_point(&Point::new(Ubig::from(0u64), Ubig::from(42u64))),
+            Err(EcError::NotOnCurve)
+        ));
+        assert!(matches!(
+            curve.validate_point(&Point::Infinity),
+            Err(EcError::PointAtInfinity)
+        ));
+    }
+
+    #[test]
+    fn ladder_matches_repeated_addition() {
+        let curve = small_curve();
+        let generator = curve.generator();
+        let mut expected = Point::Infinity;
+
+        for k in 0..=30u64 {
+            assert_eq!(curve.multiply(&Ubig::from(k), generator), expected);
+            expected = curve.add_points(&expected, generator);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use super::ec::{Curve, EcError, Point};
+use super::ubig::Ubig;
+
+#[derive(Clone)]
+pub struct EcdhKeyPair {
+    curve: Curve,
+    private: Ubig,
+    public: Point,
+}
+
+impl EcdhKeyPair {
+    pub fn generate(curve: &Curve) -> Self {
+        let private = curve.random_scalar();
+
+        Self::from_private(curve.clone(), private).expect("random scalar is in range")
+    }
+
+    pub fn from_private(curve: Curve, private: Ubig) -> Result<Self, EcError> {
+        if !private.not_zero() || &private >= curve.order() {
+            return Err(EcError::ScalarOutOfRange);
+        }
+
+        let public = curve.multiply_generator(&private);
+
+        Ok(Self {
+            curve,
+            private,
+            public,
+        })
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    pub fn private_key(&self) -> &Ubig {
+        &self.private
+    }
+
+    pub fn public_key(&self) -> &Point {
+        &self.public
+    }
+
+    /* The x-coordinate of d * Q, after checking that Q is a valid public key */
+    pub fn shared_secret(&self, peer: &Point) -> Result<Vec<u8>, EcError> {
+        self.curve.validate_point(peer)?;
+
+        self.shared_secret_unchecked(peer)
+    }
+
+    /* Skips peer validation, for invalid-curve and small-subgroup experiments */
+    pub fn shared_secret_unchecked(&self, peer: &Point) -> Result<Vec<u8>, EcError> {
+        match self.curve.multiply(&self.private, peer) {
+            Point::Infinity => Err(EcError::PointAtInfinity),
+            Point::Affine { x, .. } => Ok(self.curve.field_to_bytes(&x)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Ubig {
+        Ubig::new(s)
+    }
+
+    /* NIST CAVS 14.1 KAS ECC CDH primitive, P-256 COUNT = 0 */
+    #[test]
+    fn cavs_p256() {
+        let curve = Curve::p256();
+        let key = EcdhKeyPair::from_private(
+            curve,
+            hex("7d7dc5f71eb29ddaf80d6214632eeae03d9058af1fb6d22ed80badb62bc1a534"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            key.public_key(),
+            &Point::new(
+                hex("ead218590119e8876b29146ff89ca61770c4edbbf97d38ce385ed281d8a6b230"),
+                hex("28af61281fd35e2fa7002523acc85a429cb06ee6648325389f59edfce1405141"),
+            )
+        );
+
+        let peer = Point::new(
+            hex("700c48f77f56584c5cc632ca65640db91b6bacce3a4df6b42ce7cc838833d287"),
+            hex("db71e509e3fd9b060ddb20ba5c51dcc5948d46fbf640dfe0441782cab85fa4ac"),
+        );
+
+        assert_eq!(
+            key.shared_secret(&peer).unwrap(),
+            hex::decode("46fc62106420ff012e54a434fbdd2d25ccc5852060561e68040dd7778997bd7b")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn key_pairs_agree() {
+        let curve = Curve::p256();
+        let alice = EcdhKeyPair::generate(&curve);
+        let bob = EcdhKeyPair::generate(&curve);
+
+        assert_eq!(
+            alice.shared_secret(bob.public_key()).unwrap(),
+            bob.shared_secret(alice.public_key()).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_peers() {
+        let curve = Curve::p256();
+        let key = EcdhKeyPair::generate(&curve);
+
+        let generator = curve.generator();
+        let off_curve = Point::new(
+            generator.x().unwrap().clone(),
+            generator.y().unwrap() + &Ubig::from(1u64),
+        );
+
+        assert!(matches!(
+            key.shared_secret(&off_curve),
+            Err(EcError::NotOnCurve)
+        ));
+        assert!(key.shared_secret_unchecked(&off_curve).is_ok());
+
+        /* Validation turns infinity away up front; unchecked gets as far as d * O = O, which has no x */
+        assert!(matches!(
+            key.shared_secret(&Point::Infinity),
+            Err(EcError::PointAtInfinity)
+        ));
+        assert!(matches!(
+            key.shared_secret_unchecked(&Point::Infinity),
+            Err(EcError::PointAtInfinity)
+        ));
+    }
+
+    #[test]
+    fn small_subgroup_peers() {
+        /* y^2 = x^3 + x + 6 over F_97, cofactor 4 */
+        let curve = Curve::new(
+            Ubig::from(97u64),
+            Ubig::from(1u64),
+            Ubig::from(6u64),
+            Point::new(Ubig::from(30u64), Ubig::from(19u64)),
+            Ubig::from(29u64),
+            Ubig::from(4u64),
+        )
+        .unwrap();
+        let key = EcdhKeyPair::from_private(curve, Ubig::from(5u64)).unwrap();
+        let outside = Point::new(Ubig::from(0u64), Ubig::from(43u64));
+
+        assert!(matches!(
+            key.shared_secret(&outside),
+            Err(EcError::NotInSubgroup)
+        ));
+        assert!(key.shared_secret_unchecked(&outside).is_ok());
+    }
+
+    #[test]
+    fn from_private_checks_range() {
+        let curve = Curve::p256();
+
+        for private in [Ubig::from(0u64), curve.order().clone()] {
+            assert!(matches!(
+                EcdhKeyPair::from_private(curve.clone(), private),
+                Err(EcError::ScalarOutOfRange)
+            ));
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use super::digest::Digest;
+use super::ec::{Curve, EcError, Point};
+use super::hmac::Hmac;
+use super::ubig::Ubig;
+
+#[derive(Debug)]
+pub enum EcdsaError {
+    KeyOutOfRange,
+    NonceOutOfRange,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct EcdsaSignature {
+    pub r: Ubig,
+    pub s: Ubig,
+}
+
+impl EcdsaSignature {
+    /* (r, s) and (r, n - s) are both valid, low-S keeps the smaller one */
+    pub fn is_low_s(&self, curve: &Curve) -> bool {
+        self.s <= curve.order() >> 1
+    }
+
+    pub fn normalize_s(&self, curve: &Curve) -> Self {
+        if self.is_low_s(curve) {
+            return self.clone();
+        }
+
+        Self {
+            r: self.r.clone(),
+            s: curve.order() - &self.s,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EcdsaPublicKey {
+    curve: Curve,
+    point: Point,
+}
+
+impl EcdsaPublicKey {
+    pub fn new(curve: Curve, point: Point) -> Result<Self, EcError> {
+        curve.validate_point(&point)?;
+
+        Ok(Self { curve, point })
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    pub fn point(&self) -> &Point {
+        &self.point
+    }
+
+    pub fn verify<D: Digest>(&self, message: &[u8], signature: &EcdsaSignature) -> bool {
+        let n = self.curve.order();
+        let EcdsaSignature { r, s } = signature;
+
+        if !r.not_zero() || r >= n || !s.not_zero() || s >= n {
+            return false;
+        }
+
+        let w = match Ubig::modinv(s.clone(), n.clone()) {
+            Some(w) => w,
+            None => return false,
+        };
+
+        let e = hash_to_scalar::<D>(&self.curve, message);
+
+        let u1 = &e * &w % n;
+        let u2 = r * &w % n;
+
+        let point = self.curve.add_points(
+            &self.curve.multiply_generator(&u1),
+            &self.curve.multiply(&u2, &self.point),
+        );
+
+        match point {
+            Point::Infinity => false,
+            Point::Affine { x, .. } => x % n == *r,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EcdsaPrivateKey {
+    public: EcdsaPublicKey,
+    d: Ubig,
+}
+
+impl EcdsaPrivateKey {
+    pub fn generate(curve: &Curve) -> Self {
+        let d = curve.random_scalar();
+
+        Self::from_d(curve.clone(), d).expect("random scalar is in range")
+    }
+
+    pub fn from_d(curve: Curve, d: Ubig) -> Result<Self, EcdsaError> {
+        if !d.not_zero() || &d >= curve.order() {
+            return Err(EcdsaError::KeyOutOfRange);
+        }
+
+        let point = curve.multiply_generator(&d);
+
+        Ok(Self {
+            public: EcdsaPublicKey { curve, point },
+            d,
+        })
+    }
+
+    pub fn public_key(&self) -> &EcdsaPublicKey {
+        &self.public
+    }
+
+    pub fn d(&self) -> &Ubig {
+        &self.d
+    }
+
+    /* Deterministic nonces as in RFC 6979, with HMAC over the same digest */
+    pub fn sign<D: Digest>(&self, message: &[u8], low_s: bool) -> EcdsaSignature {
+        let curve = &self.public.curve;
+        let n = curve.order();
+
+        let hash = D::digest(message);
+        let h1 = bits2int(hash.as_ref(), n.bit_length()) % n;
+
+        let mut nonces = Rfc6979::<D>::new(&int2octets(&self.d, n), &int2octets(&h1, n));
+
+        loop {
+            let k = nonces.next_nonce(n);
+
+            if let Ok(signature) = self.sign_with_nonce::<D>(message, &k) {
+                return if low_s {
+                    signature.normalize_s(curve)
+                } else {
+                    signature
+                };
+            }
+        }
+    }
+
+    /* Signs with a caller-chosen k; reusing or biasing k leaks d */
+    pub fn sign_with_nonce<D: Digest>(
+        &self,
+        message: &[u8],
+        k: &Ubig,
+    ) -> Result<EcdsaSignature, EcdsaError> {
+        let curve = &self.public.curve;
+        let n = curve.order();
+
+        if !k.not_zero() || k >= n {
+            return Err(EcdsaError::NonceOutOfRange);
+        }
+
+        let r = match curve.multiply_generator(k) {
+            Point::Infinity => return Err(EcdsaError::NonceOutOfRange),
+            Point::Affine { x, .. } => x % n,
+        };
+
+        let k_inverse = Ubig::modinv(k.clone(), n.clone()).ok_or(EcdsaError::NonceOutOfRange)?;
+        let e = hash_to_scalar::<D>(curve, message);
+
+        let s = k_inverse * (e + &self.d * &r) % n;
+
+        if !r.not_zero() || !s.not_zero() {
+            return Err(EcdsaError::NonceOutOfRange);
+        }
+
+        Ok(EcdsaSignature { r, s })
+    }
+}
+
+struct Rfc6979<D: Digest> {
+    k: Vec<u8>,
+    v: Vec<u8>,
+    first: bool,
+    digest: std::marker::PhantomData<D>,
+}
+
+impl<D: Digest> Rfc6979<D> {
+    fn new(private: &[u8], hash: &[u8]) -> Self {
+        let mut generator = Self {
+            k: vec![0x00; D::OUTPUT_SIZE],
+            v: vec![0x01; D::OUTPUT_SIZE],
+            first: true,
+            digest: std::marker::PhantomData,
+        };
+
+        for separator in [0x00, 0x01] {
+            generator.k = generator.mac(&[&generator.v, &[separator], private, hash]);
+            generator.v = generator.mac(&[&generator.v]);
+        }
+
+        generator
+    }
+
+    fn next_nonce(&mut self, n: &Ubig) -> Ubig {
+        let q_len = n.bit_length();
+
+        loop {
+            /* Every candidate after the first reseeds K and V */
+            if !self.first {
+                self.k = self.mac(&[&self.v, &[0x00]]);
+                self.v = self.mac(&[&self.v]);
+            }
+
+            self.first = false;
+
+            let mut t = Vec::with_capacity(q_len.div_ceil(8) + D::OUTPUT_SIZE);
+
+            while 8 * t.len() < q_len {
+                self.v = self.mac(&[&self.v]);
+                t.extend_from_slice(&self.v);
+            }
+
+            let k = bits2int(&t, q_len);
+
+            if k.not_zero() && &k < n {
+                return k;
+            }
+        }
+    }
+
+    fn mac(&self, parts: &[&[u8]]) -> Vec<u8> {
+        let mut mac = Hmac::<D>::new(&self.k);
+
+        for part in parts {
+            mac.update(part);
+        }
+
+        mac.finalize().as_ref().to_vec()
+    }
+}
+
+/* The leftmost bits(n) bits of the hash */
+fn hash_to_scalar<D: Digest>(curve: &Curve, message: &[u8]) -> Ubig {
+    bits2int(D::digest(message).as_ref(), curve.order().bit_length())
+}
+
+fn bits2int(bytes: &[u8], q_len: usize) -> Ubig {
+    let mut reversed = bytes.to_vec();
+    reversed.reverse();
+
+    let x = Ubig::from(reversed.as_ref());
+
+    if 8 * bytes.len() > q_len {
+        x >> (8 * bytes.len() - q_len) as u32
+    } else {
+        x
+    }
+}
+
+fn int2octets(x: &Ubig, n: &Ubig) -> Vec<u8> {
+    let mut bytes = x.as_ref().to_vec();
+
+    bytes.resize(n.bit_length().div_ceil(8), 0);
+    bytes.reverse();
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha::Sha256;
+
+    fn u(s: &str) -> Ubig {
+        format!("0x{}", s).parse().unwrap()
+    }
+
+    fn signature(r: &str, s: &str) -> EcdsaSignature {
+        EcdsaSignature { r: u(r), s: u(s) }
+    }
+
+    /* RFC 6979, appendix A.2.5 */
+    fn rfc6979_key() -> EcdsaPrivateKey {
+        EcdsaPrivateKey::from_d(
+            Curve::p256(),
+            u("C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rfc6979_p256_sha256() {
+        let key = rfc6979_key();
+
+        assert_eq!(
+            key.public_key().point(),
+            &Point::new(
+                u("60FED4BA255A9D31C961EB74C6356D68C049B8923B61FA6CE669622E60F29FB6"),
+                u("7903FE1008B8BC99A41AE9E95628BC64F2F1B20C2D7E9F5177A3C294D4462299"),
+            )
+        );
+
+        let cases = [
+            (
+                &b"sample"[..],
+                "A6E3C57DD01ABE90086538398355DD4C3B17AA873382B0F24D6129493D8AAD60",
+                "EFD48B2AACB6A8FD1140DD9CD45E81D69D2C877B56AAF991C34D0EA84EAF3716",
+                "F7CB1C942D657C41D436C7A1B6E29F65F3E900DBB9AFF4064DC4AB2F843ACDA8",
+            ),
+            (
+                &b"test"[..],
+                "D16B6AE827F17175E040871A1C7EC3500192C4C92677336EC2537ACAEE0008E0",
+                "F1ABB023518351CD71D881567B1EA663ED3EFCF6C5132B354F28D3B0B7D38367",
+                "019F4113742A2B14BD25926B49C649155F267E60D3814B4C0CC84250E46F0083",
+            ),
+        ];
+
+        for (message, k, r, s) in cases {
+            let expected = signature(r, s);
+
+            assert_eq!(key.sign::<Sha256>(message, false), expected);
+            assert_eq!(
+                key.sign_with_nonce::<Sha256>(message, &u(k)).unwrap(),
+                expected
+            );
+            assert!(key.public_key().verify::<Sha256>(message, &expected));
+        }
+    }
+
+    #[test]
+    fn normalize_s() {
+        let curve = Curve::p256();
+        let key = rfc6979_key();
+
+        /* The "sample" signature has a high s, the "test" one a low s */
+        let high = key.sign::<Sha256>(b"sample", false);
+        let low = high.normalize_s(&curve);
+
+        assert!(!high.is_low_s(&curve));
+        assert!(low.is_low_s(&curve));
+        assert_eq!(low.r, high.r);
+        assert_eq!(&low.s + &high.s, curve.order().clone());
+        assert_eq!(key.sign::<Sha256>(b"sample", true), low);
+        assert!(key.public_key().verify::<Sha256>(b"sample", &low));
+
+        let already_low = key.sign::<Sha256>(b"test", false);
+
+        assert!(already_low.is_low_s(&curve));
+        assert_eq!(already_low.normalize_s(&curve), already_low);
+        assert_eq!(key.sign::<Sha256>(b"test", true), already_low);
+    }
+
+    #[test]
+    fn verify_rejects_bad_signatures() {
+        let curve = Curve::p256();
+        let key = rfc6979_key();
+        let public = key.public_key();
+
+        let good = key.sign::<Sha256>(b"sample", false);
+
+        assert!(!public.verify::<Sha256>(b"samplf", &good));
+        assert!(!public.verify::<Sha256>(b"sample", &signature("0", "1")));
+        assert!(!public.verify::<Sha256>(
+            b"sample",
+            &EcdsaSignature {
+                r: good.r.clone(),
+                s: &good.s + curve.order(),
+            }
+        ));
+        assert!(key
+            .sign_with_nonce::<Sha256>(b"sample", curve.order())
+            .is_err());
+    }
+}
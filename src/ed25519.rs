@@ -0,0 +1,452 @@
+use super::curve25519::FieldElement;
+use super::digest::Digest;
+use super::sha::Sha512;
+use super::ubig::Ubig;
+use super::urandom;
+use super::x25519;
+
+/* d = -121665 / 121666, little-endian */
+const D: [u8; 32] = [
+    0xa3, 0x78, 0x59, 0x13, 0xca, 0x4d, 0xeb, 0x75, 0xab, 0xd8, 0x41, 0x41, 0x4d, 0x0a, 0x70, 0x00,
+    0x98, 0xe8, 0x79, 0x77, 0x79, 0x40, 0xc7, 0x8c, 0x73, 0xfe, 0x6f, 0x2b, 0xee, 0x6c, 0x03, 0x52,
+];
+
+#[derive(Debug)]
+pub enum Ed25519Error {
+    InvalidPoint,
+}
+
+/* Extended coordinates on -x^2 + y^2 = 1 + d x^2 y^2: x = X / Z, y = Y / Z, x * y = T / Z */
+#[derive(Clone, Debug)]
+pub struct EdwardsPoint {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement,
+    t: FieldElement,
+}
+
+impl EdwardsPoint {
+    pub fn identity() -> Self {
+        Self {
+            x: FieldElement::zero(),
+            y: FieldElement::one(),
+            z: FieldElement::one(),
+            t: FieldElement::zero(),
+        }
+    }
+
+    /* y = 4/5 with the even x */
+    pub fn base_point() -> Self {
+        let four = FieldElement::new(Ubig::from(4u64));
+        let five = FieldElement::new(Ubig::from(5u64));
+
+        let mut encoded = (&four * &five.invert()).to_bytes();
+        encoded[31] &= 0x7f;
+
+        Self::decode(&encoded).expect("base point is on the curve")
+    }
+
+    pub fn from_affine(x: FieldElement, y: FieldElement) -> Self {
+        let t = &x * &y;
+
+        Self {
+            x,
+            y,
+            z: FieldElement::one(),
+            t,
+        }
+    }
+
+    /* Complete addition, also valid for doubling */
+    pub fn add(&self, other: &Self) -> Self {
+        let d2 = &d() + &d();
+
+        let a = &(&self.y - &self.x) * &(&other.y - &other.x);
+        let b = &(&self.y + &self.x) * &(&other.y + &other.x);
+        let c = &(&self.t * &d2) * &other.t;
+        let zz = &self.z * &other.z;
+        let d = &zz + &zz;
+
+        let e = &b - &a;
+        let f = &d - &c;
+        let g = &d + &c;
+        let h = &b + &a;
+
+        Self {
+            x: &e * &f,
+            y: &g * &h,
+            z: &f * &g,
+            t: &e * &h,
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        self.add(self)
+    }
+
+    pub fn negate(&self) -> Self {
+        Self {
+            x: -&self.x,
+            y: self.y.clone(),
+            z: self.z.clone(),
+            t: -&self.t,
+        }
+    }
+
+    /*
+     * Montgomery ladder with masked swaps instead of branches on the scalar
+     * bits, as in X25519. The field arithmetic is variable-time Ubig code, so
+     * this is not constant-time as a whole.
+     */
+    pub fn multiply(&self, scalar: &Ubig) -> Self {
+        let bits = std::cmp::max(253, scalar.bit_length());
+
+        let mut r0 = Self::identity();
+        let mut r1 = self.clone();
+        let mut swap = false;
+
+        for i in (0..bits).rev() {
+            let bit = scalar.bit(i);
+
+            Self::conditional_swap(&mut r0, &mut r1, swap ^ bit);
+            swap = bit;
+
+            r1 = r0.add(&r1);
+            r0 = r0.double();
+        }
+
+        Self::conditional_swap(&mut r0, &mut r1, swap);
+
+        r0
+    }
+
+    pub fn conditional_swap(a: &mut Self, b: &mut Self, swap: bool) {
+        FieldElement::conditional_swap(&mut a.x, &mut b.x, swap);
+        FieldElement::conditional_swap(&mut a.y, &mut b.y, swap);
+        FieldElement::conditional_swap(&mut a.z, &mut b.z, swap);
+        FieldElement::conditional_swap(&mut a.t, &mut b.t, swap);
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.x.is_zero() && self.y == self.z
+    }
+
+    /* y in little-endian with the sign of x in the top bit */
+    pub fn encode(&self) -> [u8; 32] {
+        let z_inverse = self.z.invert();
+
+        let x = &self.x * &z_inverse;
+        let y = &self.y * &z_inverse;
+
+        let mut encoded = y.to_bytes();
+
+        if x.is_negative() {
+            encoded[31] |= 0x80;
+        }
+
+        encoded
+    }
+
+    pub fn decode(encoded: &[u8; 32]) -> Result<Self, Ed25519Error> {
+        let x_negative = encoded[31] & 0x80 != 0;
+
+        let mut y_bytes = *encoded;
+        y_bytes[31] &= 0x7f;
+
+        /* Non-canonical encodings with y >= p are rejected */
+        if Ubig::from(y_bytes.as_ref()) >= FieldElement::prime() {
+            return Err(Ed25519Error::InvalidPoint);
+        }
+
+        let y = FieldElement::from_bytes(&y_bytes);
+
+        /* x^2 = (y^2 - 1) / (d y^2 + 1) */
+        let y_squared = y.square();
+        let u = &y_squared - &FieldElement::one();
+        let v = &(&d() * &y_squared) + &FieldElement::one();
+
+        let mut x = FieldElement::sqrt_ratio(&u, &v).ok_or(Ed25519Error::InvalidPoint)?;
+
+        if x.is_zero() && x_negative {
+            return Err(Ed25519Error::InvalidPoint);
+        }
+
+        if x.is_negative() != x_negative {
+            x = -&x;
+        }
+
+        Ok(Self::from_affine(x, y))
+    }
+}
+
+impl PartialEq for EdwardsPoint {
+    /* Projective comparison, X1 Z2 = X2 Z1 and Y1 Z2 = Y2 Z1 */
+    fn eq(&self, other: &Self) -> bool {
+        &self.x * &other.z == &other.x * &self.z && &self.y * &other.z == &other.y * &self.z
+    }
+}
+
+#[derive(Clone)]
+pub struct Ed25519PublicKey {
+    point: EdwardsPoint,
+    encoded: [u8; 32],
+}
+
+impl Ed25519PublicKey {
+    pub fn from_bytes(encoded: &[u8; 32]) -> Result<Self, Ed25519Error> {
+        let point = EdwardsPoint::decode(encoded)?;
+
+        Ok(Self {
+            point,
+            encoded: *encoded,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.encoded
+    }
+
+    pub fn point(&self) -> &EdwardsPoint {
+        &self.point
+    }
+
+    /* Cofactored check [8][S]B = [8]R + [8][k]A */
+    pub fn verify(&self, message: &[u8], signature: &[u8; 64]) -> bool {
+        let mut r_bytes = [0u8; 32];
+        r_bytes.copy_from_slice(&signature[..32]);
+
+        let r = match EdwardsPoint::decode(&r_bytes) {
+            Ok(r) => r,
+            Err(_) => return false,
+        };
+
+        let s = Ubig::from(&signature[32..]);
+
+        if s >= group_order() {
+            return false;
+        }
+
+        let k = challenge(&r_bytes, &self.encoded, message);
+
+        let left = EdwardsPoint::base_point().multiply(&s);
+        let right = r.add(&self.point.multiply(&k));
+
+        let eight = Ubig::from(8u64);
+
+        left.multiply(&eight) == right.multiply(&eight)
+    }
+}
+
+#[derive(Clone)]
+pub struct Ed25519PrivateKey {
+    seed: [u8; 32],
+    scalar: Ubig,
+    prefix: [u8; 32],
+    public: Ed25519PublicKey,
+}
+
+impl Ed25519PrivateKey {
+    pub fn generate() -> Self {
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&urandom::bytes(32));
+
+        Self::from_seed(&seed)
+    }
+
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        let hash = Sha512::digest(seed);
+
+        let mut scalar_bytes = [0u8; 32];
+        scalar_bytes.copy_from_slice(&hash[..32]);
+
+        let mut prefix = [0u8; 32];
+        prefix.copy_from_slice(&hash[32..]);
+
+        let scalar = Ubig::from(x25519::clamp(&scalar_bytes).as_ref());
+        let point = EdwardsPoint::base_point().multiply(&scalar);
+
+        Self {
+            seed: *seed,
+            scalar,
+            prefix,
+            public: Ed25519PublicKey {
+                encoded: point.encode(),
+                point,
+            },
+        }
+    }
+
+    pub fn seed(&self) -> &[u8; 32] {
+        &self.seed
+    }
+
+    pub fn public_key(&self) -> &Ed25519PublicKey {
+        &self.public
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        let l = group_order();
+
+        let mut hasher = Sha512::new();
+        hasher.update(&self.prefix);
+        hasher.update(message);
+
+        let r = Ubig::from(hasher.finalize().as_ref()) % &l;
+        let r_bytes = EdwardsPoint::base_point().multiply(&r).encode();
+
+        let k = challenge(&r_bytes, &self.public.encoded, message);
+        let s = (r + k * &self.scalar) % &l;
+
+        let mut signature = [0u8; 64];
+        let s_bytes = s.as_ref();
+
+        signature[..32].copy_from_slice(&r_bytes);
+        signature[32..32 + s_bytes.len()].copy_from_slice(s_bytes);
+
+        signature
+    }
+}
+
+/* L = 2^252 + 27742317777372353535851937790883648493 */
+fn group_order() -> Ubig {
+    (Ubig::from(1u64) << 252) + Ubig::from(27742317777372353535851937790883648493u128)
+}
+
+fn d() -> FieldElement {
+    FieldElement::from_bytes(&D)
+}
+
+fn challenge(r: &[u8; 32], public: &[u8; 32], message: &[u8]) -> Ubig {
+    let mut hasher = Sha512::new();
+    hasher.update(r);
+    hasher.update(public);
+    hasher.update(message);
+
+    Ubig::from(hasher.finalize().as_ref()) % group_order()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    /* RFC 8032, section 7.1: TEST 1, 2, 3 and SHA(abc) */
+    const VECTORS: [(&str, &str, &str, &str); 4] = [
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+             5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+             085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+        (
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac\
+             18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+        ),
+        (
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b589\
+             09351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704",
+        ),
+    ];
+
+    #[test]
+    fn rfc8032_vectors() {
+        for (seed, public, message, signature) in VECTORS {
+            let key = Ed25519PrivateKey::from_seed(&h(seed).try_into().unwrap());
+            let signature: [u8; 64] = h(signature).try_into().unwrap();
+
+            assert_eq!(key.public_key().to_bytes().to_vec(), h(public));
+            assert_eq!(key.sign(&h(message)), signature);
+            assert!(key.public_key().verify(&h(message), &signature));
+            assert!(!key.public_key().verify(b"other", &signature));
+        }
+    }
+
+    fn with_s(signature: &[u8; 64], s: &Ubig) -> [u8; 64] {
+        let mut replaced = *signature;
+        let s_bytes = s.as_ref();
+
+        replaced[32..].fill(0);
+        replaced[32..32 + s_bytes.len()].copy_from_slice(s_bytes);
+
+        replaced
+    }
+
+    #[test]
+    fn verify_rejects_s_at_or_above_l() {
+        let (seed, _, message, signature) = VECTORS[1];
+
+        let key = Ed25519PrivateKey::from_seed(&h(seed).try_into().unwrap());
+        let signature: [u8; 64] = h(signature).try_into().unwrap();
+
+        /* S + L is congruent to S, so only the range check catches it */
+        let s = Ubig::from(&signature[32..]);
+
+        for s in [&s + &group_order(), group_order()] {
+            assert!(!key
+                .public_key()
+                .verify(&h(message), &with_s(&signature, &s)));
+        }
+
+        assert!(key
+            .public_key()
+            .verify(&h(message), &with_s(&signature, &s)));
+    }
+
+    #[test]
+    fn non_canonical_encodings_are_rejected() {
+        /* y = p + 1 would otherwise decode as the identity */
+        let mut p_plus_one = [0xff; 32];
+        p_plus_one[0] = 0xee;
+        p_plus_one[31] = 0x7f;
+
+        assert!(EdwardsPoint::decode(&p_plus_one).is_err());
+        assert!(Ed25519PublicKey::from_bytes(&p_plus_one).is_err());
+
+        /* x = 0 with the sign bit set */
+        let mut negative_zero = [0u8; 32];
+        negative_zero[0] = 1;
+        negative_zero[31] = 0x80;
+
+        assert!(EdwardsPoint::decode(&negative_zero).is_err());
+
+        /* A signature whose R is non-canonical is rejected rather than reduced */
+        let (seed, _, message, signature) = VECTORS[0];
+        let key = Ed25519PrivateKey::from_seed(&h(seed).try_into().unwrap());
+
+        let mut signature: [u8; 64] = h(signature).try_into().unwrap();
+        signature[..32].copy_from_slice(&p_plus_one);
+
+        assert!(!key.public_key().verify(&h(message), &signature));
+    }
+
+    #[test]
+    fn ladder_matches_repeated_addition() {
+        let base = EdwardsPoint::base_point();
+        let mut sum = EdwardsPoint::identity();
+
+        for k in 0u64..20 {
+            assert_eq!(base.multiply(&Ubig::from(k)), sum);
+            sum = sum.add(&base);
+        }
+
+        assert!(base.multiply(&group_order()).is_identity());
+    }
+}
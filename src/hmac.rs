@@ -0,0 +1,263 @@
+use super::constant_time;
+use super::digest::Digest;
+
+#[derive(Clone)]
+pub struct Hmac<D: Digest> {
+    inner: D,
+    outer: D,
+}
+
+impl<D: Digest> Hmac<D> {
+    pub fn new(key: &[u8]) -> Self {
+        let mut block_key = vec![0; D::BLOCK_SIZE];
+
+        if key.len() > D::BLOCK_SIZE {
+            let key_hash = D::digest(key);
+            block_key[..D::OUTPUT_SIZE].copy_from_slice(key_hash.as_ref());
+        } else {
+            block_key[..key.len()].copy_from_slice(key);
+        }
+
+        let ipad: Vec<u8> = block_key.iter().map(|byte| byte ^ 0x36).collect();
+        let opad: Vec<u8> = block_key.iter().map(|byte| byte ^ 0x5c).collect();
+
+        let mut inner = D::new();
+        inner.update(&ipad);
+
+        let mut outer = D::new();
+        outer.update(&opad);
+
+        Self { inner, outer }
+    }
+
+    pub fn update(&mut self, input: &[u8]) {
+        self.inner.update(input);
+    }
+
+    pub fn finalize(mut self) -> D::Output {
+        let inner_hash = self.inner.finalize();
+        self.outer.update(inner_hash.as_ref());
+
+        self.outer.finalize()
+    }
+
+    pub fn verify(self, tag: &[u8]) -> bool {
+        constant_time::eq(self.finalize().as_ref(), tag)
+    }
+}
+
+pub fn hmac<D: Digest>(key: &[u8], input: &[u8]) -> D::Output {
+    let mut mac = Hmac::<D>::new(key);
+    mac.update(input);
+
+    mac.finalize()
+}
+
+pub fn hmac_verify<D: Digest>(key: &[u8], input: &[u8], tag: &[u8]) -> bool {
+    let mut mac = Hmac::<D>::new(key);
+    mac.update(input);
+
+    mac.verify(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha::{Sha1, Sha224, Sha256, Sha384, Sha512};
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    /* Compares a prefix, for the truncated test cases */
+    fn check<D: Digest>(key: &[u8], message: &[u8], expected: &str) {
+        let expected = h(expected);
+
+        assert_eq!(hmac::<D>(key, message).as_ref()[..expected.len()], expected);
+    }
+
+    /* RFC 2202, section 3 */
+    #[test]
+    fn hmac_sha1() {
+        let cases: [(Vec<u8>, Vec<u8>, &str); 7] = [
+            (
+                vec![0x0b; 20],
+                b"Hi There".to_vec(),
+                "b617318655057264e28bc0b6fb378c8ef146be00",
+            ),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?".to_vec(),
+                "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            ),
+            (
+                vec![0xaa; 20],
+                vec![0xdd; 50],
+                "125d7342b9ac11cd91a39af48aa17b4f63f175d3",
+            ),
+            (
+                (1..=25).collect(),
+                vec![0xcd; 50],
+                "4c9007f4026250c6bc8414f9bf50c86c2d7235da",
+            ),
+            (
+                vec![0x0c; 20],
+                b"Test With Truncation".to_vec(),
+                "4c1a03424b55e07fe7f27be1d58bb9324a9a5a04",
+            ),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+                "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            ),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data"
+                    .to_vec(),
+                "e8e99d0f45237d786d6bbaa7965c7808bbff1a91",
+            ),
+        ];
+
+        for (key, message, expected) in cases {
+            check::<Sha1>(&key, &message, expected);
+        }
+    }
+
+    /* RFC 4231, section 4; test case 5 is truncated to 128 bits */
+    #[test]
+    fn hmac_sha2() {
+        let cases: [(Vec<u8>, Vec<u8>, [&str; 4]); 7] = [
+            (
+                vec![0x0b; 20],
+                b"Hi There".to_vec(),
+                [
+                    "896fb1128abbdf196832107cd49df33f47b4b1169912ba4f53684b22",
+                    "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+                    "afd03944d84895626b0825f4ab46907f15f9dadbe4101ec682aa034c7cebc59cfaea9ea9076ede7f4af152e8b2fa9cb6",
+                    "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+                ],
+            ),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?".to_vec(),
+                [
+                    "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44",
+                    "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+                    "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649",
+                    "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+                ],
+            ),
+            (
+                vec![0xaa; 20],
+                vec![0xdd; 50],
+                [
+                    "7fb3cb3588c6c1f6ffa9694d7d6ad2649365b0c1f65d69d1ec8333ea",
+                    "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+                    "88062608d3e6ad8a0aa2ace014c8a86f0aa635d947ac9febe83ef4e55966144b2a5ab39dc13814b94e3ab6e101a34f27",
+                    "fa73b0089d56a284efb0f0756c890be9b1b5dbdd8ee81a3655f83e33b2279d39bf3e848279a722c806b485a47e67c807b946a337bee8942674278859e13292fb",
+                ],
+            ),
+            (
+                (1..=25).collect(),
+                vec![0xcd; 50],
+                [
+                    "6c11506874013cac6a2abc1bb382627cec6a90d86efc012de7afec5a",
+                    "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+                    "3e8a69b7783c25851933ab6290af6ca77a9981480850009cc5577c6e1f573b4e6801dd23c4a7d679ccf8a386c674cffb",
+                    "b0ba465637458c6990e5a8c5f61d4af7e576d97ff94b872de76f8050361ee3dba91ca5c11aa25eb4d679275cc5788063a5f19741120c4f2de2adebeb10a298dd",
+                ],
+            ),
+            (
+                vec![0x0c; 20],
+                b"Test With Truncation".to_vec(),
+                [
+                    "0e2aea68a90c8d37c988bcdb9fca6fa8",
+                    "a3b6167473100ee06e0c796c2955552b",
+                    "3abf34c3503b2a23a46efc619baef897",
+                    "415fad6271580a531d4179bc891d87a6",
+                ],
+            ),
+            (
+                vec![0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+                [
+                    "95e9a0db962095adaebe9b2d6f0dbce2d499f112f2d2b7273fa6870e",
+                    "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+                    "4ece084485813e9088d2c63a041bc5b44f9ef1012a2b588f3cd11f05033ac4c60c2ef6ab4030fe8296248df163f44952",
+                    "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+                ],
+            ),
+            (
+                vec![0xaa; 131],
+                b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm.".to_vec(),
+                [
+                    "3a854166ac5d9f023f54d517d0b39dbd946770db9c2b95c9f6f565d1",
+                    "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+                    "6617178e941f020d351e2f254e8fd32c602420feb0b8fb9adccebb82461e99c5a678cc31e799176d3860e6110c46523e",
+                    "e37b6a775dc87dbaa4dfa9f96e5e3ffddebd71f8867289865df5a32d20cdc944b6022cac3c4982b10d5eeb55c3e4de15134676fb6de0446065c97440fa8c6a58",
+                ],
+            ),
+        ];
+
+        for (key, message, [sha224, sha256, sha384, sha512]) in cases {
+            check::<Sha224>(&key, &message, sha224);
+            check::<Sha256>(&key, &message, sha256);
+            check::<Sha384>(&key, &message, sha384);
+            check::<Sha512>(&key, &message, sha512);
+        }
+    }
+
+    #[test]
+    fn incremental_matches_one_shot() {
+        let key = [0xaa; 131];
+        let message: Vec<u8> = (0..=255).collect();
+
+        let mut mac = Hmac::<Sha256>::new(&key);
+
+        for chunk in message.chunks(7) {
+            mac.update(chunk);
+        }
+
+        assert_eq!(mac.finalize(), hmac::<Sha256>(&key, &message));
+    }
+
+    #[test]
+    fn verify_rejects_wrong_and_truncated_tags() {
+        let tag = hmac::<Sha256>(b"Jefe", b"what do ya want for nothing?");
+
+        assert!(hmac_verify::<Sha256>(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            &tag
+        ));
+        assert!(!hmac_verify::<Sha256>(
+            b"Jeff",
+            b"what do ya want for nothing?",
+            &tag
+        ));
+        assert!(!hmac_verify::<Sha256>(
+            b"Jefe",
+            b"what do ya want for nothing!",
+            &tag
+        ));
+
+        let mut wrong = tag;
+        wrong[31] ^= 1;
+
+        assert!(!hmac_verify::<Sha256>(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            &wrong
+        ));
+        assert!(!hmac_verify::<Sha256>(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            &tag[..16]
+        ));
+        assert!(!hmac_verify::<Sha256>(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            &[]
+        ));
+    }
+}
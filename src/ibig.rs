@@ -0,0 +1,456 @@
+use super::ubig::{Ubig, UbigError};
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
+use std::str::FromStr;
+
+/* Sign and magnitude; zero is never negative */
+#[derive(Clone, PartialEq, Eq)]
+pub struct Ibig {
+    negative: bool,
+    magnitude: Ubig,
+}
+
+impl Ibig {
+    pub fn new(negative: bool, magnitude: Ubig) -> Self {
+        Self {
+            negative: negative && magnitude.not_zero(),
+            magnitude,
+        }
+    }
+
+    pub fn magnitude(&self) -> &Ubig {
+        &self.magnitude
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn not_zero(&self) -> bool {
+        self.magnitude.not_zero()
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::new(false, self.magnitude.clone())
+    }
+
+    pub fn pow(&self, exponent: u32) -> Self {
+        Self::new(
+            self.negative && exponent % 2 == 1,
+            self.magnitude.pow(exponent),
+        )
+    }
+
+    /* Quotient rounded towards zero; the remainder takes the sign of the dividend */
+    pub fn div_rem_trunc(&self, divisor: &Ibig) -> (Self, Self) {
+        let (quotient, rem) = self.magnitude.div_rem(&divisor.magnitude);
+
+        (
+            Self::new(self.negative != divisor.negative, quotient),
+            Self::new(self.negative, rem),
+        )
+    }
+
+    /* Quotient rounded towards negative infinity; the remainder takes the sign of the divisor */
+    pub fn div_rem_floor(&self, divisor: &Ibig) -> (Self, Self) {
+        let (quotient, rem) = self.div_rem_trunc(divisor);
+
+        if rem.not_zero() && rem.negative != divisor.negative {
+            (quotient - Self::from(1i64), rem + divisor)
+        } else {
+            (quotient, rem)
+        }
+    }
+
+    pub fn div_floor(&self, divisor: &Ibig) -> Self {
+        self.div_rem_floor(divisor).0
+    }
+
+    pub fn mod_floor(&self, divisor: &Ibig) -> Self {
+        self.div_rem_floor(divisor).1
+    }
+
+    /* Returns (g, x, y) with g = gcd(a, b) >= 0 and a * x + b * y = g */
+    pub fn extended_gcd(a: &Ibig, b: &Ibig) -> (Self, Self, Self) {
+        let (mut r0, mut r1) = (a.abs(), b.abs());
+        let (mut s0, mut s1) = (Self::from(1i64), Self::from(0i64));
+        let (mut t0, mut t1) = (Self::from(0i64), Self::from(1i64));
+
+        while r1.not_zero() {
+            let (quotient, rem) = r0.div_rem_floor(&r1);
+
+            let s = &s0 - &quotient * &s1;
+            let t = &t0 - &quotient * &t1;
+
+            r0 = r1;
+            r1 = rem;
+            s0 = s1;
+            s1 = s;
+            t0 = t1;
+            t1 = t;
+        }
+
+        /* The loop ran on |a| and |b|, so carry their signs over to the coefficients */
+        if a.negative {
+            s0 = -s0;
+        }
+
+        if b.negative {
+            t0 = -t0;
+        }
+
+        (r0, s0, t0)
+    }
+}
+
+impl From<Ubig> for Ibig {
+    fn from(value: Ubig) -> Self {
+        Self::new(false, value)
+    }
+}
+
+impl From<i64> for Ibig {
+    fn from(value: i64) -> Self {
+        Self::new(value < 0, Ubig::from(value.unsigned_abs()))
+    }
+}
+
+impl From<i128> for Ibig {
+    fn from(value: i128) -> Self {
+        Self::new(value < 0, Ubig::from(value.unsigned_abs()))
+    }
+}
+
+impl TryFrom<Ibig> for Ubig {
+    type Error = UbigError;
+
+    fn try_from(value: Ibig) -> Result<Self, Self::Error> {
+        if value.negative {
+            return Err(UbigError::Negative);
+        }
+
+        Ok(value.magnitude)
+    }
+}
+
+impl TryFrom<&Ibig> for i64 {
+    type Error = UbigError;
+
+    fn try_from(value: &Ibig) -> Result<Self, Self::Error> {
+        let magnitude = u64::try_from(&value.magnitude)?;
+
+        if value.negative {
+            0i64.checked_sub_unsigned(magnitude)
+                .ok_or(UbigError::Overflow)
+        } else {
+            i64::try_from(magnitude).map_err(|_| UbigError::Overflow)
+        }
+    }
+}
+
+impl TryFrom<&Ibig> for i128 {
+    type Error = UbigError;
+
+    fn try_from(value: &Ibig) -> Result<Self, Self::Error> {
+        let magnitude = u128::try_from(&value.magnitude)?;
+
+        if value.negative {
+            0i128
+                .checked_sub_unsigned(magnitude)
+                .ok_or(UbigError::Overflow)
+        } else {
+            i128::try_from(magnitude).map_err(|_| UbigError::Overflow)
+        }
+    }
+}
+
+/* An optional sign followed by anything Ubig accepts */
+impl FromStr for Ibig {
+    type Err = UbigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+
+        Ok(Self::new(negative, Ubig::from_str(digits)?))
+    }
+}
+
+impl fmt::Display for Ibig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad_integral(!self.negative, "", &self.magnitude.to_string())
+    }
+}
+
+impl fmt::LowerHex for Ibig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad_integral(!self.negative, "0x", &format!("{:x}", self.magnitude))
+    }
+}
+
+impl fmt::Debug for Ibig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Ibig({})", self)
+    }
+}
+
+impl Neg for Ibig {
+    type Output = Ibig;
+
+    fn neg(self) -> Self::Output {
+        Self::new(!self.negative, self.magnitude)
+    }
+}
+
+impl Neg for &Ibig {
+    type Output = Ibig;
+
+    fn neg(self) -> Self::Output {
+        Ibig::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Add<&Ibig> for &Ibig {
+    type Output = Ibig;
+
+    fn add(self, rhs: &Ibig) -> Self::Output {
+        if self.negative == rhs.negative {
+            return Ibig::new(self.negative, &self.magnitude + &rhs.magnitude);
+        }
+
+        /* Opposite signs: the larger magnitude wins */
+        if self.magnitude >= rhs.magnitude {
+            Ibig::new(self.negative, &self.magnitude - &rhs.magnitude)
+        } else {
+            Ibig::new(rhs.negative, &rhs.magnitude - &self.magnitude)
+        }
+    }
+}
+
+impl Sub<&Ibig> for &Ibig {
+    type Output = Ibig;
+
+    fn sub(self, rhs: &Ibig) -> Self::Output {
+        self + &-rhs
+    }
+}
+
+impl Mul<&Ibig> for &Ibig {
+    type Output = Ibig;
+
+    fn mul(self, rhs: &Ibig) -> Self::Output {
+        Ibig::new(
+            self.negative != rhs.negative,
+            &self.magnitude * &rhs.magnitude,
+        )
+    }
+}
+
+/* Division and remainder truncate, like the primitive signed integers */
+impl Div<&Ibig> for &Ibig {
+    type Output = Ibig;
+
+    fn div(self, rhs: &Ibig) -> Self::Output {
+        self.div_rem_trunc(rhs).0
+    }
+}
+
+impl Rem<&Ibig> for &Ibig {
+    type Output = Ibig;
+
+    fn rem(self, rhs: &Ibig) -> Self::Output {
+        self.div_rem_trunc(rhs).1
+    }
+}
+
+macro_rules! forward_binary_op {
+    ($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident) => {
+        impl $trait<Ibig> for Ibig {
+            type Output = Ibig;
+
+            fn $method(self, rhs: Ibig) -> Self::Output {
+                (&self).$method(&rhs)
+            }
+        }
+
+        impl $trait<&Ibig> for Ibig {
+            type Output = Ibig;
+
+            fn $method(self, rhs: &Ibig) -> Self::Output {
+                (&self).$method(rhs)
+            }
+        }
+
+        impl $trait<Ibig> for &Ibig {
+            type Output = Ibig;
+
+            fn $method(self, rhs: Ibig) -> Self::Output {
+                self.$method(&rhs)
+            }
+        }
+
+        impl $assign_trait<&Ibig> for Ibig {
+            fn $assign_method(&mut self, rhs: &Ibig) {
+                *self = (&*self).$method(rhs);
+            }
+        }
+
+        impl $assign_trait<Ibig> for Ibig {
+            fn $assign_method(&mut self, rhs: Ibig) {
+                *self = (&*self).$method(&rhs);
+            }
+        }
+    };
+}
+
+forward_binary_op!(Add, add, AddAssign, add_assign);
+forward_binary_op!(Sub, sub, SubAssign, sub_assign);
+forward_binary_op!(Mul, mul, MulAssign, mul_assign);
+forward_binary_op!(Div, div, DivAssign, div_assign);
+forward_binary_op!(Rem, rem, RemAssign, rem_assign);
+
+impl PartialOrd for Ibig {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ibig {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(value: i64) -> Ibig {
+        Ibig::from(value)
+    }
+
+    #[test]
+    fn truncated_and_floored_division() {
+        /* (a, b, truncated quotient and remainder, floored quotient and remainder) */
+        let cases = [
+            (7, 2, (3, 1), (3, 1)),
+            (-7, 2, (-3, -1), (-4, 1)),
+            (7, -2, (-3, 1), (-4, -1)),
+            (-7, -2, (3, -1), (3, -1)),
+            (6, -3, (-2, 0), (-2, 0)),
+            (-6, 3, (-2, 0), (-2, 0)),
+            (0, -5, (0, 0), (0, 0)),
+            (-1, 5, (0, -1), (-1, 4)),
+        ];
+
+        for (a, b, (trunc_q, trunc_r), (floor_q, floor_r)) in cases {
+            let (a, b) = (int(a), int(b));
+
+            assert_eq!(a.div_rem_trunc(&b), (int(trunc_q), int(trunc_r)));
+            assert_eq!((&a / &b, &a % &b), (int(trunc_q), int(trunc_r)));
+
+            assert_eq!(a.div_rem_floor(&b), (int(floor_q), int(floor_r)));
+            assert_eq!(
+                (a.div_floor(&b), a.mod_floor(&b)),
+                (int(floor_q), int(floor_r))
+            );
+        }
+
+        /* Beyond a single limb: -(2^100 + 1) = -2^36 - 1 floored, remainder 2^64 - 1 */
+        let a = -Ibig::from((Ubig::from(1u64) << 100) + Ubig::from(1u64));
+        let b = Ibig::from(Ubig::from(1u64) << 64);
+        let (q, r) = a.div_rem_floor(&b);
+
+        assert_eq!(q, -Ibig::from(Ubig::from(1u64) << 36) - int(1));
+        assert_eq!(r, Ibig::from(Ubig::from(u64::MAX)));
+        assert_eq!(&q * &b + &r, a);
+    }
+
+    #[test]
+    fn extended_gcd_with_signs() {
+        let cases = [
+            (240, 46, 2),
+            (-240, 46, 2),
+            (240, -46, 2),
+            (-240, -46, 2),
+            (17, -5, 1),
+            (0, -5, 5),
+            (-5, 0, 5),
+            (0, 0, 0),
+        ];
+
+        for (a, b, gcd) in cases {
+            let (a, b) = (int(a), int(b));
+            let (g, x, y) = Ibig::extended_gcd(&a, &b);
+
+            assert_eq!(g, int(gcd));
+            assert_eq!(&a * &x + &b * &y, g);
+        }
+    }
+
+    #[test]
+    fn parse_and_format() {
+        for case in [
+            "0",
+            "-1",
+            "42",
+            "-18446744073709551616",
+            "-123456789012345678901234567890",
+        ] {
+            assert_eq!(case.parse::<Ibig>().unwrap().to_string(), case);
+        }
+
+        let negative_zero: Ibig = "-0".parse().unwrap();
+
+        assert!(!negative_zero.is_negative());
+        assert_eq!(negative_zero, int(0));
+        assert_eq!(negative_zero.to_string(), "0");
+        assert_eq!((-int(0)).to_string(), "0");
+
+        assert_eq!("+17".parse::<Ibig>().unwrap(), int(17));
+        assert_eq!("-0x1f".parse::<Ibig>().unwrap(), int(-31));
+        assert_eq!(format!("{:#x}", int(-31)), "-0x1f");
+        assert_eq!(format!("{:?}", int(-31)), "Ibig(-31)");
+
+        assert!(matches!("-".parse::<Ibig>(), Err(UbigError::EmptyString)));
+        assert!(matches!(
+            "--1".parse::<Ibig>(),
+            Err(UbigError::InvalidDigit)
+        ));
+        assert!(matches!(
+            "+-1".parse::<Ibig>(),
+            Err(UbigError::InvalidDigit)
+        ));
+    }
+
+    #[test]
+    fn conversions() {
+        for value in [i64::MIN, -1, 0, 1, i64::MAX] {
+            assert_eq!(i64::try_from(&int(value)).unwrap(), value);
+        }
+
+        assert!(matches!(
+            i64::try_from(&(int(i64::MIN) - int(1))),
+            Err(UbigError::Overflow)
+        ));
+        assert!(matches!(
+            i64::try_from(&(int(i64::MAX) + int(1))),
+            Err(UbigError::Overflow)
+        ));
+
+        assert_eq!(i128::try_from(&Ibig::from(i128::MIN)).unwrap(), i128::MIN);
+        assert!(matches!(Ubig::try_from(int(-1)), Err(UbigError::Negative)));
+        assert_eq!(Ubig::try_from(int(5)).unwrap(), Ubig::from(5u64));
+    }
+}
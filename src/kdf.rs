@@ -0,0 +1,355 @@
+use super::digest::Digest;
+use super::hmac::{hmac, Hmac};
+use super::salsa20;
+use super::sha::Sha256;
+
+/*
+ * Every function takes the output length, so asking for 16, 24 or 32 bytes
+ * gives something AesKey::from accepts.
+ */
+
+#[derive(Debug)]
+pub enum KdfError {
+    OutputTooLong,
+    InvalidIterations,
+    InvalidCost,
+    InvalidBlockSize,
+}
+
+/* RFC 8018 caps the output at (2^32 - 1) blocks, the block index is 32 bits */
+pub fn pbkdf2<D: Digest>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    length: usize,
+) -> Result<Vec<u8>, KdfError> {
+    if iterations == 0 {
+        return Err(KdfError::InvalidIterations);
+    }
+
+    if length as u64 > (u32::MAX as u64) * D::OUTPUT_SIZE as u64 {
+        return Err(KdfError::OutputTooLong);
+    }
+
+    let keyed = Hmac::<D>::new(password);
+    let mut output = Vec::with_capacity(length + D::OUTPUT_SIZE);
+    let mut block_index = 1u32;
+
+    while output.len() < length {
+        let mut mac = keyed.clone();
+        mac.update(salt);
+        mac.update(&block_index.to_be_bytes());
+
+        let mut u = mac.finalize();
+        let mut block = u.as_ref().to_vec();
+
+        for _ in 1..iterations {
+            let mut mac = keyed.clone();
+            mac.update(u.as_ref());
+            u = mac.finalize();
+
+            for (byte, other) in block.iter_mut().zip(u.as_ref()) {
+                *byte ^= other;
+            }
+        }
+
+        output.extend_from_slice(&block);
+        block_index += 1;
+    }
+
+    output.truncate(length);
+
+    Ok(output)
+}
+
+/* An empty salt stands for HashLen zero bytes, as in RFC 5869 */
+pub fn hkdf_extract<D: Digest>(salt: &[u8], input_key: &[u8]) -> D::Output {
+    if salt.is_empty() {
+        return hmac::<D>(&vec![0; D::OUTPUT_SIZE], input_key);
+    }
+
+    hmac::<D>(salt, input_key)
+}
+
+pub fn hkdf_expand<D: Digest>(
+    pseudorandom_key: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, KdfError> {
+    if length > 255 * D::OUTPUT_SIZE {
+        return Err(KdfError::OutputTooLong);
+    }
+
+    let keyed = Hmac::<D>::new(pseudorandom_key);
+    let mut output = Vec::with_capacity(length + D::OUTPUT_SIZE);
+    let mut previous: Vec<u8> = Vec::new();
+
+    for counter in 1..=length.div_ceil(D::OUTPUT_SIZE) {
+        let mut mac = keyed.clone();
+        mac.update(&previous);
+        mac.update(info);
+        mac.update(&[counter as u8]);
+
+        previous = mac.finalize().as_ref().to_vec();
+        output.extend_from_slice(&previous);
+    }
+
+    output.truncate(length);
+
+    Ok(output)
+}
+
+pub fn hkdf<D: Digest>(
+    salt: &[u8],
+    input_key: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, KdfError> {
+    let pseudorandom_key = hkdf_extract::<D>(salt, input_key);
+
+    hkdf_expand::<D>(pseudorandom_key.as_ref(), info, length)
+}
+
+/* RFC 7914: cost n must be a power of two above 1, memory use is 128 * r * n bytes */
+pub fn scrypt(
+    password: &[u8],
+    salt: &[u8],
+    n: u64,
+    r: usize,
+    p: usize,
+    length: usize,
+) -> Result<Vec<u8>, KdfError> {
+    if n < 2 || !n.is_power_of_two() {
+        return Err(KdfError::InvalidCost);
+    }
+
+    if r == 0 || p == 0 || r * p >= 1 << 30 {
+        return Err(KdfError::InvalidBlockSize);
+    }
+
+    if length as u64 > (u32::MAX as u64) * 32 {
+        return Err(KdfError::OutputTooLong);
+    }
+
+    let block_length = 128 * r;
+    let mut blocks = pbkdf2::<Sha256>(password, salt, 1, p * block_length)?;
+
+    for block in blocks.chunks_mut(block_length) {
+        ro_mix(block, n, r);
+    }
+
+    pbkdf2::<Sha256>(password, &blocks, 1, length)
+}
+
+fn ro_mix(block: &mut [u8], n: u64, r: usize) {
+    let mut x: Vec<u32> = block
+        .chunks(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+
+    let mut table = Vec::with_capacity(n as usize);
+
+    for _ in 0..n {
+        table.push(x.clone());
+        x = block_mix(&x, r);
+    }
+
+    for _ in 0..n {
+        /* Integerify: the first word of the last 64-byte chunk, only the low bits matter */
+        let last = &x[(2 * r - 1) * 16..];
+        let j = ((last[0] as u64) | ((last[1] as u64) << 32)) & (n - 1);
+
+        for (word, other) in x.iter_mut().zip(&table[j as usize]) {
+            *word ^= other;
+        }
+
+        x = block_mix(&x, r);
+    }
+
+    for (bytes, word) in block.chunks_mut(4).zip(x) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+}
+
+/* Salsa20/8 over 2r chunks, even outputs first and odd outputs after */
+fn block_mix(input: &[u32], r: usize) -> Vec<u32> {
+    let mut output = vec![0u32; input.len()];
+    let mut x = [0u32; 16];
+
+    x.copy_from_slice(&input[(2 * r - 1) * 16..]);
+
+    for (i, chunk) in input.chunks(16).enumerate() {
+        for (word, other) in x.iter_mut().zip(chunk) {
+            *word ^= other;
+        }
+
+        x = salsa20::salsa20_core(&x, 8);
+
+        let position = (i / 2 + (i % 2) * r) * 16;
+        output[position..position + 16].copy_from_slice(&x);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha::Sha1;
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    #[test]
+    fn rfc6070_pbkdf2_sha1() {
+        let cases: [(&[u8], &[u8], u32, &str); 5] = [
+            (
+                b"password",
+                b"salt",
+                1,
+                "0c60c80f961f0e71f3a9b524af6012062fe037a6",
+            ),
+            (
+                b"password",
+                b"salt",
+                2,
+                "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957",
+            ),
+            (
+                b"password",
+                b"salt",
+                4096,
+                "4b007901b765489abead49d926f721d065a429c1",
+            ),
+            (
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038",
+            ),
+            (
+                b"pass\0word",
+                b"sa\0lt",
+                4096,
+                "56fa6aa75548099dcc37d7f03425e0c3",
+            ),
+        ];
+
+        for (password, salt, iterations, expected) in cases {
+            let expected = h(expected);
+            let output = pbkdf2::<Sha1>(password, salt, iterations, expected.len()).unwrap();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn rfc7914_pbkdf2_sha256() {
+        let output = pbkdf2::<Sha256>(b"passwd", b"salt", 1, 64).unwrap();
+        assert_eq!(
+            output,
+            h(
+                "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+               49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+            )
+        );
+    }
+
+    #[test]
+    fn pbkdf2_rejects_bad_parameters() {
+        assert!(matches!(
+            pbkdf2::<Sha256>(b"password", b"salt", 0, 32),
+            Err(KdfError::InvalidIterations)
+        ));
+        assert!(matches!(
+            pbkdf2::<Sha1>(b"password", b"salt", 1, (u32::MAX as usize) * 20 + 1),
+            Err(KdfError::OutputTooLong)
+        ));
+    }
+
+    #[test]
+    fn rfc5869_hkdf_sha256() {
+        let cases = [
+            (
+                h("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b"),
+                h("000102030405060708090a0b0c"),
+                h("f0f1f2f3f4f5f6f7f8f9"),
+                "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf\
+                 34007208d5b887185865",
+            ),
+            (
+                (0x00..0x50).collect(),
+                (0x60..0xb0).collect(),
+                (0xb0..=0xff).collect(),
+                "06a6b88c5853361a06104c9ceb35b45cef760014904671014a193f40c15fc244",
+                "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c\
+                 59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71\
+                 cc30c58179ec3e87c14c01d5c1f3434f1d87",
+            ),
+            (
+                h("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b"),
+                Vec::new(),
+                Vec::new(),
+                "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d\
+                 9d201395faa4b61a96c8",
+            ),
+        ];
+
+        for (input_key, salt, info, pseudorandom_key, expected) in cases {
+            let expected = h(expected);
+            let prk = hkdf_extract::<Sha256>(&salt, &input_key);
+            assert_eq!(prk.as_ref(), h(pseudorandom_key));
+
+            let output = hkdf::<Sha256>(&salt, &input_key, &info, expected.len()).unwrap();
+            assert_eq!(output, expected);
+        }
+
+        assert!(matches!(
+            hkdf_expand::<Sha256>(&[0; 32], b"", 255 * 32 + 1),
+            Err(KdfError::OutputTooLong)
+        ));
+    }
+
+    #[test]
+    fn rfc7914_scrypt() {
+        let output = scrypt(b"", b"", 16, 1, 1, 64).unwrap();
+        assert_eq!(
+            output,
+            h(
+                "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+               fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"
+            )
+        );
+
+        let output = scrypt(b"password", b"NaCl", 1024, 8, 16, 64).unwrap();
+        assert_eq!(
+            output,
+            h(
+                "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162\
+               2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"
+            )
+        );
+    }
+
+    #[test]
+    fn scrypt_rejects_bad_parameters() {
+        assert!(matches!(
+            scrypt(b"", b"", 1, 1, 1, 64),
+            Err(KdfError::InvalidCost)
+        ));
+        assert!(matches!(
+            scrypt(b"", b"", 24, 1, 1, 64),
+            Err(KdfError::InvalidCost)
+        ));
+        assert!(matches!(
+            scrypt(b"", b"", 16, 0, 1, 64),
+            Err(KdfError::InvalidBlockSize)
+        ));
+        assert!(matches!(
+            scrypt(b"", b"", 16, 1, 0, 64),
+            Err(KdfError::InvalidBlockSize)
+        ));
+    }
+}
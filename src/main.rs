@@ -1,25 +1,47 @@
 #![allow(dead_code)]
 
 mod aes;
+mod argon2;
 mod base64;
+mod blake2;
 mod chunk_pair_iter;
+mod constant_time;
+mod crt;
+mod curve25519;
 mod dh;
+mod digest;
+mod dsa;
+mod ec;
+mod ecdh;
+mod ecdsa;
+mod ed25519;
+mod hmac;
+mod ibig;
+mod kdf;
 mod key_value;
 mod md4;
+mod modes;
 mod pkcs7;
+mod prime;
 mod random;
+mod rsa;
+mod salsa20;
 mod sha;
+mod srp;
+mod stream;
 mod ubig;
 mod urandom;
+mod x25519;
 
 use aes::{aes_ctr, AesCtrIter, AesKey};
 use chunk_pair_iter::ChunkPairIter;
-use dh::nist_dh_secret;
+use dh::{DhGroup, DhKeyPair};
+use hmac::hmac;
 use md4::{md4_digest, md4_digest_from_state};
 use random::MersenneStream;
 use random::MersenneTwister;
 use sha::sha256_digest;
-use sha::{sha1_digest, sha1_digest_from_state};
+use sha::{sha1_digest, sha1_digest_from_state, Sha1};
 use ubig::Ubig;
 
 use std::collections::HashMap;
@@ -354,34 +376,8 @@ fn sha1_mac(key: &AesKey, input: &[u8]) -> [u8; 20] {
     sha1_digest(&input)
 }
 
-fn hmac_sha1(key: &AesKey, input: &[u8]) -> [u8; 20] {
-    let mut opad: [u8; 64] = [0x5c; 64];
-    let mut ipad: [u8; 64] = [0x36; 64];
-
-    let mut dkey: [u8; 64] = [0; 64];
-
-    if key.as_ref().len() > 64 {
-        let key_hash = sha1_digest(key.as_ref());
-        (&mut dkey[0..key_hash.len()]).copy_from_slice(&key_hash);
-    } else {
-        (&mut dkey[0..key.as_ref().len()]).copy_from_slice(key.as_ref());
-    }
-
-    for ((o, i), byte) in opad.iter_mut().zip(ipad.iter_mut()).zip(dkey.iter()) {
-        *o ^= byte;
-        *i ^= byte;
-    }
-
-    let inner: Vec<u8> = ipad.into_iter().chain(input.iter().copied()).collect();
-    let inner_hash = sha1_digest(&inner);
-
-    let outer: Vec<u8> = opad.into_iter().chain(inner_hash.into_iter()).collect();
-
-    sha1_digest(&outer)
-}
-
-fn validate_hmac_sha1(key: &AesKey, input: &[u8], supplied_hmac: &[u8; 20]) -> bool {
-    let computed_hmac = hmac_sha1(key, input);
+fn validate_hmac_sha1(key: &[u8], input: &[u8], supplied_hmac: &[u8; 20]) -> bool {
+    let computed_hmac = hmac::<Sha1>(key, input);
 
     for (a, b) in computed_hmac.iter().zip(supplied_hmac.iter()) {
         if a != b {
@@ -404,33 +400,25 @@ struct DhResult {
 }
 
 fn make_dh_shared_secret(g: &[u8]) -> DhResult {
-    let alice_private = urandom::bytes(20);
-    let alice_public = dh::dh_public(&alice_private, dh::nist_dh_p().as_ref(), g);
-
-    let bob_private = urandom::bytes(20);
-    let bob_public = dh::dh_public(&bob_private, dh::nist_dh_p().as_ref(), g);
+    /* DhGroup::new and peer validation both reject these generators, hence the unchecked calls */
+    let group = DhGroup::new_unchecked(DhGroup::modp_1536().p().clone(), Ubig::from(g), None)
+        .expect("MODP modulus is odd");
 
-    let alice_secret = dh::dh_secret(
-        bob_public.as_ref(),
-        &alice_private,
-        dh::nist_dh_p().as_ref(),
-    );
+    let alice = DhKeyPair::generate(&group);
+    let bob = DhKeyPair::generate(&group);
 
-    let bob_secret = dh::dh_secret(
-        alice_public.as_ref(),
-        &bob_private,
-        dh::nist_dh_p().as_ref(),
-    );
+    let alice_secret: Vec<u8> = alice.shared_secret_unchecked(bob.public_key()).into();
+    let bob_secret: Vec<u8> = bob.shared_secret_unchecked(alice.public_key()).into();
 
     assert_eq!(alice_secret, bob_secret);
 
     let shared_secret = &sha::sha1_digest(&alice_secret)[0..16];
 
     DhResult {
-        alice_public,
-        alice_private,
-        bob_public,
-        bob_private,
+        alice_public: alice.public_key().clone().into(),
+        alice_private: alice.private_key().clone().into(),
+        bob_public: bob.public_key().clone().into(),
+        bob_private: bob.private_key().clone().into(),
         shared_secret: shared_secret.to_vec(),
     }
 }
@@ -455,11 +443,11 @@ fn main() {
     let g_one_res = make_dh_shared_secret(&g_one);
 
     /* For g = p: Shared secret = [] */
-    let g_p = dh::nist_dh_p();
+    let g_p = DhGroup::modp_1536().p().clone();
     let g_p_res = make_dh_shared_secret(g_p.as_ref());
 
     /* For g = p - 1: Shared secret = 1 or p - 1 */
-    let mut g_p_minus_one = dh::nist_dh_p();
+    let mut g_p_minus_one = DhGroup::modp_1536().p().clone();
     g_p_minus_one -= &Ubig::from(g_one.as_ref());
     let g_p_minus_one_res = make_dh_shared_secret(g_p_minus_one.as_ref());
 
@@ -1,11 +1,85 @@
+use super::digest::{BlockBuffer, Digest};
+
 use std::slice::ChunksExact;
 
+const MD4_H: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
 pub fn md4_digest(input: &[u8]) -> [u8; 16] {
-    do_md4_digest(input, None, 0)
+    Md4::digest(input)
 }
 
 pub fn md4_digest_from_state(input: &[u8], state: &[u8; 16], head_bytes: usize) -> [u8; 16] {
-    do_md4_digest(input, Some(state), head_bytes)
+    let mut hasher = Md4::from_state(state, head_bytes);
+    hasher.update(input);
+
+    hasher.finalize()
+}
+
+#[derive(Clone)]
+pub struct Md4 {
+    h: [u32; 4],
+    buffer: BlockBuffer<64>,
+    length: usize,
+}
+
+impl Md4 {
+    pub fn from_state(state: &[u8; 16], processed_len: usize) -> Self {
+        let mut h = [0; 4];
+
+        for (slot, bytes) in h.iter_mut().zip(state.chunks_exact(4)) {
+            *slot = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        Self {
+            h,
+            buffer: BlockBuffer::new(),
+            length: processed_len,
+        }
+    }
+}
+
+impl Digest for Md4 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 16;
+
+    type Output = [u8; 16];
+
+    fn new() -> Self {
+        Self {
+            h: MD4_H,
+            buffer: BlockBuffer::new(),
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        let h = &mut self.h;
+
+        self.buffer.update(input, |bytes| {
+            let mut block = [0; 16];
+            place_bytes_in_block(&mut block, bytes);
+
+            md4_compress(h, &block);
+        });
+
+        self.length += input.len();
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let tail = self.buffer.remainder();
+
+        for block in Md4Blocks::new(tail, self.length - tail.len()) {
+            md4_compress(&mut self.h, &block);
+        }
+
+        let mut digest = [0; 16];
+
+        for (bytes, word) in digest.chunks_exact_mut(4).zip(self.h.iter()) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+
+        digest
+    }
 }
 
 macro_rules! r1 {
@@ -37,92 +111,70 @@ macro_rules! r3 {
     };
 }
 
-fn do_md4_digest(input: &[u8], state: Option<&[u8; 16]>, extra_byte_length: usize) -> [u8; 16] {
-    let mut h: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
-
-    if let Some(state) = state {
-        for (slot, bytes) in h.iter_mut().zip(state.chunks_exact(4)) {
-            let mut word = [0; 4];
-            word.copy_from_slice(bytes);
-
-            *slot = u32::from_le_bytes(word);
-        }
-    }
-
-    for block in Md4Blocks::new(input, extra_byte_length) {
-        let mut a = h[0];
-        let mut b = h[1];
-        let mut c = h[2];
-        let mut d = h[3];
-
-        /* Round 1 */
-        r1![a, b, c, d, block, 0, 3];
-        r1![d, a, b, c, block, 1, 7];
-        r1![c, d, a, b, block, 2, 11];
-        r1![b, c, d, a, block, 3, 19];
-        r1![a, b, c, d, block, 4, 3];
-        r1![d, a, b, c, block, 5, 7];
-        r1![c, d, a, b, block, 6, 11];
-        r1![b, c, d, a, block, 7, 19];
-        r1![a, b, c, d, block, 8, 3];
-        r1![d, a, b, c, block, 9, 7];
-        r1![c, d, a, b, block, 10, 11];
-        r1![b, c, d, a, block, 11, 19];
-        r1![a, b, c, d, block, 12, 3];
-        r1![d, a, b, c, block, 13, 7];
-        r1![c, d, a, b, block, 14, 11];
-        r1![b, c, d, a, block, 15, 19];
-
-        /* Round 2 */
-        r2![a, b, c, d, block, 0, 3];
-        r2![d, a, b, c, block, 4, 5];
-        r2![c, d, a, b, block, 8, 9];
-        r2![b, c, d, a, block, 12, 13];
-        r2![a, b, c, d, block, 1, 3];
-        r2![d, a, b, c, block, 5, 5];
-        r2![c, d, a, b, block, 9, 9];
-        r2![b, c, d, a, block, 13, 13];
-        r2![a, b, c, d, block, 2, 3];
-        r2![d, a, b, c, block, 6, 5];
-        r2![c, d, a, b, block, 10, 9];
-        r2![b, c, d, a, block, 14, 13];
-        r2![a, b, c, d, block, 3, 3];
-        r2![d, a, b, c, block, 7, 5];
-        r2![c, d, a, b, block, 11, 9];
-        r2![b, c, d, a, block, 15, 13];
-
-        /* Round 3 */
-        r3![a, b, c, d, block, 0, 3];
-        r3![d, a, b, c, block, 8, 9];
-        r3![c, d, a, b, block, 4, 11];
-        r3![b, c, d, a, block, 12, 15];
-        r3![a, b, c, d, block, 2, 3];
-        r3![d, a, b, c, block, 10, 9];
-        r3![c, d, a, b, block, 6, 11];
-        r3![b, c, d, a, block, 14, 15];
-        r3![a, b, c, d, block, 1, 3];
-        r3![d, a, b, c, block, 9, 9];
-        r3![c, d, a, b, block, 5, 11];
-        r3![b, c, d, a, block, 13, 15];
-        r3![a, b, c, d, block, 3, 3];
-        r3![d, a, b, c, block, 11, 9];
-        r3![c, d, a, b, block, 7, 11];
-        r3![b, c, d, a, block, 15, 15];
-
-        h[0] = h[0].wrapping_add(a);
-        h[1] = h[1].wrapping_add(b);
-        h[2] = h[2].wrapping_add(c);
-        h[3] = h[3].wrapping_add(d);
-    }
-
-    let mut digest = [0; 16];
-
-    (&mut digest[0..4]).copy_from_slice(&h[0].to_le_bytes());
-    (&mut digest[4..8]).copy_from_slice(&h[1].to_le_bytes());
-    (&mut digest[8..12]).copy_from_slice(&h[2].to_le_bytes());
-    (&mut digest[12..16]).copy_from_slice(&h[3].to_le_bytes());
-
-    digest
+fn md4_compress(h: &mut [u32; 4], block: &[u32; 16]) {
+    let mut a = h[0];
+    let mut b = h[1];
+    let mut c = h[2];
+    let mut d = h[3];
+
+    /* Round 1 */
+    r1![a, b, c, d, block, 0, 3];
+    r1![d, a, b, c, block, 1, 7];
+    r1![c, d, a, b, block, 2, 11];
+    r1![b, c, d, a, block, 3, 19];
+    r1![a, b, c, d, block, 4, 3];
+    r1![d, a, b, c, block, 5, 7];
+    r1![c, d, a, b, block, 6, 11];
+    r1![b, c, d, a, block, 7, 19];
+    r1![a, b, c, d, block, 8, 3];
+    r1![d, a, b, c, block, 9, 7];
+    r1![c, d, a, b, block, 10, 11];
+    r1![b, c, d, a, block, 11, 19];
+    r1![a, b, c, d, block, 12, 3];
+    r1![d, a, b, c, block, 13, 7];
+    r1![c, d, a, b, block, 14, 11];
+    r1![b, c, d, a, block, 15, 19];
+
+    /* Round 2 */
+    r2![a, b, c, d, block, 0, 3];
+    r2![d, a, b, c, block, 4, 5];
+    r2![c, d, a, b, block, 8, 9];
+    r2![b, c, d, a, block, 12, 13];
+    r2![a, b, c, d, block, 1, 3];
+    r2![d, a, b, c, block, 5, 5];
+    r2![c, d, a, b, block, 9, 9];
+    r2![b, c, d, a, block, 13, 13];
+    r2![a, b, c, d, block, 2, 3];
+    r2![d, a, b, c, block, 6, 5];
+    r2![c, d, a, b, block, 10, 9];
+    r2![b, c, d, a, block, 14, 13];
+    r2![a, b, c, d, block, 3, 3];
+    r2![d, a, b, c, block, 7, 5];
+    r2![c, d, a, b, block, 11, 9];
+    r2![b, c, d, a, block, 15, 13];
+
+    /* Round 3 */
+    r3![a, b, c, d, block, 0, 3];
+    r3![d, a, b, c, block, 8, 9];
+    r3![c, d, a, b, block, 4, 11];
+    r3![b, c, d, a, block, 12, 15];
+    r3![a, b, c, d, block, 2, 3];
+    r3![d, a, b, c, block, 10, 9];
+    r3![c, d, a, b, block, 6, 11];
+    r3![b, c, d, a, block, 14, 15];
+    r3![a, b, c, d, block, 1, 3];
+    r3![d, a, b, c, block, 9, 9];
+    r3![c, d, a, b, block, 5, 11];
+    r3![b, c, d, a, block, 13, 15];
+    r3![a, b, c, d, block, 3, 3];
+    r3![d, a, b, c, block, 11, 9];
+    r3![c, d, a, b, block, 7, 11];
+    r3![b, c, d, a, block, 15, 15];
+
+    h[0] = h[0].wrapping_add(a);
+    h[1] = h[1].wrapping_add(b);
+    h[2] = h[2].wrapping_add(c);
+    h[3] = h[3].wrapping_add(d);
 }
 
 fn md4_f(x: u32, y: u32, z: u32) -> u32 {
@@ -220,3 +272,89 @@ fn place_bytes_in_block(block: &mut [u32; 16], bytes: &[u8]) {
         *slot = u32::from_le_bytes(word_bytes);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGES: [&[u8]; 7] = [
+        b"",
+        b"a",
+        b"abc",
+        b"message digest",
+        b"abcdefghijklmnopqrstuvwxyz",
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+        b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+    ];
+
+    /* RFC 1320 A.5 */
+    #[test]
+    fn rfc1320_test_suite() {
+        let expected = [
+            "31d6cfe0d16ae931b73c59d7e0c089c0",
+            "bde52cb31de33e46245e05fbdbd6fb24",
+            "a448017aaf21d8525fc10ae87aa6729d",
+            "d9130a8164549fe818874806e1c7014b",
+            "d79e1c308aa5bbcdeea8ed63df412da9",
+            "043f8582f241db351ce627e153e7f0e4",
+            "e33b4ddc9c38f2199c3e7b164fcc0536",
+        ];
+
+        for (message, expected) in MESSAGES.iter().zip(expected) {
+            assert_eq!(hex::encode(md4_digest(message)), expected);
+        }
+    }
+
+    #[test]
+    fn incremental_updates_match() {
+        for message in MESSAGES {
+            for piece in [1, 7, 63, 64, 65] {
+                let mut hasher = Md4::new();
+
+                for chunk in message.chunks(piece) {
+                    hasher.update(chunk);
+                }
+
+                assert_eq!(hasher.finalize(), md4_digest(message));
+            }
+        }
+    }
+
+    /* Padding as for SHA-1, except that the bit length is little-endian */
+    fn glue_padding(length: usize) -> Vec<u8> {
+        let mut padding = vec![0x80];
+
+        padding.resize((119 - length % 64) % 64 + 1, 0);
+        padding.extend_from_slice(&(8 * length as u64).to_le_bytes());
+
+        padding
+    }
+
+    #[test]
+    fn from_state_extends_a_digest() {
+        let suffix = b";admin=true";
+
+        for message in MESSAGES {
+            let mut forged = [message, &glue_padding(message.len())[..]].concat();
+            let processed = forged.len();
+            forged.extend_from_slice(suffix);
+
+            let state = md4_digest(message);
+            let mut hasher = Md4::from_state(&state, processed);
+            hasher.update(suffix);
+
+            assert_eq!(hasher.finalize(), md4_digest(&forged));
+            assert_eq!(
+                md4_digest_from_state(suffix, &state, processed),
+                md4_digest(&forged)
+            );
+        }
+
+        let iv: Vec<u8> = MD4_H.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+        assert_eq!(
+            md4_digest_from_state(b"abc", &iv.try_into().unwrap(), 0),
+            md4_digest(b"abc")
+        );
+    }
+}
@@ -0,0 +1,846 @@
+use super::constant_time;
+use super::pkcs7;
+
+pub trait BlockCipher {
+    const BLOCK_SIZE: usize;
+
+    fn encrypt_block(&self, block: &mut [u8]);
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+impl<C: BlockCipher> BlockCipher for &C {
+    const BLOCK_SIZE: usize = C::BLOCK_SIZE;
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        (*self).encrypt_block(block)
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        (*self).decrypt_block(block)
+    }
+}
+
+#[derive(Debug)]
+pub enum ModeError {
+    IrregularDecryptLength,
+    WrongSizeIv,
+    WrongSizeTag,
+    PaddingError,
+    AuthenticationFailed,
+    InvalidCounterLayout,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CounterLayout {
+    pub offset: usize,
+    pub length: usize,
+    pub endianness: Endianness,
+}
+
+impl CounterLayout {
+    pub fn new(offset: usize, length: usize, endianness: Endianness) -> Self {
+        Self {
+            offset,
+            length,
+            endianness,
+        }
+    }
+
+    pub fn full_block(block_size: usize) -> Self {
+        Self::new(0, block_size, Endianness::Big)
+    }
+
+    fn mask(&self) -> u128 {
+        match self.length {
+            16 => u128::MAX,
+            length => (1 << (8 * length)) - 1,
+        }
+    }
+
+    fn read(&self, block: &[u8]) -> u128 {
+        let field = &block[self.offset..self.offset + self.length];
+        let mut bytes = [0; 16];
+
+        match self.endianness {
+            Endianness::Little => {
+                bytes[..self.length].copy_from_slice(field);
+                u128::from_le_bytes(bytes)
+            }
+
+            Endianness::Big => {
+                bytes[16 - self.length..].copy_from_slice(field);
+                u128::from_be_bytes(bytes)
+            }
+        }
+    }
+
+    fn write(&self, block: &mut [u8], counter: u128) {
+        let field = &mut block[self.offset..self.offset + self.length];
+
+        match self.endianness {
+            Endianness::Little => field.copy_from_slice(&counter.to_le_bytes()[..self.length]),
+            Endianness::Big => field.copy_from_slice(&counter.to_be_bytes()[16 - self.length..]),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    initial_block: Vec<u8>,
+    initial_counter: u128,
+    layout: CounterLayout,
+    key_stream: Vec<u8>,
+    key_stream_block: Option<u64>,
+    position: u64,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    pub fn new<B: AsRef<[u8]>>(
+        cipher: C,
+        initial_block: B,
+        layout: CounterLayout,
+    ) -> Result<Self, ModeError> {
+        let initial_block = initial_block.as_ref();
+
+        if initial_block.len() != C::BLOCK_SIZE {
+            return Err(ModeError::WrongSizeIv);
+        }
+
+        if layout.length == 0 || layout.length > 16 || layout.offset + layout.length > C::BLOCK_SIZE
+        {
+            return Err(ModeError::InvalidCounterLayout);
+        }
+
+        Ok(Self {
+            cipher,
+            initial_block: initial_block.to_vec(),
+            initial_counter: layout.read(initial_block),
+            layout,
+            key_stream: vec![0; C::BLOCK_SIZE],
+            key_stream_block: None,
+            position: 0,
+        })
+    }
+
+    pub fn with_nonce(cipher: C, nonce: u64) -> Self {
+        let half = C::BLOCK_SIZE / 2;
+
+        let nonce_bytes = std::cmp::min(half, 8);
+        let counter_bytes = std::cmp::min(C::BLOCK_SIZE - half, 8);
+
+        let mut initial_block = vec![0; C::BLOCK_SIZE];
+        initial_block[..nonce_bytes].copy_from_slice(&nonce.to_le_bytes()[..nonce_bytes]);
+
+        let layout = CounterLayout::new(half, counter_bytes, Endianness::Little);
+
+        Self::new(cipher, initial_block, layout).unwrap()
+    }
+
+    pub fn with_iv<V: AsRef<[u8]>>(cipher: C, iv: V) -> Result<Self, ModeError> {
+        Self::new(cipher, iv, CounterLayout::full_block(C::BLOCK_SIZE))
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn seek(&mut self, position: u64) {
+        self.position = position;
+    }
+
+    /* Panics once the counter field runs out, wrapping would reuse key stream */
+    pub fn counter_block(&self, block_index: u64) -> Vec<u8> {
+        let counter = self
+            .initial_counter
+            .checked_add(block_index as u128)
+            .filter(|counter| *counter <= self.layout.mask())
+            .expect("CTR counter field exhausted");
+
+        let mut block = self.initial_block.clone();
+        self.layout.write(&mut block, counter);
+
+        block
+    }
+
+    pub fn apply_key_stream(&mut self, buf: &mut [u8]) {
+        let mut done = 0;
+
+        while done < buf.len() {
+            let index = self.load_key_stream();
+            let take = std::cmp::min(C::BLOCK_SIZE - index, buf.len() - done);
+
+            for (byte, key) in buf[done..done + take]
+                .iter_mut()
+                .zip(self.key_stream[index..index + take].iter())
+            {
+                *byte ^= key;
+            }
+
+            done += take;
+            self.position += take as u64;
+        }
+    }
+
+    fn load_key_stream(&mut self) -> usize {
+        let block_index = self.position / C::BLOCK_SIZE as u64;
+
+        if self.key_stream_block != Some(block_index) {
+            self.key_stream = self.counter_block(block_index);
+            self.cipher.encrypt_block(&mut self.key_stream);
+            self.key_stream_block = Some(block_index);
+        }
+
+        (self.position % C::BLOCK_SIZE as u64) as usize
+    }
+}
+
+impl<C: BlockCipher> Iterator for Ctr<C> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.load_key_stream();
+        self.position += 1;
+
+        Some(self.key_stream[index])
+    }
+}
+
+pub fn ctr<C: BlockCipher, I: AsRef<[u8]>>(cipher: &C, input: I, nonce: u64) -> Vec<u8> {
+    let mut output = input.as_ref().to_vec();
+
+    Ctr::with_nonce(cipher, nonce).apply_key_stream(&mut output);
+
+    output
+}
+
+pub fn cbc_encrypt<C: BlockCipher, I: AsRef<[u8]>, V: AsRef<[u8]>>(
+    cipher: &C,
+    input: I,
+    iv: V,
+) -> Result<Vec<u8>, ModeError> {
+    if iv.as_ref().len() != C::BLOCK_SIZE {
+        return Err(ModeError::WrongSizeIv);
+    }
+
+    let mut last_cipher_block = iv.as_ref().to_vec();
+    let mut ciphertext = Vec::new();
+
+    for mut block in PaddedBlocks::new(input.as_ref(), C::BLOCK_SIZE) {
+        xor_inplace(&mut block, &last_cipher_block);
+        cipher.encrypt_block(&mut block);
+
+        ciphertext.extend_from_slice(&block);
+        last_cipher_block = block;
+    }
+
+    Ok(ciphertext)
+}
+
+pub fn cbc_decrypt<C: BlockCipher, I: AsRef<[u8]>, V: AsRef<[u8]>>(
+    cipher: &C,
+    input: I,
+    iv: V,
+) -> Result<Vec<u8>, ModeError> {
+    if input.as_ref().len() % C::BLOCK_SIZE != 0 {
+        return Err(ModeError::IrregularDecryptLength);
+    }
+
+    if iv.as_ref().len() != C::BLOCK_SIZE {
+        return Err(ModeError::WrongSizeIv);
+    }
+
+    let mut last_cipher_block = iv.as_ref();
+    let mut cleartext = Vec::with_capacity(input.as_ref().len());
+
+    for block in input.as_ref().chunks_exact(C::BLOCK_SIZE) {
+        let mut clear_block = block.to_vec();
+
+        cipher.decrypt_block(&mut clear_block);
+        xor_inplace(&mut clear_block, last_cipher_block);
+
+        last_cipher_block = block;
+
+        cleartext.extend_from_slice(&clear_block);
+    }
+
+    let cleartext_end = pkcs7::unpad_length(&cleartext);
+
+    if cleartext_end == cleartext.len() {
+        return Err(ModeError::PaddingError);
+    }
+
+    cleartext.truncate(cleartext_end);
+
+    Ok(cleartext)
+}
+
+pub fn ecb_encrypt<C: BlockCipher, I: AsRef<[u8]>>(cipher: &C, input: I) -> Vec<u8> {
+    PaddedBlocks::new(input.as_ref(), C::BLOCK_SIZE)
+        .flat_map(|mut block| {
+            cipher.encrypt_block(&mut block);
+            block
+        })
+        .collect()
+}
+
+pub fn ecb_decrypt<C: BlockCipher, I: AsRef<[u8]>>(
+    cipher: &C,
+    input: I,
+) -> Result<Vec<u8>, ModeError> {
+    if input.as_ref().len() % C::BLOCK_SIZE != 0 {
+        return Err(ModeError::IrregularDecryptLength);
+    }
+
+    let mut cleartext = input.as_ref().to_vec();
+
+    for block in cleartext.chunks_exact_mut(C::BLOCK_SIZE) {
+        cipher.decrypt_block(block);
+    }
+
+    Ok(cleartext)
+}
+
+pub fn gcm_encrypt<C: BlockCipher, I: AsRef<[u8]>, V: AsRef<[u8]>, A: AsRef<[u8]>>(
+    cipher: &C,
+    input: I,
+    iv: V,
+    associated_data: A,
+    tag_length: usize,
+) -> Result<(Vec<u8>, Vec<u8>), ModeError> {
+    const { assert!(C::BLOCK_SIZE == 16, "GCM requires a 128-bit block cipher") };
+
+    if !GCM_TAG_LENGTHS.contains(&tag_length) {
+        return Err(ModeError::WrongSizeTag);
+    }
+
+    let j0 = gcm_pre_counter_block(cipher, iv.as_ref())?;
+
+    let ciphertext = gcm_ctr(cipher, input.as_ref(), inc32(j0));
+
+    let mut tag = gcm_tag(cipher, associated_data.as_ref(), &ciphertext, j0);
+    tag.truncate(tag_length);
+
+    Ok((ciphertext, tag))
+}
+
+pub fn gcm_decrypt<
+    C: BlockCipher,
+    I: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+    A: AsRef<[u8]>,
+    T: AsRef<[u8]>,
+>(
+    cipher: &C,
+    input: I,
+    iv: V,
+    associated_data: A,
+    tag: T,
+) -> Result<Vec<u8>, ModeError> {
+    const { assert!(C::BLOCK_SIZE == 16, "GCM requires a 128-bit block cipher") };
+
+    let tag = tag.as_ref();
+
+    if !GCM_TAG_LENGTHS.contains(&tag.len()) {
+        return Err(ModeError::WrongSizeTag);
+    }
+
+    let j0 = gcm_pre_counter_block(cipher, iv.as_ref())?;
+
+    let computed_tag = gcm_tag(cipher, associated_data.as_ref(), input.as_ref(), j0);
+
+    if !constant_time::eq(&computed_tag[..tag.len()], tag) {
+        return Err(ModeError::AuthenticationFailed);
+    }
+
+    Ok(gcm_ctr(cipher, input.as_ref(), inc32(j0)))
+}
+
+struct PaddedBlocks<'a> {
+    block_size: usize,
+    create_padded_block: bool,
+    chunks: std::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> PaddedBlocks<'a> {
+    fn new(input: &'a [u8], block_size: usize) -> Self {
+        Self {
+            block_size,
+            create_padded_block: true,
+            chunks: input.chunks_exact(block_size),
+        }
+    }
+}
+
+impl Iterator for PaddedBlocks<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.chunks.next(), self.create_padded_block) {
+            (Some(chunk), _) => Some(chunk.to_vec()),
+
+            (None, true) => {
+                self.create_padded_block = false;
+
+                let rest = self.chunks.remainder();
+                let bytes_to_copy = rest.len();
+
+                let mut block = vec![0; self.block_size];
+                block[..bytes_to_copy].copy_from_slice(rest);
+
+                pkcs7::pad_inplace(&mut block, bytes_to_copy);
+
+                Some(block)
+            }
+
+            (None, false) => None,
+        }
+    }
+}
+
+fn xor_inplace(block: &mut [u8], rhs: &[u8]) {
+    for (a, b) in block.iter_mut().zip(rhs.iter()) {
+        *a ^= b;
+    }
+}
+
+const GCM_TAG_LENGTHS: [usize; 7] = [16, 15, 14, 13, 12, 8, 4];
+
+fn gcm_pre_counter_block<C: BlockCipher>(cipher: &C, iv: &[u8]) -> Result<[u8; 16], ModeError> {
+    if iv.is_empty() {
+        return Err(ModeError::WrongSizeIv);
+    }
+
+    if iv.len() == 12 {
+        let mut j0 = [0; 16];
+
+        j0[..12].copy_from_slice(iv);
+        j0[15] = 1;
+
+        return Ok(j0);
+    }
+
+    let mut ghash = Ghash::new(cipher);
+
+    ghash.update_padded(iv);
+    ghash.update_lengths(0, iv.len());
+
+    Ok(ghash.finalize())
+}
+
+fn gcm_tag<C: BlockCipher>(
+    cipher: &C,
+    associated_data: &[u8],
+    ciphertext: &[u8],
+    mut j0: [u8; 16],
+) -> Vec<u8> {
+    let mut ghash = Ghash::new(cipher);
+
+    ghash.update_padded(associated_data);
+    ghash.update_padded(ciphertext);
+    ghash.update_lengths(associated_data.len(), ciphertext.len());
+
+    cipher.encrypt_block(&mut j0);
+
+    let mut tag = ghash.finalize();
+    xor_inplace(&mut tag, &j0);
+
+    tag.to_vec()
+}
+
+fn gcm_ctr<C: BlockCipher>(cipher: &C, input: &[u8], mut counter_block: [u8; 16]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len());
+
+    for block in input.chunks(16) {
+        let mut key_stream = counter_block;
+        cipher.encrypt_block(&mut key_stream);
+
+        output.extend(block.iter().zip(key_stream.iter()).map(|(a, b)| a ^ b));
+
+        counter_block = inc32(counter_block);
+    }
+
+    output
+}
+
+fn inc32(mut block: [u8; 16]) -> [u8; 16] {
+    let counter = u32::from_be_bytes(block[12..].try_into().unwrap()).wrapping_add(1);
+    block[12..].copy_from_slice(&counter.to_be_bytes());
+
+    block
+}
+
+struct Ghash {
+    h: u128,
+    y: u128,
+}
+
+impl Ghash {
+    fn new<C: BlockCipher>(cipher: &C) -> Self {
+        let mut h = [0; 16];
+        cipher.encrypt_block(&mut h);
+
+        Self {
+            h: u128::from_be_bytes(h),
+            y: 0,
+        }
+    }
+
+    fn update_padded(&mut self, input: &[u8]) {
+        for chunk in input.chunks(16) {
+            let mut block: [u8; 16] = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+
+            self.update_block(u128::from_be_bytes(block));
+        }
+    }
+
+    fn update_lengths(&mut self, first_length: usize, second_length: usize) {
+        let lengths = ((first_length as u128 * 8) << 64) | (second_length as u128 * 8);
+
+        self.update_block(lengths);
+    }
+
+    fn update_block(&mut self, block: u128) {
+        self.y = gf128_mul(self.y ^ block, self.h);
+    }
+
+    fn finalize(self) -> [u8; 16] {
+        self.y.to_be_bytes()
+    }
+}
+
+fn gf128_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+
+    let mut z = 0;
+    let mut v = y;
+
+    for i in (0..128).rev() {
+        let x_bit = (x >> i) & 1;
+        z ^= v & x_bit.wrapping_neg();
+
+        let v_bit = v & 1;
+        v = (v >> 1) ^ (R & v_bit.wrapping_neg());
+    }
+
+    z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{aes_ctr, Aes, AesKey};
+    use crate::base64::base64_to_bytes;
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    fn aes(key: &str) -> Aes {
+        Aes::new(&AesKey::from(&h(key)).unwrap())
+    }
+
+    /* NIST SP 800-38A F.5 */
+    const CTR_IV: &str = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
+    const CTR_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                                 30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn ctr_sp800_38a() {
+        let cases = [
+            (
+                "2b7e151628aed2a6abf7158809cf4f3c",
+                "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+                 5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee",
+            ),
+            (
+                "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+                "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+                 2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6",
+            ),
+        ];
+
+        for (key, expected) in cases {
+            let mut buffer = h(CTR_PLAINTEXT);
+
+            Ctr::with_iv(aes(key), h(CTR_IV))
+                .unwrap()
+                .apply_key_stream(&mut buffer);
+            assert_eq!(buffer, h(expected));
+
+            Ctr::with_iv(aes(key), h(CTR_IV))
+                .unwrap()
+                .apply_key_stream(&mut buffer);
+            assert_eq!(buffer, h(CTR_PLAINTEXT));
+        }
+    }
+
+    #[test]
+    fn ctr_custom_layout() {
+        let layout = CounterLayout::new(12, 4, Endianness::Big);
+        let initial_block = h("000102030405060708090a0b00000010");
+        let mut ctr = Ctr::new(
+            aes("2b7e151628aed2a6abf7158809cf4f3c"),
+            &initial_block,
+            layout,
+        )
+        .unwrap();
+
+        assert_eq!(ctr.counter_block(2), h("000102030405060708090a0b00000012"));
+
+        let mut buffer = h(CTR_PLAINTEXT)[..40].to_vec();
+        ctr.apply_key_stream(&mut buffer);
+
+        assert_eq!(
+            buffer,
+            h(
+                "17a3a58da5979820ef3b27ca54dbe3b924fa443b2fc1d9a5a7ade2041dca1190\
+               a5b16b3bf9fb2120"
+            )
+        );
+
+        for layout in [
+            CounterLayout::new(0, 0, Endianness::Big),
+            CounterLayout::new(13, 4, Endianness::Big),
+            CounterLayout::new(0, 17, Endianness::Little),
+        ] {
+            assert!(matches!(
+                Ctr::new(aes(KEY), &initial_block, layout),
+                Err(ModeError::InvalidCounterLayout)
+            ));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "CTR counter field exhausted")]
+    fn ctr_counter_does_not_wrap() {
+        let layout = CounterLayout::new(15, 1, Endianness::Big);
+        let ctr = Ctr::new(aes(KEY), h("000000000000000000000000000000fe"), layout).unwrap();
+
+        assert_eq!(ctr.counter_block(1), h("000000000000000000000000000000ff"));
+
+        ctr.counter_block(2);
+    }
+
+    #[test]
+    fn ctr_seek_matches_full_pass() {
+        let cipher = aes(KEY);
+        let message: Vec<u8> = (0..100).collect();
+
+        let mut full = message.clone();
+        Ctr::with_nonce(&cipher, 3).apply_key_stream(&mut full);
+
+        for offset in [0usize, 1, 15, 16, 17, 50, 99, 100] {
+            let mut ctr = Ctr::with_nonce(&cipher, 3);
+            let mut suffix = message[offset..].to_vec();
+
+            ctr.seek(offset as u64);
+            ctr.apply_key_stream(&mut suffix);
+
+            assert_eq!(suffix, full[offset..]);
+            assert_eq!(ctr.position(), 100);
+        }
+    }
+
+    #[test]
+    fn ctr_with_nonce_matches_aes_ctr() {
+        let key = AesKey::from(b"YELLOW SUBMARINE").unwrap();
+        let ciphertext = base64_to_bytes(
+            "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+        );
+
+        let mut cleartext = ciphertext.clone();
+        Ctr::with_nonce(Aes::new(&key), 0).apply_key_stream(&mut cleartext);
+
+        assert_eq!(
+            cleartext,
+            b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+        );
+        assert_eq!(aes_ctr(&ciphertext, &key, 0), cleartext);
+
+        let message: Vec<u8> = (0..77).collect();
+        let mut output = message.clone();
+        Ctr::with_nonce(Aes::new(&key), u64::MAX - 1).apply_key_stream(&mut output);
+
+        assert_eq!(aes_ctr(&message, &key, u64::MAX - 1), output);
+    }
+
+    /* Test cases from McGrew and Viega, "The Galois/Counter Mode of Operation" */
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308";
+    const IV: &str = "cafebabefacedbaddecaf888";
+    const AAD: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+    const PLAINTEXT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+
+    #[test]
+    fn gcm_empty_plaintext() {
+        let cipher = aes("00000000000000000000000000000000");
+
+        let (ciphertext, tag) = gcm_encrypt(&cipher, [], [0u8; 12], [], 16).unwrap();
+
+        assert!(ciphertext.is_empty());
+        assert_eq!(tag, h("58e2fccefa7e3061367f1d57a4e7455a"));
+
+        let (ciphertext, tag) = gcm_encrypt(&cipher, [0u8; 16], [0u8; 12], [], 16).unwrap();
+
+        assert_eq!(ciphertext, h("0388dace60b6a392f328c2b971b2fe78"));
+        assert_eq!(tag, h("ab6e47d42cec13bdf53a67b21257bddf"));
+    }
+
+    #[test]
+    fn gcm_associated_data_only() {
+        let cipher = aes("77be63708971c4e240d1cb79e8d77feb");
+        let iv = h("e0e00f19fed7ba0136a797f3");
+        let aad = h("7a43ec1d9c0a5a78a0b16533a6213cab");
+
+        let (ciphertext, tag) = gcm_encrypt(&cipher, [], &iv, &aad, 16).unwrap();
+
+        assert!(ciphertext.is_empty());
+        assert_eq!(tag, h("209fcc8d3675ed938e9c7166709dd946"));
+        assert!(gcm_decrypt(&cipher, [], &iv, &aad, &tag)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn gcm_96_bit_iv() {
+        let cipher = aes(KEY);
+
+        let plaintext = h(&(PLAINTEXT.to_string() + "1aafd255"));
+        let (ciphertext, tag) = gcm_encrypt(&cipher, &plaintext, h(IV), [], 16).unwrap();
+
+        assert_eq!(
+            ciphertext,
+            h(
+                "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+               21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985"
+            )
+        );
+        assert_eq!(tag, h("4d5c2af327cd64a62cf35abd2ba6fab4"));
+
+        let (ciphertext, tag) = gcm_encrypt(&cipher, h(PLAINTEXT), h(IV), h(AAD), 16).unwrap();
+
+        assert_eq!(
+            ciphertext,
+            h(
+                "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+               21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091"
+            )
+        );
+        assert_eq!(tag, h("5bc94fbc3221a5db94fae95ae7121a47"));
+        assert_eq!(
+            gcm_decrypt(&cipher, &ciphertext, h(IV), h(AAD), &tag).unwrap(),
+            h(PLAINTEXT)
+        );
+    }
+
+    #[test]
+    fn gcm_other_iv_lengths() {
+        let cipher = aes(KEY);
+
+        let (ciphertext, tag) =
+            gcm_encrypt(&cipher, h(PLAINTEXT), h("cafebabefacedbad"), h(AAD), 16).unwrap();
+
+        assert_eq!(
+            ciphertext,
+            h(
+                "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+               73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598"
+            )
+        );
+        assert_eq!(tag, h("3612d2e79e3b0785561be14aaca2fccb"));
+
+        let iv = h(
+            "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+                    c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+        );
+        let (ciphertext, tag) = gcm_encrypt(&cipher, h(PLAINTEXT), &iv, h(AAD), 16).unwrap();
+
+        assert_eq!(
+            ciphertext,
+            h(
+                "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+               01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5"
+            )
+        );
+        assert_eq!(tag, h("619cc5aefffe0bfa462af43c1699d050"));
+        assert_eq!(
+            gcm_decrypt(&cipher, &ciphertext, &iv, h(AAD), &tag).unwrap(),
+            h(PLAINTEXT)
+        );
+
+        assert!(matches!(
+            gcm_encrypt(&cipher, h(PLAINTEXT), [], h(AAD), 16),
+            Err(ModeError::WrongSizeIv)
+        ));
+    }
+
+    #[test]
+    fn gcm_truncated_tags() {
+        let cipher = aes("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308");
+
+        let (ciphertext, tag) = gcm_encrypt(&cipher, h(PLAINTEXT), h(IV), h(AAD), 12).unwrap();
+
+        assert_eq!(
+            ciphertext,
+            h(
+                "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+               8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662"
+            )
+        );
+        assert_eq!(tag, h("76fc6ece0f4e1768cddf8853"));
+
+        for length in [4, 8, 13, 14, 15] {
+            let (_, short) = gcm_encrypt(&cipher, h(PLAINTEXT), h(IV), h(AAD), length).unwrap();
+
+            assert_eq!(short, h("76fc6ece0f4e1768cddf8853bb2d551b")[..length]);
+            assert!(gcm_decrypt(&cipher, &ciphertext, h(IV), h(AAD), &short).is_ok());
+        }
+
+        for length in [0, 3, 11, 17] {
+            assert!(matches!(
+                gcm_encrypt(&cipher, h(PLAINTEXT), h(IV), h(AAD), length),
+                Err(ModeError::WrongSizeTag)
+            ));
+        }
+
+        assert!(matches!(
+            gcm_decrypt(&cipher, &ciphertext, h(IV), h(AAD), &tag[..11]),
+            Err(ModeError::WrongSizeTag)
+        ));
+    }
+
+    #[test]
+    fn gcm_rejects_tampering() {
+        let cipher = aes(KEY);
+
+        let (ciphertext, tag) = gcm_encrypt(&cipher, h(PLAINTEXT), h(IV), h(AAD), 16).unwrap();
+
+        let mut bad_tag = tag.clone();
+        bad_tag[15] ^= 1;
+
+        let mut bad_ciphertext = ciphertext.clone();
+        bad_ciphertext[0] ^= 0x80;
+
+        let mut bad_aad = h(AAD);
+        bad_aad.push(0);
+
+        for (ciphertext, aad, tag) in [
+            (&ciphertext, &h(AAD), &bad_tag),
+            (&bad_ciphertext, &h(AAD), &tag),
+            (&ciphertext, &bad_aad, &tag),
+        ] {
+            assert!(matches!(
+                gcm_decrypt(&cipher, ciphertext, h(IV), aad, tag),
+                Err(ModeError::AuthenticationFailed)
+            ));
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use super::ubig::{Montgomery, Ubig};
+use super::urandom;
+
+const MILLER_RABIN_ROUNDS: usize = 40;
+
+const SMALL_PRIME_LIMIT: usize = 1000;
+const SMALL_PRIMES: [u32; 168] = small_primes();
+
+pub fn is_probable_prime(n: &Ubig) -> bool {
+    is_probable_prime_with_rounds(n, MILLER_RABIN_ROUNDS)
+}
+
+pub fn is_probable_prime_with_rounds(n: &Ubig, rounds: usize) -> bool {
+    if *n < Ubig::from(2u64) {
+        return false;
+    }
+
+    match trial_division(n) {
+        Some(prime) => prime,
+        None => miller_rabin(n, rounds),
+    }
+}
+
+pub fn is_safe_prime(p: &Ubig) -> bool {
+    if !p.bit(0) {
+        return false;
+    }
+
+    let q = p >> 1;
+
+    is_probable_prime(&q) && is_probable_prime(p)
+}
+
+pub fn miller_rabin(n: &Ubig, rounds: usize) -> bool {
+    let one = Ubig::from(1u64);
+    let two = Ubig::from(2u64);
+    let three = Ubig::from(3u64);
+
+    if *n < two {
+        return false;
+    }
+
+    if *n <= three {
+        return true;
+    }
+
+    if !n.bit(0) {
+        return false;
+    }
+
+    let n_minus_one = n - &one;
+    let n_minus_three = &n_minus_one - &two;
+
+    /* n - 1 = 2^s * d with d odd */
+    let mut d = n_minus_one.clone();
+    let mut s = 0;
+
+    while !d.bit(0) {
+        d >>= 1;
+        s += 1;
+    }
+
+    let montgomery = Montgomery::new(n);
+    let minus_one = montgomery.to_montgomery(&n_minus_one);
+
+    'witness: for _ in 0..rounds {
+        let a = random_below(&n_minus_three) + two.clone();
+
+        let x = montgomery.modexp(&a, &d);
+
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+
+        /* Square in Montgomery form, comparing against -1 in the same form */
+        let mut x = montgomery.to_montgomery(&x);
+
+        for _ in 1..s {
+            x = montgomery.mul(&x, &x);
+
+            if x == minus_one {
+                continue 'witness;
+            }
+        }
+
+        return false;
+    }
+
+    true
+}
+
+pub fn random_prime(bits: usize) -> Ubig {
+    assert!(bits >= 2, "a prime needs at least two bits");
+
+    loop {
+        let candidate = random_candidate(bits);
+
+        if is_probable_prime(&candidate) {
+            return candidate;
+        }
+    }
+}
+
+/* p = 2q + 1 with q prime as well */
+pub fn random_safe_prime(bits: usize) -> Ubig {
+    assert!(bits >= 3, "a safe prime needs at least three bits");
+
+    let one = Ubig::from(1u64);
+
+    loop {
+        let q = random_candidate(bits - 1);
+
+        let p = (&q << 1) + &one;
+
+        /* Sieve both halves before paying for any Miller-Rabin rounds */
+        if trial_division(&q) == Some(false) || trial_division(&p) == Some(false) {
+            continue;
+        }
+
+        if is_probable_prime(&q) && is_probable_prime(&p) {
+            return p;
+        }
+    }
+}
+
+/*
+ * Odd number of exactly `bits` bits with the top two bits set, so that the
+ * product of two such numbers has exactly twice as many bits.
+ */
+fn random_candidate(bits: usize) -> Ubig {
+    let mut bytes = urandom::bytes(bits.div_ceil(8));
+
+    let top_bits = bits - 8 * (bytes.len() - 1);
+    let top = bytes.last_mut().unwrap();
+
+    *top &= 0xffu8 >> (8 - top_bits);
+    *top |= 0x80u8 >> (8 - top_bits);
+
+    if top_bits > 1 {
+        *top |= 0x40u8 >> (8 - top_bits);
+    } else {
+        let len = bytes.len();
+        bytes[len - 2] |= 0x80;
+    }
+
+    bytes[0] |= 1;
+
+    Ubig::from(bytes.as_ref())
+}
+
+fn random_below(limit: &Ubig) -> Ubig {
+    let bytes = urandom::bytes(limit.as_ref().len() + 8);
+
+    Ubig::from(bytes.as_ref()) % limit
+}
+
+/*
+ * Some(true) or Some(false) when the small primes settle the question,
+ * None when n has no small factor but is too large to be known prime.
+ */
+fn trial_division(n: &Ubig) -> Option<bool> {
+    let small_value = u64::try_from(n).ok();
+
+    for prime in SMALL_PRIMES {
+        if remainder(n, prime) == 0 {
+            return Some(small_value == Some(prime as u64));
+        }
+    }
+
+    match small_value {
+        Some(value) if value < (SMALL_PRIME_LIMIT * SMALL_PRIME_LIMIT) as u64 => Some(true),
+        _ => None,
+    }
+}
+
+fn remainder(n: &Ubig, divisor: u32) -> u32 {
+    n.as_ref()
+        .iter()
+        .rev()
+        .fold(0, |rem, byte| ((rem << 8) | *byte as u32) % divisor)
+}
+
+const fn small_primes<const N: usize>() -> [u32; N] {
+    let mut composite = [false; SMALL_PRIME_LIMIT];
+    let mut primes = [0u32; N];
+    let mut count = 0;
+
+    let mut i = 2;
+
+    while i < SMALL_PRIME_LIMIT {
+        if !composite[i] {
+            primes[count] = i as u32;
+            count += 1;
+
+            let mut j = i * i;
+
+            while j < SMALL_PRIME_LIMIT {
+                composite[j] = true;
+                j += i;
+            }
+        }
+
+        i += 1;
+    }
+
+    assert!(count == N, "wrong number of small primes");
+
+    primes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* (6k + 1)(12k + 1)(18k + 1) with k = 10^20 + 8960, all three factors prime */
+    const LARGE_CARMICHAEL: &str =
+        "1296000000000000348368760000000031214195715600000932274576092161";
+
+    #[test]
+    fn small_numbers() {
+        let primes = [2u64, 3, 5, 7, 11, 97, 997, 1009, 7919, 999983, 1000003];
+        let composites = [0u64, 1, 4, 9, 15, 91, 1001, 994009, 1000001, 999985999949];
+
+        for n in primes {
+            assert!(is_probable_prime(&Ubig::from(n)), "{}", n);
+        }
+
+        for n in composites {
+            assert!(!is_probable_prime(&Ubig::from(n)), "{}", n);
+        }
+    }
+
+    #[test]
+    fn carmichael_numbers() {
+        for n in [561u64, 41041, 825265] {
+            let n = Ubig::from(n);
+
+            assert!(!is_probable_prime(&n));
+            assert!(!miller_rabin(&n, MILLER_RABIN_ROUNDS));
+        }
+
+        /* No factor below 1000, so this one gets past trial division */
+        let n = LARGE_CARMICHAEL.parse::<Ubig>().unwrap();
+
+        assert_eq!(trial_division(&n), None);
+        assert!(!is_probable_prime(&n));
+    }
+
+    #[test]
+    fn large_primes() {
+        let mersenne_127 = (Ubig::from(1u64) << 127) - Ubig::from(1u64);
+        let mersenne_128 = (Ubig::from(1u64) << 128) - Ubig::from(1u64);
+
+        assert!(is_probable_prime(&mersenne_127));
+        assert!(!is_probable_prime(&mersenne_128));
+        assert!(!is_probable_prime(&(&mersenne_127 * &mersenne_127)));
+    }
+
+    #[test]
+    fn safe_primes() {
+        for p in [5u64, 7, 11, 23, 47, 59, 83, 107] {
+            assert!(is_safe_prime(&Ubig::from(p)), "{}", p);
+        }
+
+        /* 29 and 13 are prime, but (p - 1) / 2 is not */
+        for p in [2u64, 3, 13, 29, 31, 45, 46] {
+            assert!(!is_safe_prime(&Ubig::from(p)), "{}", p);
+        }
+    }
+
+    #[test]
+    fn random_primes_have_the_requested_length() {
+        for bits in [2usize, 3, 8, 9, 63, 64, 65, 128, 255] {
+            let p = random_prime(bits);
+
+            assert_eq!(p.bit_length(), bits);
+            assert!(is_probable_prime(&p));
+        }
+
+        for bits in [3usize, 8, 64, 129] {
+            let p = random_safe_prime(bits);
+
+            assert_eq!(p.bit_length(), bits);
+            assert!(is_safe_prime(&p));
+        }
+    }
+}
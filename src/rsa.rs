@@ -0,0 +1,542 @@
+use super::constant_time;
+use super::digest::Digest;
+use super::prime;
+use super::sha::{Sha1, Sha224, Sha256, Sha384, Sha512};
+use super::ubig::Ubig;
+use super::urandom;
+
+pub trait Pkcs1Digest: Digest {
+    const DIGEST_INFO: &'static [u8];
+}
+
+impl Pkcs1Digest for Sha1 {
+    const DIGEST_INFO: &'static [u8] = &[
+        0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14,
+    ];
+}
+
+impl Pkcs1Digest for Sha224 {
+    const DIGEST_INFO: &'static [u8] = &[
+        0x30, 0x2d, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04,
+        0x05, 0x00, 0x04, 0x1c,
+    ];
+}
+
+impl Pkcs1Digest for Sha256 {
+    const DIGEST_INFO: &'static [u8] = &[
+        0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01,
+        0x05, 0x00, 0x04, 0x20,
+    ];
+}
+
+impl Pkcs1Digest for Sha384 {
+    const DIGEST_INFO: &'static [u8] = &[
+        0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02,
+        0x05, 0x00, 0x04, 0x30,
+    ];
+}
+
+impl Pkcs1Digest for Sha512 {
+    const DIGEST_INFO: &'static [u8] = &[
+        0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03,
+        0x05, 0x00, 0x04, 0x40,
+    ];
+}
+
+#[derive(Debug)]
+pub enum RsaError {
+    MessageOutOfRange,
+    ModulusTooShort,
+    InvalidExponent,
+    InvalidKey,
+    FaultDetected,
+}
+
+#[derive(Clone)]
+pub struct RsaPublicKey {
+    n: Ubig,
+    e: Ubig,
+}
+
+impl RsaPublicKey {
+    pub fn new(n: Ubig, e: Ubig) -> Self {
+        Self { n, e }
+    }
+
+    pub fn modulus(&self) -> &Ubig {
+        &self.n
+    }
+
+    pub fn exponent(&self) -> &Ubig {
+        &self.e
+    }
+
+    pub fn size(&self) -> usize {
+        self.n.bit_length().div_ceil(8)
+    }
+
+    pub fn encrypt_raw(&self, message: &[u8]) -> Result<Vec<u8>, RsaError> {
+        let m = os2ip(message);
+
+        if m >= self.n {
+            return Err(RsaError::MessageOutOfRange);
+        }
+
+        i2osp(Ubig::modexp(m, self.e.clone(), self.n.clone()), self.size())
+    }
+
+    pub fn verify_pkcs1v15<D: Pkcs1Digest>(&self, message: &[u8], signature: &[u8]) -> bool {
+        let k = self.size();
+
+        if signature.len() != k {
+            return false;
+        }
+
+        let (encoded, expected) = match (
+            self.encrypt_raw(signature),
+            pkcs1v15_encode::<D>(message, k),
+        ) {
+            (Ok(encoded), Ok(expected)) => (encoded, expected),
+            _ => return false,
+        };
+
+        constant_time::eq(&encoded, &expected)
+    }
+
+    pub fn verify_pss<D: Digest>(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        salt_length: usize,
+    ) -> bool {
+        let k = self.size();
+
+        if signature.len() != k {
+            return false;
+        }
+
+        let encoded = match self.encrypt_raw(signature) {
+            Ok(encoded) => encoded,
+            Err(_) => return false,
+        };
+
+        let em_bits = self.n.bit_length() - 1;
+        let em_len = em_bits.div_ceil(8);
+
+        /* The encoded message is one byte shorter when the modulus is 8n + 1 bits */
+        if encoded[..k - em_len].iter().any(|byte| *byte != 0) {
+            return false;
+        }
+
+        pss_verify::<D>(message, &encoded[k - em_len..], em_bits, salt_length)
+    }
+}
+
+#[derive(Clone)]
+pub struct RsaPrivateKey {
+    public: RsaPublicKey,
+    d: Ubig,
+    p: Ubig,
+    q: Ubig,
+    dp: Ubig,
+    dq: Ubig,
+    qinv: Ubig,
+}
+
+impl RsaPrivateKey {
+    /* The exponent must be odd and at least 3, or no choice of primes would work */
+    pub fn generate(modulus_bits: usize, exponent: u32) -> Result<Self, RsaError> {
+        if modulus_bits < 16 {
+            return Err(RsaError::ModulusTooShort);
+        }
+
+        if exponent < 3 || exponent.is_multiple_of(2) {
+            return Err(RsaError::InvalidExponent);
+        }
+
+        let e = Ubig::from(exponent.to_le_bytes().as_ref());
+
+        let p_bits = modulus_bits / 2;
+        let q_bits = modulus_bits - p_bits;
+
+        loop {
+            let p = prime::random_prime(p_bits);
+            let q = prime::random_prime(q_bits);
+
+            if p == q {
+                continue;
+            }
+
+            /* Retry when e shares a factor with p - 1 or q - 1 */
+            if let Ok(key) = Self::from_primes(p, q, e.clone()) {
+                return Ok(key);
+            }
+        }
+    }
+
+    pub fn from_primes(p: Ubig, q: Ubig, e: Ubig) -> Result<Self, RsaError> {
+        let one = Ubig::from(1u64);
+
+        let p_minus_one = &p - &one;
+        let q_minus_one = &q - &one;
+
+        let gcd = Ubig::gcd(p_minus_one.clone(), q_minus_one.clone());
+        let lambda = &p_minus_one * &q_minus_one / &gcd;
+
+        let d = Ubig::modinv(e.clone(), lambda).ok_or(RsaError::InvalidKey)?;
+        let qinv = Ubig::modinv(q.clone(), p.clone()).ok_or(RsaError::InvalidKey)?;
+
+        let dp = &d % &p_minus_one;
+        let dq = &d % &q_minus_one;
+
+        let n = &p * &q;
+
+        Ok(Self {
+            public: RsaPublicKey::new(n, e),
+            d,
+            p,
+            q,
+            dp,
+            dq,
+            qinv,
+        })
+    }
+
+    pub fn public_key(&self) -> &RsaPublicKey {
+        &self.public
+    }
+
+    pub fn private_exponent(&self) -> &Ubig {
+        &self.d
+    }
+
+    pub fn decrypt_raw(&self, ciphertext: &[u8]) -> Result<Vec<u8>, RsaError> {
+        let c = os2ip(ciphertext);
+
+        if c >= self.public.n {
+            return Err(RsaError::MessageOutOfRange);
+        }
+
+        i2osp(self.private_operation(c)?, self.public.size())
+    }
+
+    pub fn sign_pkcs1v15<D: Pkcs1Digest>(&self, message: &[u8]) -> Result<Vec<u8>, RsaError> {
+        let encoded = pkcs1v15_encode::<D>(message, self.public.size())?;
+
+        self.decrypt_raw(&encoded)
+    }
+
+    pub fn sign_pss<D: Digest>(
+        &self,
+        message: &[u8],
+        salt_length: usize,
+    ) -> Result<Vec<u8>, RsaError> {
+        let salt = urandom::bytes(salt_length);
+
+        let em_bits = self.public.n.bit_length() - 1;
+        let encoded = pss_encode::<D>(message, em_bits, &salt)?;
+
+        self.decrypt_raw(&encoded)
+    }
+
+    /*
+     * The input is blinded with r^e so the exponentiation never sees c itself,
+     * and the result is checked with the public exponent so that a faulty CRT
+     * half cannot leak a factor of n.
+     */
+    fn private_operation(&self, c: Ubig) -> Result<Ubig, RsaError> {
+        let n = &self.public.n;
+        let (r, r_inv) = self.blinding_factor();
+
+        let blinded = c.clone() * Ubig::modexp(r, self.public.e.clone(), n.clone()) % n;
+        let m = self.crt_power(blinded) * r_inv % n;
+
+        if Ubig::modexp(m.clone(), self.public.e.clone(), n.clone()) != c {
+            return Err(RsaError::FaultDetected);
+        }
+
+        Ok(m)
+    }
+
+    /* Returns r, uniform in [1, n - 1] and invertible mod n, with its inverse */
+    fn blinding_factor(&self) -> (Ubig, Ubig) {
+        let n = &self.public.n;
+        let one = Ubig::from(1u64);
+
+        loop {
+            let bytes = urandom::bytes(self.public.size() + 8);
+            let r = Ubig::from(bytes.as_ref()) % (n - &one) + one.clone();
+
+            if let Some(r_inv) = Ubig::modinv(r.clone(), n.clone()) {
+                return (r, r_inv);
+            }
+        }
+    }
+
+    /* m = m2 + q * (qinv * (m1 - m2) mod p), with m1 = c^dp mod p and m2 = c^dq mod q */
+    fn crt_power(&self, c: Ubig) -> Ubig {
+        let m1 = Ubig::modexp_constant_time(
+            c.clone(),
+            self.dp.clone(),
+            self.p.clone(),
+            self.p.bit_length(),
+        );
+        let m2 =
+            Ubig::modexp_constant_time(c, self.dq.clone(), self.q.clone(), self.q.bit_length());
+
+        let difference = m1 + &self.p - &m2 % &self.p;
+        let h = &self.qinv * difference % &self.p;
+
+        m2 + h * &self.q
+    }
+}
+
+pub fn mgf1<D: Digest>(seed: &[u8], length: usize) -> Vec<u8> {
+    let mut mask = Vec::with_capacity(length + D::OUTPUT_SIZE);
+    let mut counter = 0u32;
+
+    while mask.len() < length {
+        let mut hasher = D::new();
+        hasher.update(seed);
+        hasher.update(&counter.to_be_bytes());
+
+        mask.extend_from_slice(hasher.finalize().as_ref());
+        counter += 1;
+    }
+
+    mask.truncate(length);
+
+    mask
+}
+
+fn pkcs1v15_encode<D: Pkcs1Digest>(message: &[u8], em_len: usize) -> Result<Vec<u8>, RsaError> {
+    let t_len = D::DIGEST_INFO.len() + D::OUTPUT_SIZE;
+
+    if em_len < t_len + 11 {
+        return Err(RsaError::ModulusTooShort);
+    }
+
+    let mut encoded = Vec::with_capacity(em_len);
+
+    encoded.extend_from_slice(&[0x00, 0x01]);
+    encoded.resize(em_len - t_len - 1, 0xff);
+    encoded.push(0x00);
+    encoded.extend_from_slice(D::DIGEST_INFO);
+    encoded.extend_from_slice(D::digest(message).as_ref());
+
+    Ok(encoded)
+}
+
+fn pss_encode<D: Digest>(message: &[u8], em_bits: usize, salt: &[u8]) -> Result<Vec<u8>, RsaError> {
+    let em_len = em_bits.div_ceil(8);
+    let h_len = D::OUTPUT_SIZE;
+
+    if em_len < h_len + salt.len() + 2 {
+        return Err(RsaError::ModulusTooShort);
+    }
+
+    let h = pss_hash::<D>(message, salt);
+
+    let mut db = vec![0u8; em_len - salt.len() - h_len - 2];
+    db.push(0x01);
+    db.extend_from_slice(salt);
+
+    for (byte, mask) in db.iter_mut().zip(mgf1::<D>(h.as_ref(), em_len - h_len - 1)) {
+        *byte ^= mask;
+    }
+
+    db[0] &= 0xffu8 >> (8 * em_len - em_bits);
+
+    let mut encoded = db;
+    encoded.extend_from_slice(h.as_ref());
+    encoded.push(0xbc);
+
+    Ok(encoded)
+}
+
+fn pss_verify<D: Digest>(
+    message: &[u8],
+    encoded: &[u8],
+    em_bits: usize,
+    salt_length: usize,
+) -> bool {
+    let em_len = encoded.len();
+    let h_len = D::OUTPUT_SIZE;
+
+    if em_len < h_len + salt_length + 2 || encoded[em_len - 1] != 0xbc {
+        return false;
+    }
+
+    let (masked_db, h) = encoded[..em_len - 1].split_at(em_len - h_len - 1);
+    let top_mask = 0xffu8 >> (8 * em_len - em_bits);
+
+    if masked_db[0] & !top_mask != 0 {
+        return false;
+    }
+
+    let mut db = masked_db.to_vec();
+
+    for (byte, mask) in db.iter_mut().zip(mgf1::<D>(h, em_len - h_len - 1)) {
+        *byte ^= mask;
+    }
+
+    db[0] &= top_mask;
+
+    let padding_length = em_len - h_len - salt_length - 2;
+
+    if db[..padding_length].iter().any(|byte| *byte != 0) || db[padding_length] != 0x01 {
+        return false;
+    }
+
+    let salt = &db[db.len() - salt_length..];
+
+    constant_time::eq(h, pss_hash::<D>(message, salt).as_ref())
+}
+
+fn pss_hash<D: Digest>(message: &[u8], salt: &[u8]) -> D::Output {
+    let mut hasher = D::new();
+    hasher.update(&[0u8; 8]);
+    hasher.update(D::digest(message).as_ref());
+    hasher.update(salt);
+
+    hasher.finalize()
+}
+
+fn os2ip(input: &[u8]) -> Ubig {
+    let mut bytes = input.to_vec();
+    bytes.reverse();
+
+    Ubig::from(bytes.as_ref())
+}
+
+fn i2osp(x: Ubig, length: usize) -> Result<Vec<u8>, RsaError> {
+    let mut bytes: Vec<u8> = x.into();
+
+    while let Some(0) = bytes.last() {
+        bytes.pop();
+    }
+
+    if bytes.len() > length {
+        return Err(RsaError::MessageOutOfRange);
+    }
+
+    bytes.resize(length, 0);
+    bytes.reverse();
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &[u8] = b"The quick brown fox jumps over the lazy dog";
+
+    /* A 1024-bit key and signatures produced by Python's cryptography package */
+    const P: &str = "dd3dad880aa590fbf8bcd4d44205fb025e83c8b91b9bfb51de9a648b4d1dce6e\
+                     5be87a5e407d8c7c966a84f5bf26361e245fb5cb4ea899b851078d2d135ce3e5";
+    const Q: &str = "cb19d0349bd5ef1411ec23412a930538873d5a95867306156c92a746d18b808d\
+                     0f2a871d875ea6fe7926129620ec4abcc8833c1e7721544af32e361ec1d48715";
+
+    const PKCS1V15_SHA256: &str = "3518a2718f4b8258e961b082af9f1b778a60390639a38a6833e6c9f3d262be0b\
+                                   6e1650911a2f22ae3b3f8ba5b31a5223aef909b5aba3bfe98f0c1f62a10aee09\
+                                   81d44483645c077964e45c106fd8b03a4d3bb8a52c49a976b87f222b5cf8d02b\
+                                   c8fb2879c08a7e0806752040663ad653324104e4ff0ba22fcd2de4f321c00e4c";
+    const PKCS1V15_SHA1: &str = "8aeaa0d07f7e7a1519e5191b7098bd89dd012e439377ee60057683c753c633a9\
+                                 bcf4ef7fc5d363d4aecd2f6cbb5309e4356360a25305786097bbf9856589a9d8\
+                                 1a565175b5027b42e2102439faa5269c60b73d13ad76089374927c4a53dbd4a4\
+                                 681de7ce7b949b71a215b78643c327c6abdf0d8de720a4322658c4b598b39d7d";
+    const PSS_SHA256: &str = "a4d93790e0de70db3e23d58dd3df6b11d82d4450fa5fa5a55c6c799b10aacb9a\
+                              3fa88f4b45fdbeddbd85d3c6a9484eeaed79eb00a67cf9a12d642dd544c8ca4a\
+                              dcba485ccb561ff60b551bd5946e55cc7ba32d11ecdf2b92672bb19b0206d0ee\
+                              e7048acbf40d94883fc16b5321a95e5b384767b69d185d0e6cb80dc544575b95";
+
+    fn fixed_key() -> RsaPrivateKey {
+        RsaPrivateKey::from_primes(Ubig::new(P), Ubig::new(Q), Ubig::from(65537u64)).unwrap()
+    }
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    #[test]
+    fn pkcs1v15_known_answers() {
+        let key = fixed_key();
+        let public = key.public_key();
+
+        let signature = key.sign_pkcs1v15::<Sha256>(MESSAGE).unwrap();
+        assert_eq!(signature, h(PKCS1V15_SHA256));
+        assert!(public.verify_pkcs1v15::<Sha256>(MESSAGE, &signature));
+
+        let signature = key.sign_pkcs1v15::<Sha1>(MESSAGE).unwrap();
+        assert_eq!(signature, h(PKCS1V15_SHA1));
+        assert!(public.verify_pkcs1v15::<Sha1>(MESSAGE, &signature));
+    }
+
+    #[test]
+    fn pkcs1v15_rejects_forgeries() {
+        let key = fixed_key();
+        let public = key.public_key();
+        let signature = h(PKCS1V15_SHA256);
+
+        assert!(!public.verify_pkcs1v15::<Sha256>(b"The quick brown fox", &signature));
+        assert!(!public.verify_pkcs1v15::<Sha1>(MESSAGE, &signature));
+        assert!(!public.verify_pkcs1v15::<Sha256>(MESSAGE, &signature[1..]));
+
+        let mut tampered = signature.clone();
+        tampered[64] ^= 1;
+        assert!(!public.verify_pkcs1v15::<Sha256>(MESSAGE, &tampered));
+    }
+
+    #[test]
+    fn pss_sign_and_verify() {
+        let key = fixed_key();
+        let public = key.public_key();
+
+        assert!(public.verify_pss::<Sha256>(MESSAGE, &h(PSS_SHA256), 32));
+        assert!(!public.verify_pss::<Sha256>(MESSAGE, &h(PSS_SHA256), 20));
+
+        let signature = key.sign_pss::<Sha256>(MESSAGE, 32).unwrap();
+        assert!(public.verify_pss::<Sha256>(MESSAGE, &signature, 32));
+        assert!(!public.verify_pss::<Sha256>(b"The quick brown fox", &signature, 32));
+
+        let signature = key.sign_pss::<Sha1>(MESSAGE, 20).unwrap();
+        assert!(public.verify_pss::<Sha1>(MESSAGE, &signature, 20));
+        assert!(!public.verify_pss::<Sha256>(MESSAGE, &signature, 20));
+        assert!(!public.verify_pss::<Sha1>(b"The quick brown fox", &signature, 20));
+    }
+
+    #[test]
+    fn generate_rejects_bad_parameters() {
+        for exponent in [0, 1, 2, 4, 65536] {
+            assert!(matches!(
+                RsaPrivateKey::generate(256, exponent),
+                Err(RsaError::InvalidExponent)
+            ));
+        }
+
+        assert!(matches!(
+            RsaPrivateKey::generate(15, 65537),
+            Err(RsaError::ModulusTooShort)
+        ));
+    }
+
+    #[test]
+    fn generated_keys_round_trip() {
+        for exponent in [3, 65537] {
+            let key = RsaPrivateKey::generate(256, exponent).unwrap();
+            let public = key.public_key();
+
+            assert_eq!(public.modulus().bit_length(), 256);
+
+            let message = [0x42; 16];
+            let ciphertext = public.encrypt_raw(&message).unwrap();
+            let cleartext = key.decrypt_raw(&ciphertext).unwrap();
+
+            assert_eq!(cleartext[cleartext.len() - 16..], message);
+            assert!(cleartext[..cleartext.len() - 16]
+                .iter()
+                .all(|byte| *byte == 0));
+        }
+    }
+}
@@ -0,0 +1,33 @@
+/* The Salsa20 core on 16 little-endian words; scrypt uses 8 rounds, the stream cipher 20 */
+pub fn salsa20_core(input: &[u32; 16], rounds: usize) -> [u32; 16] {
+    assert!(rounds.is_multiple_of(2), "rounds come in column/row pairs");
+
+    let mut x = *input;
+
+    for _ in 0..rounds / 2 {
+        /* Columns */
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 5, 9, 13, 1);
+        quarter_round(&mut x, 10, 14, 2, 6);
+        quarter_round(&mut x, 15, 3, 7, 11);
+
+        /* Rows */
+        quarter_round(&mut x, 0, 1, 2, 3);
+        quarter_round(&mut x, 5, 6, 7, 4);
+        quarter_round(&mut x, 10, 11, 8, 9);
+        quarter_round(&mut x, 15, 12, 13, 14);
+    }
+
+    for (word, original) in x.iter_mut().zip(input) {
+        *word = word.wrapping_add(*original);
+    }
+
+    x
+}
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+    x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+    x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+    x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+}
@@ -1,3 +1,5 @@
+use super::digest::{BlockBuffer, Digest};
+
 use std::slice::ChunksExact;
 
 const SHA256K: [u32; 64] = [
@@ -11,70 +13,519 @@ const SHA256K: [u32; 64] = [
     0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
 ];
 
-pub fn sha256_digest(input: &[u8]) -> [u8; 32] {
-    do_sha256_digest(input, None, 0)
-}
+const SHA512K: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+const SHA224_H: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+const SHA256_H: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SHA384_H: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+const SHA512_H: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SHA512_224_H: [u64; 8] = [
+    0x8c3d37c819544da2,
+    0x73e1996689dcd4d6,
+    0x1dfab7ae32ff9c82,
+    0x679dd514582f9fcf,
+    0x0f6d2b697bd44da8,
+    0x77e36f7304c48942,
+    0x3f9d85a86a1d36c8,
+    0x1112e6ad91d692a1,
+];
+
+const SHA512_256_H: [u64; 8] = [
+    0x22312194fc2bf72c,
+    0x9f555fa3c84c64c2,
+    0x2393b86b6f53b151,
+    0x963877195940eabd,
+    0x96283ee2a88effe3,
+    0xbe5e1e2553863992,
+    0x2b0199fc2c85b8aa,
+    0x0eb72ddc81c52ca2,
+];
+
+const SHA1_H: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
 
 pub fn sha1_digest(input: &[u8]) -> [u8; 20] {
-    do_sha1_digest(input, None, 0)
+    Sha1::digest(input)
 }
 
 pub fn sha1_digest_from_state(input: &[u8], state: &[u8; 20], head_bytes: usize) -> [u8; 20] {
-    do_sha1_digest(input, Some(state), head_bytes)
+    let mut hasher = Sha1::from_state(state, head_bytes);
+    hasher.update(input);
+
+    hasher.finalize()
+}
+
+pub fn sha224_digest(input: &[u8]) -> [u8; 28] {
+    Sha224::digest(input)
+}
+
+pub fn sha256_digest(input: &[u8]) -> [u8; 32] {
+    Sha256::digest(input)
+}
+
+pub fn sha256_digest_from_state(input: &[u8], state: &[u8; 32], head_bytes: usize) -> [u8; 32] {
+    let mut hasher = Sha256::from_state(state, head_bytes);
+    hasher.update(input);
+
+    hasher.finalize()
+}
+
+pub fn sha384_digest(input: &[u8]) -> [u8; 48] {
+    Sha384::digest(input)
+}
+
+pub fn sha512_digest(input: &[u8]) -> [u8; 64] {
+    Sha512::digest(input)
+}
+
+pub fn sha512_224_digest(input: &[u8]) -> [u8; 28] {
+    let mut digest = [0; 28];
+    digest.copy_from_slice(&do_sha512_digest(input, SHA512_224_H)[..28]);
+
+    digest
+}
+
+pub fn sha512_256_digest(input: &[u8]) -> [u8; 32] {
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&do_sha512_digest(input, SHA512_256_H)[..32]);
+
+    digest
+}
+
+/* None unless t is byte-aligned, below 512 and not 384 */
+pub fn sha512_t_digest(input: &[u8], t: usize) -> Option<Vec<u8>> {
+    if t == 0 || t >= 512 || t == 384 || !t.is_multiple_of(8) {
+        return None;
+    }
+
+    Some(do_sha512_digest(input, sha512_t_iv(t))[..t / 8].to_vec())
+}
+
+#[derive(Clone)]
+pub struct Sha1 {
+    h: [u32; 5],
+    buffer: BlockBuffer<64>,
+    length: usize,
 }
 
-fn do_sha1_digest(input: &[u8], state: Option<&[u8; 20]>, extra_byte_length: usize) -> [u8; 20] {
-    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+impl Sha1 {
+    pub fn from_state(state: &[u8; 20], processed_len: usize) -> Self {
+        let mut h = [0; 5];
 
-    if let Some(state) = state {
         for (slot, bytes) in h.iter_mut().zip(state.chunks_exact(4)) {
-            let mut word = [0; 4];
-            word.copy_from_slice(bytes);
+            *slot = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
 
-            *slot = u32::from_be_bytes(word);
+        Self {
+            h,
+            buffer: BlockBuffer::new(),
+            length: processed_len,
         }
     }
+}
+
+impl Digest for Sha1 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 20;
+
+    type Output = [u8; 20];
 
-    for block in Sha1Blocks::new(input, extra_byte_length) {
-        let w = sha1_schedule(&block);
-
-        let mut a = h[0];
-        let mut b = h[1];
-        let mut c = h[2];
-        let mut d = h[3];
-        let mut e = h[4];
-        let mut t;
-
-        for i in 0..80 {
-            t = a
-                .rotate_left(5)
-                .wrapping_add(sha1_ft(i, b, c, d))
-                .wrapping_add(e)
-                .wrapping_add(sha1_kt(i))
-                .wrapping_add(w[i]);
-            e = d;
-            d = c;
-            c = b.rotate_left(30);
-            b = a;
-            a = t;
+    fn new() -> Self {
+        Self {
+            h: SHA1_H,
+            buffer: BlockBuffer::new(),
+            length: 0,
         }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        let h = &mut self.h;
+
+        self.buffer.update(input, |bytes| {
+            let mut block = [0; 16];
+            place_bytes_in_block(&mut block, bytes);
+
+            sha1_compress(h, &block);
+        });
 
-        h[0] = h[0].wrapping_add(a);
-        h[1] = h[1].wrapping_add(b);
-        h[2] = h[2].wrapping_add(c);
-        h[3] = h[3].wrapping_add(d);
-        h[4] = h[4].wrapping_add(e);
+        self.length += input.len();
     }
 
-    let mut digest = [0; 20];
+    fn finalize(mut self) -> Self::Output {
+        let tail = self.buffer.remainder();
 
-    (&mut digest[0..4]).copy_from_slice(&h[0].to_be_bytes());
-    (&mut digest[4..8]).copy_from_slice(&h[1].to_be_bytes());
-    (&mut digest[8..12]).copy_from_slice(&h[2].to_be_bytes());
-    (&mut digest[12..16]).copy_from_slice(&h[3].to_be_bytes());
-    (&mut digest[16..20]).copy_from_slice(&h[4].to_be_bytes());
+        for block in Sha1Blocks::new(tail, self.length - tail.len()) {
+            sha1_compress(&mut self.h, &block);
+        }
 
-    digest
+        let mut digest = [0; 20];
+
+        for (bytes, word) in digest.chunks_exact_mut(4).zip(self.h.iter()) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        digest
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha224(Sha256Core);
+
+impl Digest for Sha224 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 28;
+
+    type Output = [u8; 28];
+
+    fn new() -> Self {
+        Self(Sha256Core::new(SHA224_H, 0))
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input);
+    }
+
+    fn finalize(self) -> Self::Output {
+        let mut digest = [0; 28];
+        digest.copy_from_slice(&self.0.finalize()[..28]);
+
+        digest
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha256(Sha256Core);
+
+impl Sha256 {
+    pub fn from_state(state: &[u8; 32], processed_len: usize) -> Self {
+        let mut hbig = [0; 8];
+
+        for (slot, bytes) in hbig.iter_mut().zip(state.chunks_exact(4)) {
+            *slot = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        Self(Sha256Core::new(hbig, processed_len))
+    }
+}
+
+impl Digest for Sha256 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 32;
+
+    type Output = [u8; 32];
+
+    fn new() -> Self {
+        Self(Sha256Core::new(SHA256_H, 0))
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input);
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.0.finalize()
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha384(Sha512Core);
+
+impl Digest for Sha384 {
+    const BLOCK_SIZE: usize = 128;
+    const OUTPUT_SIZE: usize = 48;
+
+    type Output = [u8; 48];
+
+    fn new() -> Self {
+        Self(Sha512Core::new(SHA384_H))
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input);
+    }
+
+    fn finalize(self) -> Self::Output {
+        let mut digest = [0; 48];
+        digest.copy_from_slice(&self.0.finalize()[..48]);
+
+        digest
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha512(Sha512Core);
+
+impl Digest for Sha512 {
+    const BLOCK_SIZE: usize = 128;
+    const OUTPUT_SIZE: usize = 64;
+
+    type Output = [u8; 64];
+
+    fn new() -> Self {
+        Self(Sha512Core::new(SHA512_H))
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input);
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.0.finalize()
+    }
+}
+
+#[derive(Clone)]
+struct Sha256Core {
+    hbig: [u32; 8],
+    buffer: BlockBuffer<64>,
+    length: usize,
+}
+
+impl Sha256Core {
+    fn new(hbig: [u32; 8], length: usize) -> Self {
+        Self {
+            hbig,
+            buffer: BlockBuffer::new(),
+            length,
+        }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        let hbig = &mut self.hbig;
+
+        self.buffer.update(input, |bytes| {
+            let mut block = [0; 64];
+            place_bytes_in_block(&mut block, bytes);
+
+            sha256_compress(hbig, block);
+        });
+
+        self.length += input.len();
+    }
+
+    fn finalize(mut self) -> [u8; 32] {
+        let tail = self.buffer.remainder();
+
+        for block in Sha256Blocks::new(tail, self.length - tail.len()) {
+            sha256_compress(&mut self.hbig, block);
+        }
+
+        let mut digest = [0; 32];
+
+        for (bytes, word) in digest.chunks_exact_mut(4).zip(self.hbig.iter()) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        digest
+    }
+}
+
+#[derive(Clone)]
+struct Sha512Core {
+    hbig: [u64; 8],
+    buffer: BlockBuffer<128>,
+    length: u128,
+}
+
+impl Sha512Core {
+    fn new(hbig: [u64; 8]) -> Self {
+        Self {
+            hbig,
+            buffer: BlockBuffer::new(),
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        let hbig = &mut self.hbig;
+
+        self.buffer.update(input, |bytes| {
+            let mut block = [0; 80];
+            place_bytes_in_wide_block(&mut block, bytes);
+
+            sha512_compress(hbig, block);
+        });
+
+        self.length += input.len() as u128;
+    }
+
+    fn finalize_words(mut self) -> [u64; 8] {
+        let tail = self.buffer.remainder();
+
+        for block in Sha512Blocks::new(tail, self.length - tail.len() as u128) {
+            sha512_compress(&mut self.hbig, block);
+        }
+
+        self.hbig
+    }
+
+    fn finalize(self) -> [u8; 64] {
+        let hbig = self.finalize_words();
+        let mut digest = [0; 64];
+
+        for (bytes, word) in digest.chunks_exact_mut(8).zip(hbig.iter()) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        digest
+    }
+}
+
+fn do_sha512_digest(input: &[u8], hbig: [u64; 8]) -> [u8; 64] {
+    let mut core = Sha512Core::new(hbig);
+    core.update(input);
+
+    core.finalize()
+}
+
+fn sha512_t_iv(t: usize) -> [u64; 8] {
+    let mut hbig = SHA512_H;
+
+    for word in hbig.iter_mut() {
+        *word ^= 0xa5a5_a5a5_a5a5_a5a5;
+    }
+
+    let mut core = Sha512Core::new(hbig);
+    core.update(format!("SHA-512/{}", t).as_bytes());
+
+    core.finalize_words()
+}
+
+fn sha1_compress(h: &mut [u32; 5], block: &[u32; 16]) {
+    let w = sha1_schedule(block);
+
+    let mut a = h[0];
+    let mut b = h[1];
+    let mut c = h[2];
+    let mut d = h[3];
+    let mut e = h[4];
+    let mut t;
+
+    for i in 0..80 {
+        t = a
+            .rotate_left(5)
+            .wrapping_add(sha1_ft(i, b, c, d))
+            .wrapping_add(e)
+            .wrapping_add(sha1_kt(i))
+            .wrapping_add(w[i]);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
+    }
+
+    h[0] = h[0].wrapping_add(a);
+    h[1] = h[1].wrapping_add(b);
+    h[2] = h[2].wrapping_add(c);
+    h[3] = h[3].wrapping_add(d);
+    h[4] = h[4].wrapping_add(e);
 }
 
 fn sha1_schedule(m: &[u32; 16]) -> [u32; 80] {
@@ -175,75 +626,46 @@ impl<'a> Iterator for Sha1Blocks<'a> {
     }
 }
 
-fn do_sha256_digest(input: &[u8], state: Option<&[u8; 32]>, extra_byte_length: usize) -> [u8; 32] {
-    let mut hbig: [u32; 8] = [
-        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
-        0x5be0cd19,
-    ];
-
-    if let Some(state) = state {
-        for (slot, bytes) in hbig.iter_mut().zip(state.chunks_exact(4)) {
-            let mut word = [0; 4];
-            word.copy_from_slice(bytes);
-
-            *slot = u32::from_be_bytes(word);
-        }
+fn sha256_compress(hbig: &mut [u32; 8], mut block: [u32; 64]) {
+    sha256_schedule(&mut block);
+
+    let mut a = hbig[0];
+    let mut b = hbig[1];
+    let mut c = hbig[2];
+    let mut d = hbig[3];
+    let mut e = hbig[4];
+    let mut f = hbig[5];
+    let mut g = hbig[6];
+    let mut h = hbig[7];
+
+    for i in 0..64 {
+        let t1 = h
+            .wrapping_add(e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25))
+            .wrapping_add((e & f) ^ ((!e) & g))
+            .wrapping_add(SHA256K[i])
+            .wrapping_add(block[i]);
+
+        let t2 = (a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22))
+            .wrapping_add((a & b) ^ (a & c) ^ (b & c));
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
     }
 
-    for mut block in Sha256Blocks::new(input, extra_byte_length) {
-        sha256_schedule(&mut block);
-
-        let mut a = hbig[0];
-        let mut b = hbig[1];
-        let mut c = hbig[2];
-        let mut d = hbig[3];
-        let mut e = hbig[4];
-        let mut f = hbig[5];
-        let mut g = hbig[6];
-        let mut h = hbig[7];
-
-        for i in 0..64 {
-            let t1 = h
-                .wrapping_add(e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25))
-                .wrapping_add((e & f) ^ ((!e) & g))
-                .wrapping_add(SHA256K[i])
-                .wrapping_add(block[i]);
-
-            let t2 = (a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22))
-                .wrapping_add((a & b) ^ (a & c) ^ (b & c));
-
-            h = g;
-            g = f;
-            f = e;
-            e = d.wrapping_add(t1);
-            d = c;
-            c = b;
-            b = a;
-            a = t1.wrapping_add(t2);
-        }
-
-        hbig[0] = hbig[0].wrapping_add(a);
-        hbig[1] = hbig[1].wrapping_add(b);
-        hbig[2] = hbig[2].wrapping_add(c);
-        hbig[3] = hbig[3].wrapping_add(d);
-        hbig[4] = hbig[4].wrapping_add(e);
-        hbig[5] = hbig[5].wrapping_add(f);
-        hbig[6] = hbig[6].wrapping_add(g);
-        hbig[7] = hbig[7].wrapping_add(h);
-    }
-
-    let mut digest = [0; 32];
-
-    (&mut digest[0..4]).copy_from_slice(&hbig[0].to_be_bytes());
-    (&mut digest[4..8]).copy_from_slice(&hbig[1].to_be_bytes());
-    (&mut digest[8..12]).copy_from_slice(&hbig[2].to_be_bytes());
-    (&mut digest[12..16]).copy_from_slice(&hbig[3].to_be_bytes());
-    (&mut digest[16..20]).copy_from_slice(&hbig[4].to_be_bytes());
-    (&mut digest[20..24]).copy_from_slice(&hbig[5].to_be_bytes());
-    (&mut digest[24..28]).copy_from_slice(&hbig[6].to_be_bytes());
-    (&mut digest[28..32]).copy_from_slice(&hbig[7].to_be_bytes());
-
-    digest
+    hbig[0] = hbig[0].wrapping_add(a);
+    hbig[1] = hbig[1].wrapping_add(b);
+    hbig[2] = hbig[2].wrapping_add(c);
+    hbig[3] = hbig[3].wrapping_add(d);
+    hbig[4] = hbig[4].wrapping_add(e);
+    hbig[5] = hbig[5].wrapping_add(f);
+    hbig[6] = hbig[6].wrapping_add(g);
+    hbig[7] = hbig[7].wrapping_add(h);
 }
 
 fn sha256_schedule(block: &mut [u32; 64]) {
@@ -268,7 +690,7 @@ struct Sha256Blocks<'a> {
 impl<'a> Sha256Blocks<'a> {
     fn new(input: &'a [u8], extra_byte_length: usize) -> Self {
         Self {
-            chunks: input.chunks_exact(256),
+            chunks: input.chunks_exact(64),
             tail_block: None,
             bit_length: (input.len() + extra_byte_length) as u64 * 8,
             done: false,
@@ -322,6 +744,123 @@ impl<'a> Iterator for Sha256Blocks<'a> {
     }
 }
 
+fn sha512_compress(hbig: &mut [u64; 8], mut block: [u64; 80]) {
+    sha512_schedule(&mut block);
+
+    let mut a = hbig[0];
+    let mut b = hbig[1];
+    let mut c = hbig[2];
+    let mut d = hbig[3];
+    let mut e = hbig[4];
+    let mut f = hbig[5];
+    let mut g = hbig[6];
+    let mut h = hbig[7];
+
+    for i in 0..80 {
+        let t1 = h
+            .wrapping_add(e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41))
+            .wrapping_add((e & f) ^ ((!e) & g))
+            .wrapping_add(SHA512K[i])
+            .wrapping_add(block[i]);
+
+        let t2 = (a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39))
+            .wrapping_add((a & b) ^ (a & c) ^ (b & c));
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    hbig[0] = hbig[0].wrapping_add(a);
+    hbig[1] = hbig[1].wrapping_add(b);
+    hbig[2] = hbig[2].wrapping_add(c);
+    hbig[3] = hbig[3].wrapping_add(d);
+    hbig[4] = hbig[4].wrapping_add(e);
+    hbig[5] = hbig[5].wrapping_add(f);
+    hbig[6] = hbig[6].wrapping_add(g);
+    hbig[7] = hbig[7].wrapping_add(h);
+}
+
+fn sha512_schedule(block: &mut [u64; 80]) {
+    for i in 16..80 {
+        let s1 = block[i - 2];
+        let s0 = block[i - 15];
+
+        block[i] = (s1.rotate_right(19) ^ s1.rotate_right(61) ^ s1.wrapping_shr(6))
+            .wrapping_add(block[i - 7])
+            .wrapping_add(s0.rotate_right(1) ^ s0.rotate_right(8) ^ s0.wrapping_shr(7))
+            .wrapping_add(block[i - 16]);
+    }
+}
+
+struct Sha512Blocks<'a> {
+    chunks: ChunksExact<'a, u8>,
+    tail_block: Option<[u64; 80]>,
+    bit_length: u128,
+    done: bool,
+}
+
+impl<'a> Sha512Blocks<'a> {
+    fn new(input: &'a [u8], extra_byte_length: u128) -> Self {
+        Self {
+            chunks: input.chunks_exact(128),
+            tail_block: None,
+            bit_length: (input.len() as u128 + extra_byte_length) * 8,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Sha512Blocks<'_> {
+    type Item = [u64; 80];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            let tail_block = self.tail_block;
+            self.tail_block = None;
+
+            return tail_block;
+        }
+
+        let mut block: [u64; 80] = [0; 80];
+
+        if let Some(bytes) = self.chunks.next() {
+            place_bytes_in_wide_block(&mut block, bytes);
+
+            return Some(block);
+        }
+
+        let tail = self.chunks.remainder();
+        let tail_bytes = tail.len();
+
+        place_bytes_in_wide_block(&mut block, tail);
+
+        let length_upper = (self.bit_length >> 64) as u64;
+        let length_lower = self.bit_length as u64;
+
+        if tail_bytes > 111 {
+            let mut tail_block = [0; 80];
+
+            tail_block[14] = length_upper;
+            tail_block[15] = length_lower;
+
+            self.tail_block = Some(tail_block);
+        } else {
+            block[14] = length_upper;
+            block[15] = length_lower;
+        }
+
+        self.done = true;
+
+        Some(block)
+    }
+}
+
 fn place_bytes_in_block(block: &mut [u32], bytes: &[u8]) {
     let mut bytes = bytes
         .iter()
@@ -340,3 +879,281 @@ fn place_bytes_in_block(block: &mut [u32], bytes: &[u8]) {
         *slot = u32::from_be_bytes(word_bytes);
     }
 }
+
+fn place_bytes_in_wide_block(block: &mut [u64], bytes: &[u8]) {
+    let mut bytes = bytes
+        .iter()
+        .copied()
+        .chain([0x80])
+        .chain([0].into_iter().cycle());
+
+    for slot in block.iter_mut() {
+        let mut word_bytes: [u8; 8] = [0; 8];
+
+        for byte in word_bytes.iter_mut() {
+            *byte = bytes.next().unwrap();
+        }
+
+        *slot = u64::from_be_bytes(word_bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+     * "", "abc", the 448- and 896-bit FIPS 180-4 messages, then runs of 'a'
+     * around the padding boundaries of both block sizes
+     */
+    const LENGTHS: [usize; 8] = [55, 56, 63, 64, 111, 112, 127, 128];
+
+    fn messages() -> Vec<Vec<u8>> {
+        let mut messages = vec![
+            b"".to_vec(),
+            b"abc".to_vec(),
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+            b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+              hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"
+                .to_vec(),
+        ];
+
+        messages.extend(LENGTHS.iter().map(|length| vec![b'a'; *length]));
+
+        messages
+    }
+
+    fn check<const N: usize>(digest: fn(&[u8]) -> [u8; N], expected: [&str; 12]) {
+        for (message, expected) in messages().iter().zip(expected) {
+            assert_eq!(
+                hex::encode(digest(message)),
+                expected,
+                "{} bytes",
+                message.len()
+            );
+        }
+    }
+
+    #[test]
+    fn sha224_digest() {
+        check(
+            super::sha224_digest,
+            [
+                "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f",
+                "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+                "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525",
+                "c97ca9a559850ce97a04a96def6d99a9e0e0e2ab14e6b8df265fc0b3",
+                "fb0bd626a70c28541dfa781bb5cc4d7d7f56622a58f01a0b1ddd646f",
+                "d40854fc9caf172067136f2e29e1380b14626bf6f0dd06779f820dcd",
+                "1d4e051f4d6fed2a63fd2421e65834cec00d64456553de3496ae8b1d",
+                "a88cd5cde6d6fe9136a4e58b49167461ea95d388ca2bdb7afdc3cbf4",
+                "4aeec1a49b2c1bc663abf2809b36faaa64359523d4f26d02dbc2cba3",
+                "0336b66821946e7f1052102e3b9c29f3039efe9b261746370305f894",
+                "0822db3f33424aead078f71ed05f30edc077a3c254b7c79c89a7a4a1",
+                "39873a2441c56608137850f4c54dde157710b9a2b83c8bdc756dd643",
+            ],
+        );
+    }
+
+    #[test]
+    fn sha256_digest() {
+        check(
+            super::sha256_digest,
+            [
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+                "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1",
+                "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+                "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+                "7d3e74a05d7db15bce4ad9ec0658ea98e3f06eeecf16b4c6fff2da457ddc2f34",
+                "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+                "6374f73208854473827f6f6a3f43b1f53eaa3b82c21c1a6d69a2110b2a79baad",
+                "f54353008a2553262ecdc4a34749563ba0950e8b0fc8652780b0a614b99683c1",
+                "c57e9278af78fa3cab38667bef4ce29d783787a2f731d4e12200270f0c32320a",
+                "6836cf13bac400e9105071cd6af47084dfacad4e5e302c94bfed24e013afb73e",
+            ],
+        );
+    }
+
+    #[test]
+    fn sha384_digest() {
+        check(
+            super::sha384_digest,
+            [
+                "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+                "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+                "3391fdddfc8dc7393707a65b1b4709397cf8b1d162af05abfe8f450de5f36bc6b0455a8520bc4e6f5fe95b1fe3c8452b",
+                "09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039",
+                "5d91ac7e74e62b5c728904b40f10784d66b7af9cb6302123e48c92f0432ceb8d2a92c02de77dcb29ed75c4b42bde46f4",
+                "8a8d9649ea04e993a6ca7135af7e3392cc5fca84f8531cac7aa3feed4eb98f55dcbe0f3284b61c6f35f98b02cc644b4c",
+                "7e7f097e95b52bb8f53383450ecaf9868187c130981730c03b6d573adfc0b991e365244e5b4bfa082bfb43c517d37120",
+                "2e404b9339da795776e510d96930b3be2904c500395b8cb7413334b82d4dec413b4b8113045a05bbbcff846f027423f6",
+                "3c37955051cb5c3026f94d551d5b5e2ac38d572ae4e07172085fed81f8466b8f90dc23a8ffcdea0b8d8e58e8fdacc80a",
+                "187d4e07cb306103c69967bf544d0dfbe9042577599c73c330abc0cb64c61236d5ed565ee19119d8c31779a38f791fcd",
+                "9bd06b1763c2cf7aef40e795dc65bc96d59c41b537f3ad72ebdefd485476b5717c1aeb37c327fe9c1831b12b9efd08ae",
+                "edb12730a366098b3b2beac75a3bef1b0969b15c48e2163c23d96994f8d1bef760c7e27f3c464d3829f56c0d53808b0b",
+            ],
+        );
+    }
+
+    #[test]
+    fn sha512_digest() {
+        check(
+            super::sha512_digest,
+            [
+                "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+                "204a8fc6dda82f0a0ced7beb8e08a41657c16ef468b228a8279be331a703c33596fd15c13b1b07f9aa1d3bea57789ca031ad85c7a71dd70354ec631238ca3445",
+                "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909",
+                "b0220c772cbf6c1822e2cb38a437d0e1d58772417a4bbb21c961364f8b6143e05aa6316dca8d1d7b19e16448419076395f6086cb55101fbd6d5497b148e1745f",
+                "962b64aae357d2a4fee3ded8b539bdc9d325081822b0bfc55583133aab44f18bafe11d72a7ae16c79ce2ba620ae2242d5144809161945f1367f41b3972e26e04",
+                "c1b0f5c6d3b03dfe4a2602e67242f54e344090b66e01100a469b129f583f016c7e27dddeaa438393dcc7ec54b0b57c9ba7af007f9b56db5f6fb677d972a31362",
+                "01d35c10c6c38c2dcf48f7eebb3235fb5ad74a65ec4cd016e2354c637a8fb49b695ef3c1d6f7ae4cd74d78cc9c9bcac9d4f23a73019998a7f73038a5c9b2dbde",
+                "fa9121c7b32b9e01733d034cfc78cbf67f926c7ed83e82200ef86818196921760b4beff48404df811b953828274461673c68d04e297b0eb7b2b4d60fc6b566a2",
+                "c01d080efd492776a1c43bd23dd99d0a2e626d481e16782e75d54c2503b5dc32bd05f0f1ba33e568b88fd2d970929b719ecbb152f58f130a407c8830604b70ca",
+                "828613968b501dc00a97e08c73b118aa8876c26b8aac93df128502ab360f91bab50a51e088769a5c1eff4782ace147dce3642554199876374291f5d921629502",
+                "b73d1929aa615934e61a871596b3f3b33359f42b8175602e89f7e06e5f658a243667807ed300314b95cacdd579f3e33abdfbe351909519a846d465c59582f321",
+            ],
+        );
+    }
+
+    #[test]
+    fn sha512_224_digest() {
+        check(
+            super::sha512_224_digest,
+            [
+                "6ed0dd02806fa89e25de060c19d3ac86cabb87d6a0ddd05c333b84f4",
+                "4634270f707b6a54daae7530460842e20e37ed265ceee9a43e8924aa",
+                "e5302d6d54bb242275d1e7622d68df6eb02dedd13f564c13dbda2174",
+                "23fec5bb94d60b23308192640b0c453335d664734fe40e7268674af9",
+                "70a40c59a45a82e85038580887f60fb8bb3d3a0562dceac4a909de2e",
+                "b0f36f90a5c465e3376e275ce596b72b0b9dedc382f180215a8136e1",
+                "25be9fbfe4a7c66c7fd9b66d8aeaf01a73f5112945e988b371f14f56",
+                "02682009a96ba45d69a5d10c95e108f0f9c0fb095e14a8a0abd62856",
+                "3ebe1b48e8c66acb9ae014db95b4bec93de7e9572bff41cf566bd7d0",
+                "79b41fef2a0439d2705724a67615f7bcbcd2bf5664a7774b80818eb6",
+                "65aec5ddd181bb86e1921d493a0667492cb8dbc2b560ec061ed2c492",
+                "261b94bcba554264b3b738e9e09e7dc68ac8e0b4c8517fe9bb7c3617",
+            ],
+        );
+    }
+
+    #[test]
+    fn sha512_256_digest() {
+        check(
+            super::sha512_256_digest,
+            [
+                "c672b8d1ef56ed28ab87c3622c5114069bdd3ad7b8f9737498d0c01ecef0967a",
+                "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23",
+                "bde8e1f9f19bb9fd3406c90ec6bc47bd36d8ada9f11880dbc8a22a7078b6a461",
+                "3928e184fb8690f840da3988121d31be65cb9d3ef83ee6146feac861e19b563a",
+                "f6513468f05e7cec7d52fc337ef79dfa7c82520268d3aeba4002ead9a5642916",
+                "baa8bd7fb02a11878c6a1d5400f06ec5d96cd6f566da032f8dcbb602beea4ca5",
+                "3c4458337119e69459650d3851234acd0300b39640803807b139ffc1da44c8ba",
+                "2c3fa8f196f2aac65f15166666ecc77bd9fe195bae83ef06bb75c7857c163db9",
+                "0239e429f98d0ed61ee8e2a7c30afe98c1c3a80ce5dff62a107e9c538f7632ce",
+                "9216b5303edb66504570bee90e48ea5beaa5e9fe9f760bbd3e0460559fc005f6",
+                "2fe3b2a6ee7e12f6fe4ba82166541ad9b4ed882c493581cbe300d68f3757b778",
+                "b88f97e274f9c1d49f181c8cbd01a9c74930ad055a46ac4499a1d601f1c80bf2",
+            ],
+        );
+    }
+
+    #[test]
+    fn sha512_t() {
+        for message in messages() {
+            assert_eq!(
+                sha512_t_digest(&message, 224).unwrap(),
+                super::sha512_224_digest(&message)
+            );
+            assert_eq!(
+                sha512_t_digest(&message, 256).unwrap(),
+                super::sha512_256_digest(&message)
+            );
+        }
+
+        for t in [0, 4, 100, 384, 512, 520] {
+            assert!(sha512_t_digest(b"abc", t).is_none());
+        }
+    }
+
+    fn check_incremental<D: Digest>(one_shot: fn(&[u8]) -> D::Output)
+    where
+        D::Output: PartialEq + std::fmt::Debug,
+    {
+        for message in messages() {
+            for piece in [1, 17, 64, 65, 128, 129] {
+                let mut hasher = D::new();
+
+                for chunk in message.chunks(piece) {
+                    hasher.update(chunk);
+                }
+
+                assert_eq!(hasher.finalize(), one_shot(&message));
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_updates_match() {
+        check_incremental::<Sha1>(super::sha1_digest);
+        check_incremental::<Sha224>(super::sha224_digest);
+        check_incremental::<Sha256>(super::sha256_digest);
+        check_incremental::<Sha384>(super::sha384_digest);
+        check_incremental::<Sha512>(super::sha512_digest);
+    }
+
+    /* Merkle-Damgard padding for a message of `length` bytes, with a 64-bit big-endian length */
+    fn glue_padding(length: usize) -> Vec<u8> {
+        let mut padding = vec![0x80];
+
+        padding.resize((119 - length % 64) % 64 + 1, 0);
+        padding.extend_from_slice(&(8 * length as u64).to_be_bytes());
+
+        padding
+    }
+
+    #[test]
+    fn from_state_extends_a_digest() {
+        let secret = b"YELLOW SUBMARINE, keyed prefix";
+        let suffix = b";admin=true";
+
+        for length in [0, 1, 55, 56, 64, 100] {
+            let message = [&secret[..], &vec![b'm'; length]].concat();
+            let mut forged = [message.clone(), glue_padding(message.len())].concat();
+            let processed = forged.len();
+            forged.extend_from_slice(suffix);
+
+            let state = super::sha1_digest(&message);
+            let mut hasher = Sha1::from_state(&state, processed);
+            hasher.update(suffix);
+
+            assert_eq!(hasher.finalize(), super::sha1_digest(&forged));
+            assert_eq!(
+                super::sha1_digest_from_state(suffix, &state, processed),
+                super::sha1_digest(&forged)
+            );
+
+            let state = super::sha256_digest(&message);
+            let mut hasher = Sha256::from_state(&state, processed);
+            hasher.update(suffix);
+
+            assert_eq!(hasher.finalize(), super::sha256_digest(&forged));
+            assert_eq!(
+                super::sha256_digest_from_state(suffix, &state, processed),
+                super::sha256_digest(&forged)
+            );
+        }
+    }
+
+    #[test]
+    fn from_initial_state_matches_new() {
+        let sha1_iv: Vec<u8> = SHA1_H.iter().flat_map(|word| word.to_be_bytes()).collect();
+
+        assert_eq!(
+            super::sha1_digest_from_state(b"abc", &sha1_iv.try_into().unwrap(), 0),
+            super::sha1_digest(b"abc")
+        );
+    }
+}
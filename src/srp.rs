@@ -0,0 +1,567 @@
+use super::constant_time;
+use super::dh::{DhGroup, DhKeyPair};
+use super::digest::Digest;
+use super::hmac;
+use super::sha::Sha256;
+use super::ubig::Ubig;
+use super::urandom;
+
+#[derive(Debug)]
+pub enum SrpError {
+    PublicOutOfRange,
+    ScramblerIsZero,
+    ProofMismatch,
+}
+
+/* What the server stores per user: I, s and v = g^x with x = H(s | H(I | ":" | P)) */
+#[derive(Clone)]
+pub struct SrpVerifier {
+    identity: String,
+    salt: Vec<u8>,
+    verifier: Ubig,
+}
+
+impl SrpVerifier {
+    pub fn new(group: &DhGroup, identity: &str, password: &str) -> Self {
+        Self::with_salt(group, identity, password, &urandom::bytes(16))
+    }
+
+    pub fn with_salt(group: &DhGroup, identity: &str, password: &str, salt: &[u8]) -> Self {
+        let x = private_value(identity, password, salt);
+        let verifier = Ubig::modexp_constant_time(
+            group.g().clone(),
+            x,
+            group.p().clone(),
+            8 * Sha256::OUTPUT_SIZE,
+        );
+
+        Self {
+            identity: identity.to_string(),
+            salt: salt.to_vec(),
+            verifier,
+        }
+    }
+
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    pub fn verifier(&self) -> &Ubig {
+        &self.verifier
+    }
+}
+
+/* Client before it has seen the server's s and B */
+pub struct SrpClient {
+    group: DhGroup,
+    identity: String,
+    password: String,
+    ephemeral: DhKeyPair,
+}
+
+impl SrpClient {
+    pub fn new(group: &DhGroup, identity: &str, password: &str) -> Self {
+        Self {
+            group: group.clone(),
+            identity: identity.to_string(),
+            password: password.to_string(),
+            ephemeral: DhKeyPair::generate(group),
+        }
+    }
+
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    /* A = g^a */
+    pub fn public_key(&self) -> &Ubig {
+        self.ephemeral.public_key()
+    }
+
+    /* S = (B - k g^x)^(a + u x), then M1 = H(H(N) ^ H(g) | H(I) | s | A | B | K) */
+    pub fn respond(self, salt: &[u8], server_public: &Ubig) -> Result<SrpClientSession, SrpError> {
+        let p = self.group.p();
+
+        /* B must lie in [1, N - 1] */
+        if !server_public.not_zero() || server_public >= p {
+            return Err(SrpError::PublicOutOfRange);
+        }
+
+        let client_public = self.ephemeral.public_key();
+        let u = scrambler(&self.group, client_public, server_public);
+
+        if !u.not_zero() {
+            return Err(SrpError::ScramblerIsZero);
+        }
+
+        let x = private_value(&self.identity, &self.password, salt);
+        let k = multiplier(&self.group);
+
+        let g_x = secret_power(&self.group, self.group.g(), &x);
+        let base = (server_public + p - &k * &g_x % p) % p;
+        let exponent = self.ephemeral.private_key() + &(u * x);
+
+        let key = hash(&[&pad(
+            &self.group,
+            &secret_power(&self.group, &base, &exponent),
+        )]);
+        let proof = client_proof(
+            &self.group,
+            &self.identity,
+            salt,
+            client_public,
+            server_public,
+            &key,
+        );
+
+        let expected_server_proof = hash(&[&pad(&self.group, client_public), &proof, &key]);
+
+        Ok(SrpClientSession {
+            key,
+            proof,
+            expected_server_proof,
+        })
+    }
+}
+
+pub struct SrpClientSession {
+    key: [u8; 32],
+    proof: [u8; 32],
+    expected_server_proof: [u8; 32],
+}
+
+impl SrpClientSession {
+    /* M1, sent to the server */
+    pub fn proof(&self) -> &[u8] {
+        &self.proof
+    }
+
+    /* M2 = H(A | M1 | K) proves the server knew v */
+    pub fn verify_server(&self, server_proof: &[u8]) -> Result<(), SrpError> {
+        if !constant_time::eq(&self.expected_server_proof, server_proof) {
+            return Err(SrpError::ProofMismatch);
+        }
+
+        Ok(())
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+/* Server before it has seen the client's A */
+pub struct SrpServer {
+    group: DhGroup,
+    record: SrpVerifier,
+    ephemeral: DhKeyPair,
+    public: Ubig,
+}
+
+impl SrpServer {
+    /* B = k v + g^b */
+    pub fn new(group: &DhGroup, record: &SrpVerifier) -> Self {
+        let ephemeral = DhKeyPair::generate(group);
+        let k = multiplier(group);
+
+        let public = (k * &record.verifier + ephemeral.public_key()) % group.p();
+
+        Self {
+            group: group.clone(),
+            record: record.clone(),
+            ephemeral,
+            public,
+        }
+    }
+
+    pub fn salt(&self) -> &[u8] {
+        &self.record.salt
+    }
+
+    pub fn public_key(&self) -> &Ubig {
+        &self.public
+    }
+
+    /* S = (A v^u)^b */
+    pub fn respond(self, client_public: &Ubig) -> Result<SrpServerSession, SrpError> {
+        let p = self.group.p();
+
+        /* A = 0, N, 2N, ... would force S = 0 whatever the password */
+        if !client_public.not_zero() || client_public >= p {
+            return Err(SrpError::PublicOutOfRange);
+        }
+
+        let u = scrambler(&self.group, client_public, &self.public);
+
+        if !u.not_zero() {
+            return Err(SrpError::ScramblerIsZero);
+        }
+
+        let v_u = Ubig::modexp(self.record.verifier.clone(), u, p.clone());
+        let base = client_public * &v_u % p;
+
+        let shared = secret_power(&self.group, &base, self.ephemeral.private_key());
+        let key = hash(&[&pad(&self.group, &shared)]);
+
+        let expected_client_proof = client_proof(
+            &self.group,
+            &self.record.identity,
+            &self.record.salt,
+            client_public,
+            &self.public,
+            &key,
+        );
+
+        let proof = hash(&[
+            &pad(&self.group, client_public),
+            &expected_client_proof,
+            &key,
+        ]);
+
+        Ok(SrpServerSession {
+            key,
+            proof,
+            expected_client_proof,
+        })
+    }
+}
+
+pub struct SrpServerSession {
+    key: [u8; 32],
+    proof: [u8; 32],
+    expected_client_proof: [u8; 32],
+}
+
+impl SrpServerSession {
+    /* Checks M1 and only then releases M2 */
+    pub fn verify_client(&self, client_proof: &[u8]) -> Result<&[u8], SrpError> {
+        if !constant_time::eq(&self.expected_client_proof, client_proof) {
+            return Err(SrpError::ProofMismatch);
+        }
+
+        Ok(&self.proof)
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+/*
+ * Simplified SRP: B = g^b and u is a random 128-bit number, so B no longer
+ * depends on the verifier. A man-in-the-middle posing as the server learns
+ * HMAC(K, salt) and can test password guesses offline.
+ */
+pub fn simple_srp_verifier(group: &DhGroup, salt: &[u8], password: &str) -> Ubig {
+    let x = simple_private_value(salt, password);
+
+    Ubig::modexp_constant_time(
+        group.g().clone(),
+        x,
+        group.p().clone(),
+        8 * Sha256::OUTPUT_SIZE,
+    )
+}
+
+pub struct SimpleSrpClient {
+    group: DhGroup,
+    password: String,
+    ephemeral: DhKeyPair,
+}
+
+impl SimpleSrpClient {
+    pub fn new(group: &DhGroup, password: &str) -> Self {
+        Self {
+            group: group.clone(),
+            password: password.to_string(),
+            ephemeral: DhKeyPair::generate(group),
+        }
+    }
+
+    pub fn public_key(&self) -> &Ubig {
+        self.ephemeral.public_key()
+    }
+
+    /* S = B^(a + u x), answers with HMAC-SHA256(K, salt) */
+    pub fn respond(&self, salt: &[u8], server_public: &Ubig, u: &Ubig) -> [u8; 32] {
+        let x = simple_private_value(salt, &self.password);
+        let exponent = self.ephemeral.private_key() + &(u * &x);
+
+        let shared = secret_power(&self.group, server_public, &exponent);
+        let key = hash(&[&pad(&self.group, &shared)]);
+
+        hmac::hmac::<Sha256>(&key, salt)
+    }
+}
+
+pub struct SimpleSrpServer {
+    group: DhGroup,
+    salt: Vec<u8>,
+    verifier: Ubig,
+    ephemeral: DhKeyPair,
+    u: Ubig,
+}
+
+impl SimpleSrpServer {
+    pub fn new(group: &DhGroup, salt: &[u8], verifier: &Ubig) -> Self {
+        Self {
+            group: group.clone(),
+            salt: salt.to_vec(),
+            verifier: verifier.clone(),
+            ephemeral: DhKeyPair::generate(group),
+            u: Ubig::from(urandom::bytes(16).as_ref()),
+        }
+    }
+
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    pub fn public_key(&self) -> &Ubig {
+        self.ephemeral.public_key()
+    }
+
+    pub fn u(&self) -> &Ubig {
+        &self.u
+    }
+
+    /* b, which an impersonating server knows and uses for the dictionary attack */
+    pub fn private_key(&self) -> &Ubig {
+        self.ephemeral.private_key()
+    }
+
+    /* S = (A v^u)^b */
+    pub fn verify(&self, client_public: &Ubig, mac: &[u8]) -> bool {
+        let key = simple_server_key(
+            &self.group,
+            client_public,
+            &self.verifier,
+            &self.u,
+            self.ephemeral.private_key(),
+        );
+
+        hmac::hmac_verify::<Sha256>(&key, &self.salt, mac)
+    }
+
+    /* Replays the server side for each candidate v until one reproduces the client's HMAC */
+    pub fn dictionary_attack<'a, I>(
+        &self,
+        client_public: &Ubig,
+        mac: &[u8],
+        candidates: I,
+    ) -> Option<&'a str>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        candidates.into_iter().find(|password| {
+            let verifier = simple_srp_verifier(&self.group, &self.salt, password);
+            let key = simple_server_key(
+                &self.group,
+                client_public,
+                &verifier,
+                &self.u,
+                self.ephemeral.private_key(),
+            );
+
+            hmac::hmac_verify::<Sha256>(&key, &self.salt, mac)
+        })
+    }
+}
+
+fn simple_server_key(
+    group: &DhGroup,
+    client_public: &Ubig,
+    verifier: &Ubig,
+    u: &Ubig,
+    b: &Ubig,
+) -> [u8; 32] {
+    let p = group.p();
+
+    let v_u = Ubig::modexp(verifier.clone(), u.clone(), p.clone());
+    let base = client_public * &v_u % p;
+
+    hash(&[&pad(group, &secret_power(group, &base, b))])
+}
+
+/* x = H(s | H(I | ":" | P)) as in RFC 5054 */
+fn private_value(identity: &str, password: &str, salt: &[u8]) -> Ubig {
+    let inner = hash(&[identity.as_bytes(), b":", password.as_bytes()]);
+
+    hash_to_int(&hash(&[salt, &inner]))
+}
+
+fn simple_private_value(salt: &[u8], password: &str) -> Ubig {
+    hash_to_int(&hash(&[salt, password.as_bytes()]))
+}
+
+/* k = H(N | PAD(g)) */
+fn multiplier(group: &DhGroup) -> Ubig {
+    hash_to_int(&hash(&[&pad(group, group.p()), &pad(group, group.g())]))
+}
+
+/* u = H(PAD(A) | PAD(B)) */
+fn scrambler(group: &DhGroup, client_public: &Ubig, server_public: &Ubig) -> Ubig {
+    hash_to_int(&hash(&[
+        &pad(group, client_public),
+        &pad(group, server_public),
+    ]))
+}
+
+fn client_proof(
+    group: &DhGroup,
+    identity: &str,
+    salt: &[u8],
+    client_public: &Ubig,
+    server_public: &Ubig,
+    key: &[u8],
+) -> [u8; 32] {
+    let mut group_hash = hash(&[&pad(group, group.p())]);
+    let g_hash = hash(&[&pad(group, group.g())]);
+
+    for (byte, other) in group_hash.iter_mut().zip(g_hash) {
+        *byte ^= other;
+    }
+
+    hash(&[
+        &group_hash,
+        &hash(&[identity.as_bytes()]),
+        salt,
+        &pad(group, client_public),
+        &pad(group, server_public),
+        key,
+    ])
+}
+
+/* Bounded by the longer of the exponent and the modulus, so only the exponent length leaks */
+fn secret_power(group: &DhGroup, base: &Ubig, exponent: &Ubig) -> Ubig {
+    let bits = std::cmp::max(exponent.bit_length(), group.p().bit_length());
+
+    Ubig::modexp_constant_time(base.clone(), exponent.clone(), group.p().clone(), bits)
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    for part in parts {
+        hasher.update(part);
+    }
+
+    hasher.finalize()
+}
+
+fn hash_to_int(hash: &[u8]) -> Ubig {
+    let mut bytes = hash.to_vec();
+    bytes.reverse();
+
+    Ubig::from(bytes.as_ref())
+}
+
+/* Big-endian, left-padded to the length of N */
+fn pad(group: &DhGroup, x: &Ubig) -> Vec<u8> {
+    let mut bytes = x.as_ref().to_vec();
+
+    bytes.resize(group.p().bit_length().div_ceil(8), 0);
+    bytes.reverse();
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(
+        group: &DhGroup,
+        record: &SrpVerifier,
+        password: &str,
+    ) -> (SrpClientSession, SrpServerSession) {
+        let client = SrpClient::new(group, record.identity(), password);
+        let server = SrpServer::new(group, record);
+
+        let client_public = client.public_key().clone();
+        let client_session = client
+            .respond(server.salt(), server.public_key())
+            .expect("server public key in range");
+        let server_session = server
+            .respond(&client_public)
+            .expect("client public key in range");
+
+        (client_session, server_session)
+    }
+
+    #[test]
+    fn client_and_server_agree() {
+        let group = DhGroup::modp_1536();
+        let record = SrpVerifier::new(&group, "alice@example.com", "correct horse");
+
+        let (client, server) = handshake(&group, &record, "correct horse");
+        let server_proof = server
+            .verify_client(client.proof())
+            .expect("client proof accepted");
+
+        client.verify_server(server_proof).unwrap();
+        assert_eq!(client.key(), server.key());
+    }
+
+    #[test]
+    fn wrong_password_is_rejected() {
+        let group = DhGroup::modp_1536();
+        let record = SrpVerifier::new(&group, "alice@example.com", "correct horse");
+
+        let (client, server) = handshake(&group, &record, "battery staple");
+
+        assert!(matches!(
+            server.verify_client(client.proof()),
+            Err(SrpError::ProofMismatch)
+        ));
+        assert_ne!(client.key(), server.key());
+    }
+
+    #[test]
+    fn degenerate_public_keys_are_rejected() {
+        let group = DhGroup::modp_1536();
+        let record = SrpVerifier::new(&group, "alice@example.com", "correct horse");
+        let p = group.p();
+
+        for public in [Ubig::from(0u64), p.clone(), p + &Ubig::from(1u64)] {
+            let server = SrpServer::new(&group, &record);
+            assert!(matches!(
+                server.respond(&public),
+                Err(SrpError::PublicOutOfRange)
+            ));
+
+            let client = SrpClient::new(&group, "alice@example.com", "correct horse");
+            assert!(matches!(
+                client.respond(record.salt(), &public),
+                Err(SrpError::PublicOutOfRange)
+            ));
+        }
+    }
+
+    #[test]
+    fn simple_srp_dictionary_attack() {
+        let group = DhGroup::modp_1536();
+        let salt = urandom::bytes(16);
+        let verifier = simple_srp_verifier(&group, &salt, "sunshine");
+
+        let client = SimpleSrpClient::new(&group, "sunshine");
+        let server = SimpleSrpServer::new(&group, &salt, &verifier);
+
+        let mac = client.respond(server.salt(), server.public_key(), server.u());
+        assert!(server.verify(client.public_key(), &mac));
+
+        let candidates = ["password", "123456", "letmein", "sunshine", "dragon"];
+        assert_eq!(
+            server.dictionary_attack(client.public_key(), &mac, candidates),
+            Some("sunshine")
+        );
+        assert_eq!(
+            server.dictionary_attack(client.public_key(), &mac, ["password", "dragon"]),
+            None
+        );
+    }
+}
@@ -0,0 +1,644 @@
+use super::modes::{BlockCipher, Ctr, ModeError};
+use super::pkcs7;
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+pub struct CbcEncryptor<C: BlockCipher, W: Write> {
+    cipher: C,
+    writer: W,
+    last_cipher_block: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl<C: BlockCipher, W: Write> CbcEncryptor<C, W> {
+    pub fn new<V: AsRef<[u8]>>(cipher: C, iv: V, writer: W) -> Result<Self, ModeError> {
+        if iv.as_ref().len() != C::BLOCK_SIZE {
+            return Err(ModeError::WrongSizeIv);
+        }
+
+        Ok(Self {
+            cipher,
+            writer,
+            last_cipher_block: iv.as_ref().to_vec(),
+            buffer: Vec::with_capacity(C::BLOCK_SIZE),
+        })
+    }
+
+    pub fn finalize(mut self) -> io::Result<W> {
+        let block = pad_final_block::<C>(&self.buffer);
+        let ciphertext = cbc_encrypt_blocks(&self.cipher, &self.last_cipher_block, &block);
+
+        self.writer.write_all(&ciphertext)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+impl<C: BlockCipher, W: Write> Write for CbcEncryptor<C, W> {
+    /*
+     * The chaining state only advances once write_all succeeds, so a failed
+     * call can be retried with the same input. If write_all fails partway
+     * the inner writer may already hold a prefix of the ciphertext.
+     */
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut pending = self.buffer.clone();
+        pending.extend_from_slice(buf);
+
+        let full_blocks = pending.len() - pending.len() % C::BLOCK_SIZE;
+        let ciphertext = cbc_encrypt_blocks(
+            &self.cipher,
+            &self.last_cipher_block,
+            &pending[..full_blocks],
+        );
+
+        self.writer.write_all(&ciphertext)?;
+
+        chain_last_block::<C>(&mut self.last_cipher_block, &ciphertext);
+        self.buffer = pending.split_off(full_blocks);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub struct CbcDecryptor<C: BlockCipher, W: Write> {
+    cipher: C,
+    writer: W,
+    last_cipher_block: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl<C: BlockCipher, W: Write> CbcDecryptor<C, W> {
+    pub fn new<V: AsRef<[u8]>>(cipher: C, iv: V, writer: W) -> Result<Self, ModeError> {
+        if iv.as_ref().len() != C::BLOCK_SIZE {
+            return Err(ModeError::WrongSizeIv);
+        }
+
+        Ok(Self {
+            cipher,
+            writer,
+            last_cipher_block: iv.as_ref().to_vec(),
+            buffer: Vec::with_capacity(2 * C::BLOCK_SIZE),
+        })
+    }
+
+    pub fn finalize(mut self) -> io::Result<W> {
+        if self.buffer.len() != C::BLOCK_SIZE {
+            return Err(ModeError::IrregularDecryptLength.into());
+        }
+
+        let mut cleartext = cbc_decrypt_blocks(&self.cipher, &self.last_cipher_block, &self.buffer);
+        unpad(&mut cleartext)?;
+
+        self.writer.write_all(&cleartext)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+impl<C: BlockCipher, W: Write> Write for CbcDecryptor<C, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut pending = self.buffer.clone();
+        pending.extend_from_slice(buf);
+
+        let ready = ready_to_decrypt::<C>(pending.len());
+        let cleartext =
+            cbc_decrypt_blocks(&self.cipher, &self.last_cipher_block, &pending[..ready]);
+
+        self.writer.write_all(&cleartext)?;
+
+        chain_last_block::<C>(&mut self.last_cipher_block, &pending[..ready]);
+        self.buffer = pending.split_off(ready);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub struct CbcEncryptReader<C: BlockCipher, R: Read> {
+    cipher: C,
+    reader: R,
+    last_cipher_block: Vec<u8>,
+    buffer: Vec<u8>,
+    output: Vec<u8>,
+    finished: bool,
+}
+
+impl<C: BlockCipher, R: Read> CbcEncryptReader<C, R> {
+    pub fn new<V: AsRef<[u8]>>(cipher: C, iv: V, reader: R) -> Result<Self, ModeError> {
+        if iv.as_ref().len() != C::BLOCK_SIZE {
+            return Err(ModeError::WrongSizeIv);
+        }
+
+        Ok(Self {
+            cipher,
+            reader,
+            last_cipher_block: iv.as_ref().to_vec(),
+            buffer: Vec::with_capacity(C::BLOCK_SIZE),
+            output: Vec::new(),
+            finished: false,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn fill_output(&mut self, size_hint: usize) -> io::Result<()> {
+        while self.output.is_empty() && !self.finished {
+            let mut chunk = vec![0; size_hint.max(C::BLOCK_SIZE)];
+            let read = self.reader.read(&mut chunk)?;
+
+            let blocks = if read == 0 {
+                self.finished = true;
+                pad_final_block::<C>(&self.buffer)
+            } else {
+                self.buffer.extend_from_slice(&chunk[..read]);
+
+                let full_blocks = self.buffer.len() - self.buffer.len() % C::BLOCK_SIZE;
+                self.buffer.drain(..full_blocks).collect()
+            };
+
+            self.output = cbc_encrypt_blocks(&self.cipher, &self.last_cipher_block, &blocks);
+            chain_last_block::<C>(&mut self.last_cipher_block, &self.output);
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: BlockCipher, R: Read> Read for CbcEncryptReader<C, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill_output(buf.len())?;
+
+        Ok(drain_into(&mut self.output, buf))
+    }
+}
+
+pub struct CbcDecryptReader<C: BlockCipher, R: Read> {
+    cipher: C,
+    reader: R,
+    last_cipher_block: Vec<u8>,
+    buffer: Vec<u8>,
+    output: Vec<u8>,
+    finished: bool,
+}
+
+impl<C: BlockCipher, R: Read> CbcDecryptReader<C, R> {
+    pub fn new<V: AsRef<[u8]>>(cipher: C, iv: V, reader: R) -> Result<Self, ModeError> {
+        if iv.as_ref().len() != C::BLOCK_SIZE {
+            return Err(ModeError::WrongSizeIv);
+        }
+
+        Ok(Self {
+            cipher,
+            reader,
+            last_cipher_block: iv.as_ref().to_vec(),
+            buffer: Vec::with_capacity(2 * C::BLOCK_SIZE),
+            output: Vec::new(),
+            finished: false,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn fill_output(&mut self, size_hint: usize) -> io::Result<()> {
+        while self.output.is_empty() && !self.finished {
+            let mut chunk = vec![0; size_hint.max(C::BLOCK_SIZE)];
+            let read = self.reader.read(&mut chunk)?;
+
+            if read == 0 {
+                if self.buffer.len() != C::BLOCK_SIZE {
+                    return Err(ModeError::IrregularDecryptLength.into());
+                }
+
+                let mut cleartext =
+                    cbc_decrypt_blocks(&self.cipher, &self.last_cipher_block, &self.buffer);
+                unpad(&mut cleartext)?;
+
+                self.finished = true;
+                self.buffer.clear();
+                self.output = cleartext;
+            } else {
+                self.buffer.extend_from_slice(&chunk[..read]);
+
+                let ready = ready_to_decrypt::<C>(self.buffer.len());
+                let blocks: Vec<u8> = self.buffer.drain(..ready).collect();
+
+                self.output = cbc_decrypt_blocks(&self.cipher, &self.last_cipher_block, &blocks);
+                chain_last_block::<C>(&mut self.last_cipher_block, &blocks);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: BlockCipher, R: Read> Read for CbcDecryptReader<C, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill_output(buf.len())?;
+
+        Ok(drain_into(&mut self.output, buf))
+    }
+}
+
+pub struct CtrStream<C: BlockCipher, S> {
+    key_stream: Ctr<C>,
+    inner: S,
+}
+
+impl<C: BlockCipher, S> CtrStream<C, S> {
+    pub fn new(cipher: C, nonce: u64, inner: S) -> Self {
+        Self::with_ctr(Ctr::with_nonce(cipher, nonce), inner)
+    }
+
+    pub fn with_ctr(key_stream: Ctr<C>, inner: S) -> Self {
+        Self { key_stream, inner }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn apply_key_stream(&mut self, buf: &mut [u8]) {
+        self.key_stream.apply_key_stream(buf);
+    }
+}
+
+impl<C: BlockCipher, R: Read> Read for CtrStream<C, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+
+        self.apply_key_stream(&mut buf[..read]);
+
+        Ok(read)
+    }
+}
+
+impl<C: BlockCipher, W: Write> Write for CtrStream<C, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let position = self.key_stream.position();
+        let mut output = buf.to_vec();
+
+        self.apply_key_stream(&mut output);
+
+        /* Rewind so a retry after a failed write reuses the same key stream */
+        if let Err(error) = self.inner.write_all(&output) {
+            self.key_stream.seek(position);
+
+            return Err(error);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<C: BlockCipher, S: Seek> Seek for CtrStream<C, S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.key_stream.seek(position);
+
+        Ok(position)
+    }
+}
+
+impl From<ModeError> for io::Error {
+    fn from(value: ModeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", value))
+    }
+}
+
+fn cbc_encrypt_blocks<C: BlockCipher>(cipher: &C, iv: &[u8], input: &[u8]) -> Vec<u8> {
+    let mut ciphertext = Vec::with_capacity(input.len());
+    let mut last_cipher_block = iv.to_vec();
+
+    for block in input.chunks_exact(C::BLOCK_SIZE) {
+        for (a, b) in last_cipher_block.iter_mut().zip(block.iter()) {
+            *a ^= b;
+        }
+
+        cipher.encrypt_block(&mut last_cipher_block);
+
+        ciphertext.extend_from_slice(&last_cipher_block);
+    }
+
+    ciphertext
+}
+
+fn cbc_decrypt_blocks<C: BlockCipher>(cipher: &C, iv: &[u8], input: &[u8]) -> Vec<u8> {
+    let mut cleartext = Vec::with_capacity(input.len());
+    let mut last_cipher_block = iv;
+
+    for block in input.chunks_exact(C::BLOCK_SIZE) {
+        let mut clear_block = block.to_vec();
+        cipher.decrypt_block(&mut clear_block);
+
+        for (a, b) in clear_block.iter_mut().zip(last_cipher_block.iter()) {
+            *a ^= b;
+        }
+
+        last_cipher_block = block;
+
+        cleartext.extend_from_slice(&clear_block);
+    }
+
+    cleartext
+}
+
+fn chain_last_block<C: BlockCipher>(last_cipher_block: &mut [u8], ciphertext: &[u8]) {
+    if let Some(block) = ciphertext.rchunks_exact(C::BLOCK_SIZE).next() {
+        last_cipher_block.copy_from_slice(block);
+    }
+}
+
+/* Hold back the last full block, since it carries the padding */
+fn ready_to_decrypt<C: BlockCipher>(buffered: usize) -> usize {
+    let held_back = match buffered % C::BLOCK_SIZE {
+        0 => C::BLOCK_SIZE,
+        partial => partial,
+    };
+
+    buffered.saturating_sub(held_back)
+}
+
+fn pad_final_block<C: BlockCipher>(rest: &[u8]) -> Vec<u8> {
+    let bytes_to_copy = rest.len();
+
+    let mut block = vec![0; C::BLOCK_SIZE];
+    block[..bytes_to_copy].copy_from_slice(rest);
+
+    pkcs7::pad_inplace(&mut block, bytes_to_copy);
+
+    block
+}
+
+fn unpad(cleartext: &mut Vec<u8>) -> Result<(), ModeError> {
+    let cleartext_end = pkcs7::unpad_length(cleartext);
+
+    if cleartext_end == cleartext.len() {
+        return Err(ModeError::PaddingError);
+    }
+
+    cleartext.truncate(cleartext_end);
+
+    Ok(())
+}
+
+fn drain_into(output: &mut Vec<u8>, buf: &mut [u8]) -> usize {
+    let count = output.len().min(buf.len());
+
+    buf[..count].copy_from_slice(&output[..count]);
+    output.drain(..count);
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{aes_cbc_encrypt, aes_ctr, Aes, AesKey};
+    use std::io::Cursor;
+
+    const IV: [u8; 16] = [3; 16];
+
+    fn aes() -> Aes {
+        Aes::new(&AesKey::from(b"YELLOW SUBMARINE").unwrap())
+    }
+
+    /* Hands out at most `chunk` bytes per read */
+    struct Trickle<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = self.chunk.min(buf.len()).min(self.data.len());
+
+            buf[..count].copy_from_slice(&self.data[..count]);
+            self.data = &self.data[count..];
+
+            Ok(count)
+        }
+    }
+
+    /* Rejects every write after the first `accept` calls */
+    struct Flaky {
+        written: Vec<u8>,
+        accept: usize,
+    }
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.accept == 0 {
+                return Err(io::ErrorKind::Other.into());
+            }
+
+            self.accept -= 1;
+            self.written.extend_from_slice(buf);
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cbc_adapters_match_one_shot() {
+        for length in [0usize, 1, 15, 16, 17, 31, 32, 33, 100] {
+            let message: Vec<u8> = (0..length).map(|i| (i * 7) as u8).collect();
+            let expected =
+                aes_cbc_encrypt(&message, &AesKey::from(b"YELLOW SUBMARINE").unwrap(), IV).unwrap();
+
+            for chunk in [1usize, 5, 16, 17, 64] {
+                let mut encryptor = CbcEncryptor::new(aes(), IV, Vec::new()).unwrap();
+
+                for piece in message.chunks(chunk) {
+                    encryptor.write_all(piece).unwrap();
+                }
+
+                assert_eq!(encryptor.finalize().unwrap(), expected);
+
+                let mut decryptor = CbcDecryptor::new(aes(), IV, Vec::new()).unwrap();
+
+                for piece in expected.chunks(chunk) {
+                    decryptor.write_all(piece).unwrap();
+                }
+
+                assert_eq!(decryptor.finalize().unwrap(), message);
+
+                let source = Trickle {
+                    data: &message,
+                    chunk,
+                };
+                let mut ciphertext = Vec::new();
+
+                CbcEncryptReader::new(aes(), IV, source)
+                    .unwrap()
+                    .read_to_end(&mut ciphertext)
+                    .unwrap();
+
+                assert_eq!(ciphertext, expected);
+
+                let source = Trickle {
+                    data: &expected,
+                    chunk,
+                };
+                let mut cleartext = Vec::new();
+
+                CbcDecryptReader::new(aes(), IV, source)
+                    .unwrap()
+                    .read_to_end(&mut cleartext)
+                    .unwrap();
+
+                assert_eq!(cleartext, message);
+            }
+        }
+    }
+
+    #[test]
+    fn cbc_readers_reject_bad_input() {
+        let mut cleartext = Vec::new();
+        let mut reader = CbcDecryptReader::new(aes(), IV, &[0u8; 15][..]).unwrap();
+
+        assert!(reader.read_to_end(&mut cleartext).is_err());
+
+        let mut reader = CbcDecryptReader::new(aes(), IV, &[][..]).unwrap();
+
+        assert!(reader.read_to_end(&mut cleartext).is_err());
+
+        /* A zero final byte is never valid padding */
+        let mut block = [0u8; 16];
+        aes().encrypt_block(&mut block);
+
+        let mut reader = CbcDecryptReader::new(aes(), [0u8; 16], &block[..]).unwrap();
+
+        assert!(reader.read_to_end(&mut cleartext).is_err());
+    }
+
+    #[test]
+    fn cbc_writers_survive_failed_writes() {
+        let message: Vec<u8> = (0..48).collect();
+        let expected =
+            aes_cbc_encrypt(&message, &AesKey::from(b"YELLOW SUBMARINE").unwrap(), IV).unwrap();
+
+        let sink = Flaky {
+            written: Vec::new(),
+            accept: 1,
+        };
+        let mut encryptor = CbcEncryptor::new(aes(), IV, sink).unwrap();
+
+        encryptor.write_all(&message[..16]).unwrap();
+        assert!(encryptor.write(&message[16..]).is_err());
+
+        encryptor.writer.accept = usize::MAX;
+        encryptor.write_all(&message[16..]).unwrap();
+
+        assert_eq!(encryptor.finalize().unwrap().written, expected);
+
+        let sink = Flaky {
+            written: Vec::new(),
+            accept: 1,
+        };
+        let mut decryptor = CbcDecryptor::new(aes(), IV, sink).unwrap();
+
+        decryptor.write_all(&expected[..32]).unwrap();
+        assert!(decryptor.write(&expected[32..]).is_err());
+
+        decryptor.writer.accept = usize::MAX;
+        decryptor.write_all(&expected[32..]).unwrap();
+
+        assert_eq!(decryptor.finalize().unwrap().written, message);
+    }
+
+    #[test]
+    fn ctr_adapters_match_one_shot() {
+        let key = AesKey::from(b"YELLOW SUBMARINE").unwrap();
+        let message: Vec<u8> = (0..100).map(|i| (i * 13) as u8).collect();
+        let expected = aes_ctr(&message, &key, 7);
+
+        for chunk in [1usize, 3, 7, 15, 17, 33] {
+            let mut stream = CtrStream::new(aes(), 7, Vec::new());
+
+            for piece in message.chunks(chunk) {
+                stream.write_all(piece).unwrap();
+            }
+
+            assert_eq!(stream.into_inner(), expected);
+
+            let source = Trickle {
+                data: &expected,
+                chunk,
+            };
+            let mut cleartext = Vec::new();
+
+            CtrStream::new(aes(), 7, source)
+                .read_to_end(&mut cleartext)
+                .unwrap();
+
+            assert_eq!(cleartext, message);
+        }
+    }
+
+    #[test]
+    fn ctr_seek() {
+        let key = AesKey::from(b"YELLOW SUBMARINE").unwrap();
+        let message: Vec<u8> = (0..100).collect();
+        let ciphertext = aes_ctr(&message, &key, 7);
+
+        let mut stream = CtrStream::new(aes(), 7, Cursor::new(ciphertext.clone()));
+
+        for offset in [37u64, 0, 16, 99, 5] {
+            let mut cleartext = Vec::new();
+
+            assert_eq!(stream.seek(SeekFrom::Start(offset)).unwrap(), offset);
+            stream.read_to_end(&mut cleartext).unwrap();
+
+            assert_eq!(cleartext, message[offset as usize..]);
+        }
+
+        /* Overwriting the middle of the stream gives the same bytes back */
+        let mut stream = CtrStream::new(aes(), 7, Cursor::new(ciphertext.clone()));
+
+        stream.seek(SeekFrom::Start(20)).unwrap();
+        stream.write_all(&message[20..50]).unwrap();
+
+        assert_eq!(stream.into_inner().into_inner(), ciphertext);
+    }
+
+    #[test]
+    fn ctr_writer_survives_failed_writes() {
+        let key = AesKey::from(b"YELLOW SUBMARINE").unwrap();
+        let message: Vec<u8> = (0..40).collect();
+
+        let sink = Flaky {
+            written: Vec::new(),
+            accept: 1,
+        };
+        let mut stream = CtrStream::new(aes(), 7, sink);
+
+        stream.write_all(&message[..10]).unwrap();
+        assert!(stream.write(&message[10..]).is_err());
+
+        stream.inner.accept = usize::MAX;
+        stream.write_all(&message[10..]).unwrap();
+
+        assert_eq!(stream.into_inner().written, aes_ctr(&message, &key, 7));
+    }
+}
@@ -1,319 +1,1555 @@
+#[cfg(test)]
+use std::cell::Cell;
+use std::cell::OnceCell;
 use std::cmp::Ordering;
 use std::fmt;
-use std::iter::Rev;
-use std::ops::{Add, Mul, Rem, ShlAssign, ShrAssign, SubAssign};
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Rem, RemAssign, Shl, ShlAssign, Shr, ShrAssign,
+    Sub, SubAssign,
+};
+use std::str::FromStr;
+
+const KARATSUBA_THRESHOLD: usize = 32;
+
+#[derive(Debug)]
+pub enum UbigError {
+    EmptyString,
+    InvalidDigit,
+    Overflow,
+    Negative,
+}
 
+/*
+ * Little-endian 64-bit limbs with no leading zero limbs, so zero is empty.
+ * The byte view handed out by AsRef is built lazily and dropped on mutation.
+ */
 #[derive(Clone)]
-pub struct Ubig(Vec<u8>);
+pub struct Ubig {
+    limbs: Vec<u64>,
+    bytes: OnceCell<Vec<u8>>,
+}
 
 impl Ubig {
     pub fn new(int: &str) -> Self {
         let mut bytes = hex::decode(int).unwrap();
         bytes.reverse();
 
-        Self(bytes)
+        Self::from(bytes.as_ref())
     }
 
-    pub fn modexp(base: Self, mut exponent: Self, modulus: Self) -> Self {
-        let mut res = Self(vec![1]);
-        let mut base = base % &modulus;
+    pub fn modexp(base: Self, exponent: Self, modulus: Self) -> Self {
+        assert!(
+            modulus.not_zero(),
+            "attempt to calculate the remainder with a divisor of zero"
+        );
+
+        if modulus.limbs[0] & 1 == 1 {
+            return Montgomery::new(&modulus).modexp(&base, &exponent);
+        }
+
+        /* Even moduli cannot use Montgomery reduction */
+        let mut res = Self::from_limbs(vec![1]) % &modulus;
+        let base = base % &modulus;
 
-        let two = Self(vec![2]);
+        for i in (0..exponent.bit_length()).rev() {
+            res = (res.clone() * res) % &modulus;
 
-        while exponent.not_zero() {
-            if (exponent.clone() % &two).is_one() {
+            if exponent.bit(i) {
                 res = (res * base.clone()) % &modulus;
             }
+        }
+
+        res
+    }
+
+    pub fn modexp_constant_time(
+        base: Self,
+        exponent: Self,
+        modulus: Self,
+        exponent_bits: usize,
+    ) -> Self {
+        Montgomery::new(&modulus).modexp_constant_time(&base, &exponent, exponent_bits)
+    }
+
+    pub fn div_rem(&self, divisor: &Ubig) -> (Self, Self) {
+        assert!(divisor.not_zero(), "attempt to divide by zero");
+
+        if self < divisor {
+            return (Self::from_limbs(vec![]), self.clone());
+        }
+
+        let (quotient, rem) = div_rem_limbs(&self.limbs, &divisor.limbs);
+
+        (Self::from_limbs(quotient), Self::from_limbs(rem))
+    }
+
+    pub fn gcd(mut a: Self, mut b: Self) -> Self {
+        while b.not_zero() {
+            let rem = a % &b;
+
+            a = b;
+            b = rem;
+        }
+
+        a
+    }
+
+    /*
+     * Returns (g, x, y) with g = gcd(a, b), a * x = g (mod b) and b * y = g (mod a).
+     * The Bezout coefficients alternate in sign, so only their magnitudes are
+     * tracked and the negative one is folded back into range at the end.
+     */
+    pub fn extended_gcd(a: Self, b: Self) -> (Self, Self, Self) {
+        let (mut r0, mut r1) = (a.clone(), b.clone());
+        let (mut s0, mut s1) = (Self::from_limbs(vec![1]), Self::from_limbs(vec![]));
+        let (mut t0, mut t1) = (Self::from_limbs(vec![]), Self::from_limbs(vec![1]));
+        let mut s_negative = false;
+
+        while r1.not_zero() {
+            let (quotient, rem) = r0.div_rem(&r1);
+
+            let s = s0 + quotient.clone() * s1.clone();
+            let t = t0 + quotient * t1.clone();
+
+            r0 = r1;
+            r1 = rem;
+            s0 = s1;
+            s1 = s;
+            t0 = t1;
+            t1 = t;
+
+            s_negative = !s_negative;
+        }
+
+        let x = Self::fold_coefficient(s0, s_negative, b, &r0);
+        let y = Self::fold_coefficient(t0, !s_negative, a, &r0);
+
+        (r0, x, y)
+    }
+
+    /* None when a is not invertible, and for moduli 0 and 1 */
+    pub fn modinv(a: Self, modulus: Self) -> Option<Self> {
+        if modulus <= Self::from_limbs(vec![1]) {
+            return None;
+        }
+
+        let (gcd, x, _) = Self::extended_gcd(a, modulus.clone());
+
+        if !gcd.is_one() {
+            return None;
+        }
+
+        Some(x % &modulus)
+    }
+
+    fn fold_coefficient(magnitude: Self, negative: bool, other: Self, gcd: &Ubig) -> Self {
+        if !negative || !magnitude.not_zero() {
+            return magnitude;
+        }
+
+        let mut folded = other / gcd;
+        folded -= &magnitude;
+
+        folded
+    }
+
+    pub fn not_zero(&self) -> bool {
+        !self.limbs.is_empty()
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.limbs == [1]
+    }
+
+    fn from_limbs(mut limbs: Vec<u64>) -> Self {
+        normalize(&mut limbs);
+
+        Self {
+            limbs,
+            bytes: OnceCell::new(),
+        }
+    }
+
+    pub fn checked_sub(&self, rhs: &Ubig) -> Option<Self> {
+        if self < rhs {
+            return None;
+        }
+
+        let mut limbs = self.limbs.clone();
+        sub_limbs_inplace(&mut limbs, &rhs.limbs);
+
+        Some(Self::from_limbs(limbs))
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> Self {
+        let mut res = Self::from(1u64);
+        let mut base = self.clone();
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                res = &res * &base;
+            }
 
             exponent >>= 1;
 
-            base = (base.clone() * base) % &modulus;
+            if exponent > 0 {
+                base = &base * &base;
+            }
         }
 
         res
     }
 
-    fn not_zero(&self) -> bool {
-        for byte in self.0.iter() {
-            if *byte != 0 {
-                return true;
+    /* Floor of the n-th root, and whether it is exact */
+    pub fn nth_root(&self, n: u32) -> (Self, bool) {
+        assert!(n > 0, "zeroth root is undefined");
+
+        if n == 1 || !self.not_zero() {
+            return (self.clone(), true);
+        }
+
+        /* Newton's method from a power of two above the root decreases monotonically to the floor */
+        let n_big = Self::from(n as u64);
+        let n_minus_one = Self::from(n as u64 - 1);
+
+        let mut x = Self::from(1u64) << self.bit_length().div_ceil(n as usize) as u32;
+
+        loop {
+            let y = (&n_minus_one * &x + self / &x.pow(n - 1)) / &n_big;
+
+            if y >= x {
+                break;
             }
+
+            x = y;
         }
 
-        false
+        let exact = x.pow(n) == *self;
+
+        (x, exact)
     }
 
-    fn is_one(&self) -> bool {
-        let leading_zeros = self.0.iter().rev().take_while(|byte| **byte == 0).count();
-        let bytes = &self.0[0..self.0.len() - leading_zeros];
+    pub fn isqrt(&self) -> (Self, bool) {
+        self.nth_root(2)
+    }
+
+    pub fn icbrt(&self) -> (Self, bool) {
+        self.nth_root(3)
+    }
 
-        bytes.len() == 1 && *bytes.first().unwrap() == 1
+    pub fn bit_length(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => 64 * self.limbs.len() - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn bit(&self, index: usize) -> bool {
+        self.limbs
+            .get(index / 64)
+            .is_some_and(|limb| limb >> (index % 64) & 1 == 1)
+    }
+
+    pub fn set_bit(&mut self, index: usize, value: bool) {
+        let limb = index / 64;
+
+        if limb >= self.limbs.len() {
+            if !value {
+                return;
+            }
+
+            self.limbs.resize(limb + 1, 0);
+        }
+
+        if value {
+            self.limbs[limb] |= 1 << (index % 64);
+        } else {
+            self.limbs[limb] &= !(1 << (index % 64));
+        }
+
+        normalize(&mut self.limbs);
+        self.invalidate_bytes();
+    }
+
+    /*
+     * Swaps a and b when swap is set, by masking every limb of both, so
+     * there is no branch on swap. Both are padded to the wider of the two
+     * first, so only their lengths show.
+     */
+    pub fn conditional_swap(a: &mut Self, b: &mut Self, swap: bool) {
+        let width = std::cmp::max(a.limbs.len(), b.limbs.len());
+
+        a.limbs.resize(width, 0);
+        b.limbs.resize(width, 0);
+
+        let mask = std::hint::black_box(swap as u64).wrapping_neg();
+
+        for (x, y) in a.limbs.iter_mut().zip(b.limbs.iter_mut()) {
+            let difference = mask & (*x ^ *y);
+
+            *x ^= difference;
+            *y ^= difference;
+        }
+
+        for value in [a, b] {
+            normalize(&mut value.limbs);
+            value.invalidate_bytes();
+        }
+    }
+
+    fn invalidate_bytes(&mut self) {
+        self.bytes = OnceCell::new();
+    }
+}
+
+/* Reusable context for arithmetic modulo a fixed odd modulus */
+#[derive(Clone)]
+pub struct Montgomery {
+    modulus: Ubig,
+    n0_inv: u64,
+    r_squared: Vec<u64>,
+    #[cfg(test)]
+    multiplications: Cell<u64>,
+}
+
+impl Montgomery {
+    pub fn new(modulus: &Ubig) -> Self {
+        assert!(
+            modulus.limbs.first().is_some_and(|limb| limb & 1 == 1),
+            "Montgomery reduction needs an odd modulus"
+        );
+
+        /* Newton iteration for m^-1 mod 2^64, doubling the correct bits each step */
+        let m0 = modulus.limbs[0];
+        let mut inverse = 1u64;
+
+        for _ in 0..6 {
+            inverse = inverse.wrapping_mul(2u64.wrapping_sub(m0.wrapping_mul(inverse)));
+        }
+
+        let n = modulus.limbs.len();
+
+        let mut r_squared = vec![0u64; 2 * n + 1];
+        r_squared[2 * n] = 1;
+
+        let (_, mut r_squared) = div_rem_limbs(&r_squared, &modulus.limbs);
+        r_squared.resize(n, 0);
+
+        Self {
+            modulus: modulus.clone(),
+            n0_inv: inverse.wrapping_neg(),
+            r_squared,
+            #[cfg(test)]
+            multiplications: Cell::new(0),
+        }
+    }
+
+    pub fn modulus(&self) -> &Ubig {
+        &self.modulus
+    }
+
+    /* Montgomery multiplications performed so far, for checking that secret exponents don't change it */
+    #[cfg(test)]
+    pub fn multiplications(&self) -> u64 {
+        self.multiplications.get()
+    }
+
+    pub fn to_montgomery(&self, x: &Ubig) -> Ubig {
+        Ubig::from_limbs(self.mul_limbs(&self.widen(x), &self.r_squared))
+    }
+
+    pub fn to_standard(&self, x: &Ubig) -> Ubig {
+        let mut one = vec![0u64; self.width()];
+        one[0] = 1;
+
+        Ubig::from_limbs(self.mul_limbs(&self.widen(x), &one))
+    }
+
+    pub fn mul(&self, a: &Ubig, b: &Ubig) -> Ubig {
+        Ubig::from_limbs(self.mul_limbs(&self.widen(a), &self.widen(b)))
+    }
+
+    /* Left-to-right sliding window over the exponent bits */
+    pub fn modexp(&self, base: &Ubig, exponent: &Ubig) -> Ubig {
+        let bits = exponent.bit_length();
+
+        let window = match bits {
+            0..=32 => 1,
+            33..=128 => 3,
+            129..=512 => 4,
+            _ => 5,
+        };
+
+        let base = self.widen(&self.to_montgomery(base));
+        let base_squared = self.mul_limbs(&base, &base);
+
+        /* Odd powers base^1, base^3, ..., base^(2^window - 1) */
+        let mut table = vec![base];
+
+        for i in 1..1 << (window - 1) {
+            let next = self.mul_limbs(&table[i - 1], &base_squared);
+            table.push(next);
+        }
+
+        let mut res = self.widen(&self.to_montgomery(&Ubig::from_limbs(vec![1])));
+        let mut i = bits;
+
+        while i > 0 {
+            if !exponent.bit(i - 1) {
+                res = self.mul_limbs(&res, &res);
+                i -= 1;
+                continue;
+            }
+
+            /* Longest run of at most `window` bits that ends in a set bit */
+            let mut length = std::cmp::min(window, i);
+
+            while !exponent.bit(i - length) {
+                length -= 1;
+            }
+
+            let mut value = 0;
+
+            for j in (i - length..i).rev() {
+                value = value << 1 | exponent.bit(j) as usize;
+                res = self.mul_limbs(&res, &res);
+            }
+
+            res = self.mul_limbs(&res, &table[value >> 1]);
+            i -= length;
+        }
+
+        self.to_standard(&Ubig::from_limbs(res))
+    }
+
+    /*
+     * Fixed 4-bit windows over exactly `exponent_bits` bits: every window costs
+     * four squarings and one multiplication, and the table entry is picked by
+     * scanning the whole table, so neither timing nor memory access depends on
+     * the exponent. Only the width of the exponent is public.
+     */
+    pub fn modexp_constant_time(&self, base: &Ubig, exponent: &Ubig, exponent_bits: usize) -> Ubig {
+        assert!(
+            exponent.bit_length() <= exponent_bits,
+            "exponent is wider than exponent_bits"
+        );
+
+        let mut exponent_limbs = exponent.limbs.clone();
+        exponent_limbs.resize(exponent_bits.div_ceil(64) + 1, 0);
+
+        let one = self.widen(&self.to_montgomery(&Ubig::from_limbs(vec![1])));
+        let base = self.widen(&self.to_montgomery(base));
+
+        /* base^0 .. base^15 */
+        let mut table = vec![one.clone(), base.clone()];
+
+        for i in 2..16 {
+            let next = self.mul_limbs(&table[i - 1], &base);
+            table.push(next);
+        }
+
+        let mut res = one;
+
+        for window in (0..exponent_bits.div_ceil(4)).rev() {
+            for _ in 0..4 {
+                res = self.mul_limbs(&res, &res);
+            }
+
+            let bit = 4 * window;
+            let value = (exponent_limbs[bit / 64] >> (bit % 64)) & 0xf;
+
+            res = self.mul_limbs(&res, &select_limbs(&table, value as usize));
+        }
+
+        self.to_standard(&Ubig::from_limbs(res))
+    }
+
+    fn width(&self) -> usize {
+        self.modulus.limbs.len()
+    }
+
+    /* Operands at or above the modulus are reduced first, the limb arithmetic assumes x < m */
+    fn widen(&self, x: &Ubig) -> Vec<u64> {
+        let mut limbs = if *x < self.modulus {
+            x.limbs.clone()
+        } else {
+            (x.clone() % &self.modulus).limbs
+        };
+
+        limbs.resize(self.width(), 0);
+
+        limbs
+    }
+
+    /* Coarsely integrated operand scanning: a * b * R^-1 mod m */
+    fn mul_limbs(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let m = &self.modulus.limbs;
+        let n = m.len();
+
+        let mut t = vec![0u64; n + 2];
+
+        for &a_limb in a.iter() {
+            let mut carry = 0u64;
+
+            for j in 0..n {
+                let sum = t[j] as u128 + a_limb as u128 * b[j] as u128 + carry as u128;
+                t[j] = sum as u64;
+                carry = (sum >> 64) as u64;
+            }
+
+            let sum = t[n] as u128 + carry as u128;
+            t[n] = sum as u64;
+            t[n + 1] = (sum >> 64) as u64;
+
+            let q = t[0].wrapping_mul(self.n0_inv);
+
+            let sum = t[0] as u128 + q as u128 * m[0] as u128;
+            let mut carry = (sum >> 64) as u64;
+
+            for j in 1..n {
+                let sum = t[j] as u128 + q as u128 * m[j] as u128 + carry as u128;
+                t[j - 1] = sum as u64;
+                carry = (sum >> 64) as u64;
+            }
+
+            let sum = t[n] as u128 + carry as u128;
+            t[n - 1] = sum as u64;
+            t[n] = t[n + 1] + (sum >> 64) as u64;
+            t[n + 1] = 0;
+        }
+
+        t.truncate(n + 1);
+
+        /* Always subtract, then keep whichever of t and t - m is in range without branching */
+        let mut reduced = t.clone();
+        let mut borrow = false;
+
+        for (i, limb) in reduced.iter_mut().enumerate() {
+            let (difference, b1) = limb.overflowing_sub(m.get(i).copied().unwrap_or(0));
+            let (difference, b2) = difference.overflowing_sub(borrow as u64);
+
+            *limb = difference;
+            borrow = b1 | b2;
+        }
+
+        let keep_t = 0u64.wrapping_sub(borrow as u64);
+
+        for (limb, reduced_limb) in t.iter_mut().zip(reduced) {
+            *limb = (*limb & keep_t) | (reduced_limb & !keep_t);
+        }
+
+        t.truncate(n);
+
+        #[cfg(test)]
+        self.multiplications.set(self.multiplications.get() + 1);
+
+        t
     }
 }
 
 impl From<&[u8]> for Ubig {
     fn from(value: &[u8]) -> Self {
-        Self(Vec::from(value))
+        let limbs = value
+            .chunks(8)
+            .map(|chunk| {
+                let mut limb = [0u8; 8];
+                limb[..chunk.len()].copy_from_slice(chunk);
+
+                u64::from_le_bytes(limb)
+            })
+            .collect();
+
+        Self::from_limbs(limbs)
     }
 }
 
 impl From<Ubig> for Vec<u8> {
     fn from(value: Ubig) -> Self {
-        value.0
+        match value.bytes.into_inner() {
+            Some(bytes) => bytes,
+            None => limbs_to_bytes(&value.limbs),
+        }
     }
 }
 
 impl AsRef<[u8]> for Ubig {
     fn as_ref(&self) -> &[u8] {
-        &self.0
+        self.bytes.get_or_init(|| limbs_to_bytes(&self.limbs))
     }
 }
 
-impl fmt::Display for Ubig {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.0.is_empty() {
-            return write!(f, "0");
-        }
+impl From<u64> for Ubig {
+    fn from(value: u64) -> Self {
+        Self::from_limbs(vec![value])
+    }
+}
+
+impl From<u128> for Ubig {
+    fn from(value: u128) -> Self {
+        Self::from_limbs(vec![value as u64, (value >> 64) as u64])
+    }
+}
 
-        for byte in self.0.iter().rev() {
-            write!(f, "{:02x}", byte)?;
+impl TryFrom<&Ubig> for u64 {
+    type Error = UbigError;
+
+    fn try_from(value: &Ubig) -> Result<Self, Self::Error> {
+        match value.limbs.len() {
+            0 => Ok(0),
+            1 => Ok(value.limbs[0]),
+            _ => Err(UbigError::Overflow),
         }
+    }
+}
+
+impl TryFrom<&Ubig> for u128 {
+    type Error = UbigError;
 
-        Ok(())
+    fn try_from(value: &Ubig) -> Result<Self, Self::Error> {
+        match value.limbs.len() {
+            0 => Ok(0),
+            1 => Ok(value.limbs[0] as u128),
+            2 => Ok((value.limbs[1] as u128) << 64 | value.limbs[0] as u128),
+            _ => Err(UbigError::Overflow),
+        }
     }
 }
 
-impl Add for Ubig {
-    type Output = Self;
+/* Decimal, or hex with a 0x prefix */
+impl FromStr for Ubig {
+    type Err = UbigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (digits, radix) = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(digits) => (digits, 16),
+            None => (s, 10),
+        };
+
+        if digits.is_empty() {
+            return Err(UbigError::EmptyString);
+        }
+
+        let mut limbs = vec![];
+
+        for c in digits.chars() {
+            let digit = c.to_digit(radix).ok_or(UbigError::InvalidDigit)?;
+
+            mul_add_small(&mut limbs, radix as u64, digit as u64);
+        }
+
+        Ok(Self::from_limbs(limbs))
+    }
+}
 
-    fn add(self, rhs: Ubig) -> Self::Output {
-        let length = std::cmp::max(self.0.len(), rhs.0.len());
+impl fmt::Display for Ubig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /* Peel off 19 decimal digits at a time, the most that fit in a limb */
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
 
-        let mut result = Vec::with_capacity(length + 1);
-        let mut carry = 0u16;
+        let mut limbs = self.limbs.clone();
+        let mut chunks = vec![];
 
-        for (a, b) in self
-            .0
-            .into_iter()
-            .chain([0].into_iter().cycle())
-            .zip(rhs.0.into_iter().cycle())
-            .take(length)
-        {
-            let a = a as u16;
-            let b = b as u16;
+        while !limbs.is_empty() {
+            chunks.push(div_rem_small(&mut limbs, CHUNK));
+        }
 
-            let c = a + b + carry;
+        let mut digits = match chunks.pop() {
+            Some(top) => top.to_string(),
+            None => String::from("0"),
+        };
 
-            result.push((c & 0xff) as u8);
-            carry = c.wrapping_shr(8);
+        for chunk in chunks.iter().rev() {
+            digits.push_str(&format!("{:019}", chunk));
         }
 
-        if carry != 0 {
-            result.push(carry as u8);
+        f.pad_integral(true, "", &digits)
+    }
+}
+
+impl fmt::LowerHex for Ubig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits = match self.limbs.last() {
+            Some(top) => format!("{:x}", top),
+            None => String::from("0"),
+        };
+
+        for limb in self.limbs.iter().rev().skip(1) {
+            digits.push_str(&format!("{:016x}", limb));
         }
 
-        Self(result)
+        f.pad_integral(true, "0x", &digits)
     }
 }
 
-impl Mul for Ubig {
-    type Output = Self;
+impl fmt::Debug for Ubig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Ubig({:#x})", self)
+    }
+}
 
-    fn mul(self, rhs: Self) -> Self::Output {
-        let length = std::cmp::max(self.0.len(), rhs.0.len());
+impl Add<&Ubig> for &Ubig {
+    type Output = Ubig;
 
-        let mut work = vec![0u8; 2 * length];
+    fn add(self, rhs: &Ubig) -> Self::Output {
+        let (longer, shorter) = if self.limbs.len() >= rhs.limbs.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
 
-        for (ia, a) in self.0.into_iter().enumerate() {
-            for (ib, b) in rhs.0.iter().enumerate() {
-                let mul = (a as u16) * (*b as u16) + work[ia + ib] as u16;
+        let mut limbs = longer.limbs.clone();
+        add_limbs_inplace(&mut limbs, &shorter.limbs, 0);
 
-                let slot = mul as u8;
-                work[ia + ib] = slot;
+        Ubig::from_limbs(limbs)
+    }
+}
 
-                let mut carry = mul.wrapping_shr(8) as u8;
+impl Sub<&Ubig> for &Ubig {
+    type Output = Ubig;
 
-                for s in (&mut work[ia + ib + 1..]).iter_mut() {
-                    if carry == 0 {
-                        break;
-                    }
+    fn sub(self, rhs: &Ubig) -> Self::Output {
+        self.checked_sub(rhs)
+            .expect("attempt to subtract with overflow")
+    }
+}
 
-                    let sum = *s as u16 + carry as u16;
+impl Mul<&Ubig> for &Ubig {
+    type Output = Ubig;
 
-                    *s = sum as u8;
-                    carry = sum.wrapping_shr(8) as u8;
-                }
-            }
-        }
+    fn mul(self, rhs: &Ubig) -> Self::Output {
+        Ubig::from_limbs(mul_limbs(&self.limbs, &rhs.limbs))
+    }
+}
 
-        while let Some(0) = work.last() {
-            work.pop();
-        }
+impl Div<&Ubig> for &Ubig {
+    type Output = Ubig;
 
-        Self(work)
+    fn div(self, rhs: &Ubig) -> Self::Output {
+        self.div_rem(rhs).0
     }
 }
 
-impl Rem<&Ubig> for Ubig {
-    type Output = Self;
+impl Rem<&Ubig> for &Ubig {
+    type Output = Ubig;
 
     fn rem(self, rhs: &Ubig) -> Self::Output {
-        let mut rem = Self(vec![0u8]);
-
-        for byte in self.0.into_iter().rev() {
-            for i in 0..8 {
-                rem <<= 1;
+        self.div_rem(rhs).1
+    }
+}
 
-                let s = rem.0.first_mut().unwrap();
-                *s |= byte.wrapping_shr(7 - i) & 0x01;
+/* The owned and mixed forms all defer to the reference-reference impls above */
+macro_rules! forward_binary_op {
+    ($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident) => {
+        impl $trait<Ubig> for Ubig {
+            type Output = Ubig;
 
-                if rem >= *rhs {
-                    rem -= rhs;
-                }
+            fn $method(self, rhs: Ubig) -> Self::Output {
+                (&self).$method(&rhs)
             }
         }
 
-        let leading_zeros = rem.0.iter().rev().take_while(|byte| **byte == 0).count();
-
-        rem.0.truncate(rem.0.len() - leading_zeros);
+        impl $trait<&Ubig> for Ubig {
+            type Output = Ubig;
 
-        rem
-    }
-}
+            fn $method(self, rhs: &Ubig) -> Self::Output {
+                (&self).$method(rhs)
+            }
+        }
 
-impl ShlAssign<u32> for Ubig {
-    fn shl_assign(&mut self, rhs: u32) {
-        let mut shift_in_bytes = vec![0u8; rhs as usize / 8];
-        let shift = rhs % 8;
+        impl $trait<Ubig> for &Ubig {
+            type Output = Ubig;
 
-        if shift != 0 {
-            let mut carry = 0u8;
+            fn $method(self, rhs: Ubig) -> Self::Output {
+                self.$method(&rhs)
+            }
+        }
 
-            for byte in self.0.iter_mut() {
-                let new_carry = byte.wrapping_shr(8 - shift);
-                *byte = byte.wrapping_shl(shift) | carry;
-                carry = new_carry;
+        impl $assign_trait<&Ubig> for Ubig {
+            fn $assign_method(&mut self, rhs: &Ubig) {
+                *self = (&*self).$method(rhs);
             }
+        }
 
-            if carry != 0 {
-                self.0.push(carry);
+        impl $assign_trait<Ubig> for Ubig {
+            fn $assign_method(&mut self, rhs: Ubig) {
+                *self = (&*self).$method(&rhs);
             }
         }
+    };
+}
+
+forward_binary_op!(Add, add, AddAssign, add_assign);
+forward_binary_op!(Sub, sub, SubAssign, sub_assign);
+forward_binary_op!(Mul, mul, MulAssign, mul_assign);
+forward_binary_op!(Div, div, DivAssign, div_assign);
+forward_binary_op!(Rem, rem, RemAssign, rem_assign);
 
-        shift_in_bytes.extend_from_slice(&self.0);
+impl ShlAssign<u32> for Ubig {
+    fn shl_assign(&mut self, rhs: u32) {
+        if self.limbs.is_empty() {
+            return;
+        }
 
-        self.0 = shift_in_bytes;
+        let mut limbs = vec![0u64; rhs as usize / 64];
+        limbs.extend(shl_limbs(&self.limbs, rhs % 64));
+
+        normalize(&mut limbs);
+
+        self.limbs = limbs;
+        self.invalidate_bytes();
     }
 }
 
 impl ShrAssign<u32> for Ubig {
     fn shr_assign(&mut self, rhs: u32) {
-        let drop = (rhs / 8) as usize;
-        let shift = rhs % 8;
+        let drop = std::cmp::min(rhs as usize / 64, self.limbs.len());
+        let shift = rhs % 64;
+
+        self.limbs.drain(..drop);
 
         if shift != 0 {
             let mut carry = 0;
 
-            for byte in self.0.iter_mut().rev() {
-                let new_carry = byte.wrapping_shl(8 - shift);
-                *byte = byte.wrapping_shr(shift) | carry;
+            for limb in self.limbs.iter_mut().rev() {
+                let new_carry = *limb << (64 - shift);
+                *limb = *limb >> shift | carry;
                 carry = new_carry;
             }
         }
 
-        let leading_zeros = self.0.iter().rev().take_while(|byte| **byte == 0).count();
+        normalize(&mut self.limbs);
+        self.invalidate_bytes();
+    }
+}
+
+impl Shl<u32> for &Ubig {
+    type Output = Ubig;
+
+    fn shl(self, rhs: u32) -> Self::Output {
+        let mut shifted = self.clone();
+        shifted <<= rhs;
 
-        self.0 = Vec::from(&self.0[drop..self.0.len() - leading_zeros]);
+        shifted
     }
 }
 
-impl SubAssign<&Ubig> for Ubig {
-    fn sub_assign(&mut self, rhs: &Ubig) {
-        let mut loan = 0;
+impl Shl<u32> for Ubig {
+    type Output = Ubig;
 
-        for (byte, sub) in self
-            .0
-            .iter_mut()
-            .zip(rhs.0.iter().chain([0].iter().cycle()))
-        {
-            let mut res = *byte as i32 - loan as i32 - *sub as i32;
-            loan = 0;
+    fn shl(mut self, rhs: u32) -> Self::Output {
+        self <<= rhs;
 
-            if res < 0 {
-                res += 256;
-                loan = 1;
-            }
+        self
+    }
+}
 
-            *byte = res as u8;
-        }
+impl Shr<u32> for &Ubig {
+    type Output = Ubig;
+
+    fn shr(self, rhs: u32) -> Self::Output {
+        let mut shifted = self.clone();
+        shifted >>= rhs;
+
+        shifted
+    }
+}
+
+impl Shr<u32> for Ubig {
+    type Output = Ubig;
+
+    fn shr(mut self, rhs: u32) -> Self::Output {
+        self >>= rhs;
+
+        self
     }
 }
 
 impl PartialEq for Ubig {
     fn eq(&self, other: &Self) -> bool {
-        let length = std::cmp::max(self.0.len(), other.0.len());
+        self.limbs == other.limbs
+    }
+}
 
-        for (a, b) in self
-            .0
-            .iter()
-            .chain([0].iter().cycle())
-            .zip(other.0.iter().chain([0].iter().cycle()))
-            .take(length)
-        {
-            if a != b {
-                return false;
-            }
+impl Eq for Ubig {}
+
+impl PartialOrd for Ubig {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ubig {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_limbs(&self.limbs, &other.limbs)
+    }
+}
+
+/* Returns table[index], reading every entry so the access pattern is independent of index */
+fn select_limbs(table: &[Vec<u64>], index: usize) -> Vec<u64> {
+    let mut selected = vec![0u64; table[0].len()];
+
+    for (i, entry) in table.iter().enumerate() {
+        let difference = (i ^ index) as u64;
+        let mask = ((difference | difference.wrapping_neg()) >> 63).wrapping_sub(1);
+
+        for (limb, entry_limb) in selected.iter_mut().zip(entry.iter()) {
+            *limb |= entry_limb & mask;
         }
+    }
+
+    selected
+}
+
+/* limbs = limbs * multiplier + addend */
+fn mul_add_small(limbs: &mut Vec<u64>, multiplier: u64, addend: u64) {
+    let mut carry = addend;
 
-        true
+    for limb in limbs.iter_mut() {
+        let product = *limb as u128 * multiplier as u128 + carry as u128;
+
+        *limb = product as u64;
+        carry = (product >> 64) as u64;
+    }
+
+    if carry != 0 {
+        limbs.push(carry);
     }
 }
 
-impl PartialOrd for Ubig {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let length = std::cmp::max(self.0.len(), other.0.len());
+/* limbs = limbs / divisor, returning the remainder */
+fn div_rem_small(limbs: &mut Vec<u64>, divisor: u64) -> u64 {
+    let mut rem = 0u128;
 
-        let lhs = LeadingZeroRevIter::new(&self.0, length);
-        let rhs = LeadingZeroRevIter::new(&other.0, length);
+    for limb in limbs.iter_mut().rev() {
+        let numerator = rem << 64 | *limb as u128;
 
-        for (a, b) in lhs.into_iter().zip(rhs.into_iter()) {
-            if a > b {
-                return Some(Ordering::Greater);
-            } else if b > a {
-                return Some(Ordering::Less);
-            }
+        *limb = (numerator / divisor as u128) as u64;
+        rem = numerator % divisor as u128;
+    }
+
+    normalize(limbs);
+
+    rem as u64
+}
+
+fn normalize(limbs: &mut Vec<u64>) {
+    while let Some(0) = limbs.last() {
+        limbs.pop();
+    }
+}
+
+fn limbs_to_bytes(limbs: &[u64]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = limbs.iter().flat_map(|limb| limb.to_le_bytes()).collect();
+
+    while let Some(0) = bytes.last() {
+        bytes.pop();
+    }
+
+    bytes
+}
+
+/* Compares as numbers, ignoring any leading zero limbs */
+fn compare_limbs(a: &[u64], b: &[u64]) -> Ordering {
+    let length = std::cmp::max(a.len(), b.len());
+
+    for i in (0..length).rev() {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+
+        match x.cmp(&y) {
+            Ordering::Equal => continue,
+            ordering => return ordering,
+        }
+    }
+
+    Ordering::Equal
+}
+
+/* a += b << (64 * offset), growing a as needed */
+fn add_limbs_inplace(a: &mut Vec<u64>, b: &[u64], offset: usize) {
+    if a.len() < b.len() + offset {
+        a.resize(b.len() + offset, 0);
+    }
+
+    let mut carry = false;
+
+    for (i, limb) in b.iter().enumerate() {
+        let (sum, c1) = a[i + offset].overflowing_add(*limb);
+        let (sum, c2) = sum.overflowing_add(carry as u64);
+
+        a[i + offset] = sum;
+        carry = c1 || c2;
+    }
+
+    let mut i = b.len() + offset;
+
+    while carry {
+        if i == a.len() {
+            a.push(0);
         }
 
-        Some(Ordering::Equal)
+        let (sum, c) = a[i].overflowing_add(1);
+
+        a[i] = sum;
+        carry = c;
+        i += 1;
     }
 }
 
-struct LeadingZeroRevIter<'a> {
-    body: Rev<std::slice::Iter<'a, u8>>,
-    leading_zeros: usize,
+/* a -= b, where the caller guarantees a >= b */
+fn sub_limbs_inplace(a: &mut [u64], b: &[u64]) {
+    let mut borrow = false;
+
+    for (i, limb) in a.iter_mut().enumerate() {
+        let (difference, b1) = limb.overflowing_sub(b.get(i).copied().unwrap_or(0));
+        let (difference, b2) = difference.overflowing_sub(borrow as u64);
+
+        *limb = difference;
+        borrow = b1 || b2;
+
+        if !borrow && i >= b.len() {
+            break;
+        }
+    }
 }
 
-impl<'a> LeadingZeroRevIter<'a> {
-    fn new(input: &'a [u8], length: usize) -> Self {
-        let leading_zeros = length - input.len();
-        let body = input.iter().rev();
+fn shl_limbs(limbs: &[u64], shift: u32) -> Vec<u64> {
+    let mut shifted = Vec::with_capacity(limbs.len() + 1);
 
-        Self {
-            body,
-            leading_zeros,
+    if shift == 0 {
+        shifted.extend_from_slice(limbs);
+        shifted.push(0);
+
+        return shifted;
+    }
+
+    let mut carry = 0;
+
+    for limb in limbs.iter() {
+        shifted.push(limb << shift | carry);
+        carry = limb >> (64 - shift);
+    }
+
+    shifted.push(carry);
+
+    shifted
+}
+
+fn mul_limbs(a: &[u64], b: &[u64]) -> Vec<u64> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+
+    let half = std::cmp::max(a.len(), b.len()).div_ceil(2);
+
+    if a.len() < KARATSUBA_THRESHOLD
+        || b.len() < KARATSUBA_THRESHOLD
+        || a.len() <= half
+        || b.len() <= half
+    {
+        return schoolbook_mul(a, b);
+    }
+
+    /* a * b = z2 * B^2 + ((a0 + a1)(b0 + b1) - z2 - z0) * B + z0 */
+    let (a0, a1) = a.split_at(half);
+    let (b0, b1) = b.split_at(half);
+
+    let z0 = mul_limbs(a0, b0);
+    let z2 = mul_limbs(a1, b1);
+
+    let mut a_sum = a0.to_vec();
+    add_limbs_inplace(&mut a_sum, a1, 0);
+
+    let mut b_sum = b0.to_vec();
+    add_limbs_inplace(&mut b_sum, b1, 0);
+
+    let mut z1 = mul_limbs(&a_sum, &b_sum);
+    sub_limbs_inplace(&mut z1, &z0);
+    sub_limbs_inplace(&mut z1, &z2);
+
+    let mut product = z0;
+    add_limbs_inplace(&mut product, &z1, half);
+    add_limbs_inplace(&mut product, &z2, 2 * half);
+
+    normalize(&mut product);
+
+    product
+}
+
+fn schoolbook_mul(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut product = vec![0u64; a.len() + b.len()];
+
+    for (i, &a_limb) in a.iter().enumerate() {
+        let mut carry = 0u64;
+
+        for (j, &b_limb) in b.iter().enumerate() {
+            let sum = product[i + j] as u128 + a_limb as u128 * b_limb as u128 + carry as u128;
+            product[i + j] = sum as u64;
+            carry = (sum >> 64) as u64;
         }
+
+        product[i + b.len()] = carry;
     }
+
+    normalize(&mut product);
+
+    product
 }
 
-impl<'a> Iterator for LeadingZeroRevIter<'a> {
-    type Item = u8;
+/* Knuth's algorithm D on normalised limbs; the divisor must have no leading zero limbs */
+fn div_rem_limbs(dividend: &[u64], divisor: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let n = divisor.len();
+
+    if n == 1 {
+        let mut quotient = vec![0u64; dividend.len()];
+        let mut rem = 0u128;
+
+        for (i, limb) in dividend.iter().enumerate().rev() {
+            let numerator = rem << 64 | *limb as u128;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.leading_zeros > 0 {
-            self.leading_zeros -= 1;
-            return Some(0);
+            quotient[i] = (numerator / divisor[0] as u128) as u64;
+            rem = numerator % divisor[0] as u128;
         }
 
-        self.body.next().copied()
+        return (quotient, vec![rem as u64]);
+    }
+
+    let shift = divisor[n - 1].leading_zeros();
+
+    let mut v = shl_limbs(divisor, shift);
+    v.truncate(n);
+
+    let mut u = shl_limbs(dividend, shift);
+
+    if u.len() < n + 1 {
+        u.resize(n + 1, 0);
+    }
+
+    let m = u.len() - n - 1;
+    let mut quotient = vec![0u64; m + 1];
+
+    let v_top = v[n - 1] as u128;
+    let v_next = v[n - 2] as u128;
+
+    for j in (0..=m).rev() {
+        let numerator = (u[j + n] as u128) << 64 | u[j + n - 1] as u128;
+
+        let mut q_hat = numerator / v_top;
+        let mut r_hat = numerator % v_top;
+
+        while q_hat >> 64 != 0 || q_hat * v_next > (r_hat << 64 | u[j + n - 2] as u128) {
+            q_hat -= 1;
+            r_hat += v_top;
+
+            if r_hat >> 64 != 0 {
+                break;
+            }
+        }
+
+        let mut borrow = false;
+        let mut carry = 0u64;
+
+        for i in 0..n {
+            let product = q_hat * v[i] as u128 + carry as u128;
+            carry = (product >> 64) as u64;
+
+            let (difference, b1) = u[i + j].overflowing_sub(product as u64);
+            let (difference, b2) = difference.overflowing_sub(borrow as u64);
+
+            u[i + j] = difference;
+            borrow = b1 || b2;
+        }
+
+        let (difference, b1) = u[j + n].overflowing_sub(carry);
+        let (difference, b2) = difference.overflowing_sub(borrow as u64);
+
+        u[j + n] = difference;
+
+        if b1 || b2 {
+            q_hat -= 1;
+
+            let mut carry = false;
+
+            for i in 0..n {
+                let (sum, c1) = u[i + j].overflowing_add(v[i]);
+                let (sum, c2) = sum.overflowing_add(carry as u64);
+
+                u[i + j] = sum;
+                carry = c1 || c2;
+            }
+
+            u[j + n] = u[j + n].wrapping_add(carry as u64);
+        }
+
+        quotient[j] = q_hat as u64;
+    }
+
+    u.truncate(n);
+
+    let mut rem = u;
+
+    if shift != 0 {
+        let mut carry = 0;
+
+        for limb in rem.iter_mut().rev() {
+            let new_carry = *limb << (64 - shift);
+            *limb = *limb >> shift | carry;
+            carry = new_carry;
+        }
+    }
+
+    normalize(&mut quotient);
+    normalize(&mut rem);
+
+    (quotient, rem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dh::DhGroup;
+    use crate::urandom;
+
+    fn hex(s: &str) -> Ubig {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn division_add_back_step() {
+        /* Knuth's q-hat overshoots by one here and the divisor has to be added back */
+        let cases = [
+            (
+                "0x7fffffffffffffff800000000000000000000000000000000000000000000000",
+                "0x800000000000000000000000000000000000000000000001",
+                "0xfffffffffffffffe",
+                "0x7fffffffffffffffffffffffffffffff0000000000000002",
+            ),
+            /* Needs the q-hat correction loop as well as the add-back */
+            (
+                "0xfffffffffffffffc0000000000000000ffffffffffffffff0000000000000000",
+                "0x8000000000000006ffffffffffffffff6c70338dd2a4fa3c",
+                "0x1ffffffffffffffdc",
+                "0xfe271f98e45ab60b723fc73ff19f333070",
+            ),
+        ];
+
+        for (dividend, divisor, quotient, remainder) in cases {
+            let (dividend, divisor) = (hex(dividend), hex(divisor));
+            let (q, r) = dividend.div_rem(&divisor);
+
+            assert_eq!(q, hex(quotient));
+            assert_eq!(r, hex(remainder));
+            assert_eq!(&q * &divisor + &r, dividend);
+        }
+    }
+
+    #[test]
+    fn division_identity_on_random_operands() {
+        for _ in 0..200 {
+            let dividend = Ubig::from(urandom::bytes(urandom::range(1, 80) as usize).as_ref());
+            let mut divisor = Ubig::from(urandom::bytes(urandom::range(1, 40) as usize).as_ref());
+
+            if !divisor.not_zero() {
+                divisor = Ubig::from(1u64);
+            }
+
+            let (q, r) = dividend.div_rem(&divisor);
+
+            assert!(r < divisor);
+            assert_eq!(&q * &divisor + &r, dividend);
+        }
+    }
+
+    #[test]
+    fn gcd_and_bezout() {
+        assert_eq!(
+            Ubig::gcd(Ubig::from(0u64), Ubig::from(12u64)),
+            Ubig::from(12u64)
+        );
+        assert_eq!(
+            Ubig::gcd(Ubig::from(12u64), Ubig::from(0u64)),
+            Ubig::from(12u64)
+        );
+        assert_eq!(
+            Ubig::gcd(Ubig::from(240u64), Ubig::from(46u64)),
+            Ubig::from(2u64)
+        );
+
+        let shared = Ubig::from(1u64) << 64;
+        assert_eq!(
+            Ubig::gcd(&shared * &Ubig::from(3u64), &shared * &Ubig::from(5u64)),
+            shared
+        );
+
+        let pairs = [
+            (Ubig::from(240u64), Ubig::from(46u64)),
+            (Ubig::from(46u64), Ubig::from(240u64)),
+            (Ubig::from(17u64), Ubig::from(1u64)),
+            (
+                Ubig::from(3u64).pow(200),
+                (Ubig::from(1u64) << 150) + Ubig::from(12345u64),
+            ),
+            (
+                hex("0xfffffffffffffffc0000000000000000ffffffffffffffff0000000000000000"),
+                hex("0x8000000000000006ffffffffffffffff6c70338dd2a4fa3c"),
+            ),
+        ];
+
+        for (a, b) in pairs {
+            let (g, x, y) = Ubig::extended_gcd(a.clone(), b.clone());
+
+            assert_eq!(g, Ubig::gcd(a.clone(), b.clone()));
+            assert!(!(&a % &g).not_zero() && !(&b % &g).not_zero());
+
+            /* a x = g (mod b) and b y = g (mod a) */
+            assert_eq!(&a * &x % &b, &g % &b);
+            assert_eq!(&b * &y % &a, &g % &a);
+        }
+    }
+
+    #[test]
+    fn modular_inverses() {
+        let p = (Ubig::from(1u64) << 127) - Ubig::from(1u64);
+        let a = Ubig::from(3u64).pow(100);
+        let inverse = Ubig::modinv(a.clone(), p.clone()).unwrap();
+
+        assert!((&a * &inverse % &p).is_one());
+        assert_eq!(
+            Ubig::modinv(Ubig::from(3u64), Ubig::from(7u64)),
+            Some(Ubig::from(5u64))
+        );
+
+        assert_eq!(Ubig::modinv(Ubig::from(6u64), Ubig::from(9u64)), None);
+        assert_eq!(Ubig::modinv(Ubig::from(0u64), Ubig::from(9u64)), None);
+        assert_eq!(
+            Ubig::modinv(&p * &Ubig::from(2u64), &p * &Ubig::from(3u64)),
+            None
+        );
+        assert_eq!(Ubig::modinv(Ubig::from(1u64), Ubig::from(0u64)), None);
+        assert_eq!(Ubig::modinv(Ubig::from(1u64), Ubig::from(1u64)), None);
+    }
+
+    #[test]
+    fn decimal_round_trips() {
+        let cases = [
+            "0",
+            "1",
+            "9999999999999999999",
+            "10000000000000000000",
+            "18446744073709551615",
+            "18446744073709551616",
+            "1606938044258990275541962092341162602522202993782792835301376",
+        ];
+
+        for case in cases {
+            let value: Ubig = case.parse().unwrap();
+            assert_eq!(value.to_string(), case);
+        }
+
+        assert_eq!("000123".parse::<Ubig>().unwrap().to_string(), "123");
+        assert_eq!(format!("{:>5}", Ubig::from(42u64)), "   42");
+        assert_eq!(
+            "1606938044258990275541962092341162602522202993782792835301376"
+                .parse::<Ubig>()
+                .unwrap(),
+            Ubig::from(1u64) << 200
+        );
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let cases = [
+            ("0x0", Ubig::from(0u64)),
+            ("0x1", Ubig::from(1u64)),
+            ("0xffffffffffffffff", Ubig::from(u64::MAX)),
+            ("0x10000000000000000", Ubig::from(1u128 << 64)),
+            (
+                "0x5a4653ca673768565b41f775d6947d55cf3813d1",
+                Ubig::from(3u64).pow(100),
+            ),
+        ];
+
+        for (case, expected) in cases {
+            let value: Ubig = case.parse().unwrap();
+
+            assert_eq!(value, expected);
+            assert_eq!(format!("{:#x}", value), case);
+            assert_eq!(format!("0x{:x}", value), case);
+        }
+
+        assert_eq!("0XABCdef".parse::<Ubig>().unwrap(), Ubig::from(0xabcdefu64));
+        assert_eq!(format!("{:?}", Ubig::from(255u64)), "Ubig(0xff)");
+    }
+
+    #[test]
+    fn parse_rejects_bad_input() {
+        assert!(matches!("".parse::<Ubig>(), Err(UbigError::EmptyString)));
+        assert!(matches!("0x".parse::<Ubig>(), Err(UbigError::EmptyString)));
+
+        for case in ["12a", "0xg", "-1", " 1", "1 ", "+1", "0x-1", "1_000"] {
+            assert!(
+                matches!(case.parse::<Ubig>(), Err(UbigError::InvalidDigit)),
+                "{}",
+                case
+            );
+        }
+    }
+
+    #[test]
+    fn narrowing_conversions() {
+        assert_eq!(u64::try_from(&Ubig::from(0u64)).unwrap(), 0);
+        assert_eq!(u64::try_from(&Ubig::from(u64::MAX)).unwrap(), u64::MAX);
+        assert!(matches!(
+            u64::try_from(&Ubig::from(1u128 << 64)),
+            Err(UbigError::Overflow)
+        ));
+
+        assert_eq!(u128::try_from(&Ubig::from(u128::MAX)).unwrap(), u128::MAX);
+        assert_eq!(u128::try_from(&Ubig::from(7u64)).unwrap(), 7);
+        assert!(matches!(
+            u128::try_from(&(Ubig::from(1u64) << 128)),
+            Err(UbigError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(Ubig::from(0u64).pow(0), Ubig::from(1u64));
+        assert_eq!(Ubig::from(0u64).pow(5), Ubig::from(0u64));
+        assert_eq!(Ubig::from(7u64).pow(1), Ubig::from(7u64));
+        assert_eq!(Ubig::from(2u64).pow(200), Ubig::from(1u64) << 200);
+        assert_eq!(Ubig::from(10u64).pow(38), Ubig::from(10u128.pow(38)));
+        assert_eq!(
+            Ubig::from(3u64).pow(100).to_string(),
+            "515377520732011331036461129765621272702107522001"
+        );
+    }
+
+    #[test]
+    fn montgomery_reduces_wide_operands() {
+        let modulus: Ubig = "0xfffffffffffffffffffffffffffffffeffffffffffffffff"
+            .parse()
+            .unwrap();
+        let montgomery = Montgomery::new(&modulus);
+
+        let a: Ubig = "123456789012345678901234567890".parse().unwrap();
+        let b = &(&modulus * &modulus) + &a;
+
+        let a_mont = montgomery.to_montgomery(&a);
+        let b_mont = montgomery.to_montgomery(&b);
+
+        assert_eq!(a_mont, b_mont);
+        assert_eq!(montgomery.to_standard(&(&a_mont + &modulus)), a);
+        assert_eq!(
+            montgomery.to_standard(&montgomery.mul(&(&a_mont + &(&modulus * &modulus)), &b_mont)),
+            &(&a * &a) % &modulus
+        );
+    }
+
+    /* The constant-time path must do the same amount of work whatever the exponent's bits are */
+    #[test]
+    fn constant_time_modexp_operation_counts() {
+        let p = DhGroup::modp_1536().p().clone();
+        let g = Ubig::from([2u8].as_ref());
+
+        let exponents = [
+            vec![0u8; 32],
+            vec![0xff; 32],
+            vec![0x01],
+            [vec![0u8; 31], vec![0x80]].concat(),
+            urandom::bytes(32),
+            urandom::bytes(32),
+        ];
+
+        let counts: Vec<u64> = exponents
+            .iter()
+            .map(|exponent| {
+                let exponent = Ubig::from(exponent.as_ref());
+                let montgomery = Montgomery::new(&p);
+
+                assert_eq!(
+                    montgomery.modexp_constant_time(&g, &exponent, 256),
+                    Ubig::modexp(g.clone(), exponent, p.clone())
+                );
+
+                montgomery.multiplications()
+            })
+            .collect();
+
+        assert!(counts.iter().all(|count| *count == counts[0]));
+    }
+
+    fn assert_root(x: &Ubig, n: u32, root: &Ubig, exact: bool) {
+        assert_eq!(x.nth_root(n), (root.clone(), exact));
+    }
+
+    #[test]
+    fn roots_of_perfect_powers_and_neighbours() {
+        let one = Ubig::from(1u64);
+
+        /* Roots of about bits / n bits, so every power is 2048 to 4096 bits long */
+        for bits in [2200u32, 3072, 4096] {
+            for n in [2u32, 3, 5, 17] {
+                let root = Ubig::from(0xdeadbeefu64).pow(bits / (32 * n));
+                let power = root.pow(n);
+
+                assert!((2048..=4096).contains(&power.bit_length()));
+
+                assert_root(&power, n, &root, true);
+                assert_root(&(&power + &one), n, &root, false);
+                assert_root(&(&power - &one), n, &(&root - &one), false);
+            }
+        }
+
+        let seven = Ubig::from(7u64);
+
+        assert_eq!(
+            (&seven.pow(1500) + &Ubig::from(12345u64)).isqrt(),
+            (seven.pow(750), false)
+        );
+        assert_eq!(seven.pow(1500).icbrt(), (seven.pow(500), true));
+    }
+
+    #[test]
+    fn roots_of_zero_and_one() {
+        for n in [1u32, 2, 3, 64, 1000] {
+            assert_root(&Ubig::from(0u64), n, &Ubig::from(0u64), true);
+            assert_root(&Ubig::from(1u64), n, &Ubig::from(1u64), true);
+        }
+    }
+
+    #[test]
+    fn roots_of_random_large_numbers() {
+        let one = Ubig::from(1u64);
+
+        for bytes in [256, 300, 384, 512] {
+            let x = Ubig::from(urandom::bytes(bytes).as_ref());
+
+            for n in [2u32, 3, 7, 64] {
+                let (root, exact) = x.nth_root(n);
+
+                assert!(root.pow(n) <= x);
+                assert!((&root + &one).pow(n) > x);
+                assert_eq!(exact, root.pow(n) == x);
+            }
+        }
+    }
+
+    #[test]
+    fn conditional_swap() {
+        let small = Ubig::from(5u64);
+        let large: Ubig = "0x1234567890abcdef1234567890abcdef1234567890"
+            .parse()
+            .unwrap();
+
+        let mut a = small.clone();
+        let mut b = large.clone();
+
+        Ubig::conditional_swap(&mut a, &mut b, false);
+        assert_eq!((&a, &b), (&small, &large));
+
+        Ubig::conditional_swap(&mut a, &mut b, true);
+        assert_eq!((&a, &b), (&large, &small));
+        assert_eq!(b.as_ref(), [5]);
     }
 }
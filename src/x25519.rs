@@ -0,0 +1,200 @@
+use super::curve25519::FieldElement;
+use super::ubig::Ubig;
+use super::urandom;
+
+pub const X25519_BASEPOINT: [u8; 32] = [
+    9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+#[derive(Debug)]
+pub enum X25519Error {
+    LowOrderPoint,
+}
+
+/* Clear the cofactor bits and fix the top bit, as in RFC 7748 */
+pub fn clamp(scalar: &[u8; 32]) -> [u8; 32] {
+    let mut clamped = *scalar;
+
+    clamped[0] &= 248;
+    clamped[31] &= 127;
+    clamped[31] |= 64;
+
+    clamped
+}
+
+pub fn x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    let k = Ubig::from(clamp(scalar).as_ref());
+    let x1 = FieldElement::from_bytes(u);
+
+    let a24 = FieldElement::new(Ubig::from(121665u64));
+
+    let mut x2 = FieldElement::one();
+    let mut z2 = FieldElement::zero();
+    let mut x3 = x1.clone();
+    let mut z3 = FieldElement::one();
+    let mut swap = false;
+
+    for t in (0..255).rev() {
+        let bit = k.bit(t);
+
+        swap ^= bit;
+        FieldElement::conditional_swap(&mut x2, &mut x3, swap);
+        FieldElement::conditional_swap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = &x2 + &z2;
+        let aa = a.square();
+        let b = &x2 - &z2;
+        let bb = b.square();
+        let e = &aa - &bb;
+        let c = &x3 + &z3;
+        let d = &x3 - &z3;
+        let da = &d * &a;
+        let cb = &c * &b;
+
+        x3 = (&da + &cb).square();
+        z3 = &x1 * &(&da - &cb).square();
+        x2 = &aa * &bb;
+        z2 = &e * &(&aa + &(&a24 * &e));
+    }
+
+    FieldElement::conditional_swap(&mut x2, &mut x3, swap);
+    FieldElement::conditional_swap(&mut z2, &mut z3, swap);
+
+    (&x2 * &z2.invert()).to_bytes()
+}
+
+pub fn x25519_private() -> [u8; 32] {
+    let mut private = [0u8; 32];
+    private.copy_from_slice(&urandom::bytes(32));
+
+    private
+}
+
+pub fn x25519_public(private: &[u8; 32]) -> [u8; 32] {
+    x25519(private, &X25519_BASEPOINT)
+}
+
+/* An all-zero result means the peer sent a point of small order */
+pub fn x25519_shared_secret(
+    private: &[u8; 32],
+    peer_public: &[u8; 32],
+) -> Result<[u8; 32], X25519Error> {
+    let shared = x25519(private, peer_public);
+
+    if shared.iter().fold(0u8, |acc, byte| acc | byte) == 0 {
+        return Err(X25519Error::LowOrderPoint);
+    }
+
+    Ok(shared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(s: &str) -> [u8; 32] {
+        hex::decode(s).unwrap().try_into().unwrap()
+    }
+
+    /* RFC 7748, section 5.2 */
+    #[test]
+    fn rfc7748_scalar_multiplication() {
+        assert_eq!(
+            x25519(
+                &bytes("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4"),
+                &bytes("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c"),
+            ),
+            bytes("c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552")
+        );
+
+        assert_eq!(
+            x25519(
+                &bytes("4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d"),
+                &bytes("e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493"),
+            ),
+            bytes("95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957")
+        );
+    }
+
+    fn iterate(iterations: usize) -> [u8; 32] {
+        let mut k = X25519_BASEPOINT;
+        let mut u = X25519_BASEPOINT;
+
+        for _ in 0..iterations {
+            let next = x25519(&k, &u);
+            u = k;
+            k = next;
+        }
+
+        k
+    }
+
+    #[test]
+    fn rfc7748_iterated_once() {
+        assert_eq!(
+            iterate(1),
+            bytes("422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079")
+        );
+    }
+
+    /* Takes a while without optimizations, run with --ignored */
+    #[test]
+    #[ignore]
+    fn rfc7748_iterated_1000_times() {
+        assert_eq!(
+            iterate(1000),
+            bytes("684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51")
+        );
+    }
+
+    /* RFC 7748, section 6.1 */
+    #[test]
+    fn rfc7748_diffie_hellman() {
+        let alice = bytes("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = bytes("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+
+        let alice_public = x25519_public(&alice);
+        let bob_public = x25519_public(&bob);
+
+        assert_eq!(
+            alice_public,
+            bytes("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            bob_public,
+            bytes("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+        );
+
+        let shared = bytes("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+
+        assert_eq!(x25519_shared_secret(&alice, &bob_public).unwrap(), shared);
+        assert_eq!(x25519_shared_secret(&bob, &alice_public).unwrap(), shared);
+    }
+
+    #[test]
+    fn non_canonical_u_is_reduced() {
+        let scalar = bytes("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4");
+
+        /* p + 9, and 9 with the ignored top bit set */
+        let p_plus_nine = bytes("f6ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+        let mut top_bit = X25519_BASEPOINT;
+        top_bit[31] |= 0x80;
+
+        let expected = x25519(&scalar, &X25519_BASEPOINT);
+
+        assert_eq!(x25519(&scalar, &p_plus_nine), expected);
+        assert_eq!(x25519(&scalar, &top_bit), expected);
+    }
+
+    #[test]
+    fn low_order_points_are_rejected() {
+        let private = x25519_private();
+
+        let mut one = [0u8; 32];
+        one[0] = 1;
+
+        assert!(x25519_shared_secret(&private, &[0; 32]).is_err());
+        assert!(x25519_shared_secret(&private, &one).is_err());
+    }
+}